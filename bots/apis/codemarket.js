@@ -6,6 +6,7 @@
  */
 
 const BASE_URL = 'http://[::1]:8000'; // JS localhost url
+const ADMIN_SECRET = ''; // Only needed for operator requests
 
 /**
  * Base API GET request to get data from the market
@@ -33,13 +34,282 @@ exports.base_api_post = async function(url, data) {
     const got = require('got');
 
     try {
-        const response = await got.post(BASE_URL.concat(url), {form: data});
+        const response = await got.post(BASE_URL.concat(url), {form: data, headers: {'x-admin-secret': ADMIN_SECRET}});
         return JSON.parse(response.body);
     } catch (error) {
         return JSON.parse(error.response.body);
     }
 };
 
+/**
+ * Base API DELETE request to remove data from the market
+ * @param {string} url - The request extension
+ * @returns {object} The return object
+ */
+exports.base_api_delete = async function(url) {
+    const got = require('got');
+
+    try {
+        const response = await got.delete(BASE_URL.concat(url), {headers: {'x-admin-secret': ADMIN_SECRET}});
+        return JSON.parse(response.body);
+    } catch (error) {
+        return JSON.parse(error.response.body);
+    }
+};
+
+/**
+ * Accept a supply contract proposed to you
+ * @param {integer} contract - The id of the contract
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains the contract id or errors
+ */
+exports.accept_contract = async function(contract, uuid) {
+    payload = { contract, uuid };
+    return await exports.base_api_post('/api/contracts/accept', payload);
+};
+
+/**
+ * Change the bits of a vendor and the units of an item it holds, needs
+ * ADMIN_SECRET
+ * @param {string} vendor - Name of the vendor
+ * @param {float} bits - The change in bits
+ * @param {string} item - The name of the item to change
+ * @param {integer} count - The change in units of the item
+ * @returns {object} Contains the new bits and units or errors
+ */
+exports.admin_adjust = async function(vendor, bits = undefined, item = undefined, count = undefined) {
+    payload = { vendor };
+    if (bits !== undefined) { payload.bits = bits; }
+    if (item !== undefined && count !== undefined) { payload.item = item; payload.count = count; }
+    return await exports.base_api_post('/admin/adjust', payload);
+};
+
+/**
+ * Stop a vendor from trading, or let it trade again, needs ADMIN_SECRET
+ * @param {string} vendor - Name of the vendor
+ * @param {boolean} frozen - False to unfreeze the vendor
+ * @returns {object} Contains success or errors
+ */
+exports.admin_freeze = async function(vendor, frozen = true) {
+    payload = { vendor, frozen };
+    return await exports.base_api_post('/admin/freeze', payload);
+};
+
+/**
+ * Add new units of an item to the store of a vendor, needs ADMIN_SECRET
+ * @param {string} vendor - Name of the vendor
+ * @param {string} item - The name of the item
+ * @param {integer} count - The number of units to add
+ * @param {float} price - The price of the item if the vendor does not have it
+ * @returns {object} Contains the units the vendor holds or errors
+ */
+exports.admin_inject = async function(vendor, item, count, price) {
+    payload = { vendor, item, count, price };
+    return await exports.base_api_post('/admin/inject', payload);
+};
+
+/**
+ * Revoke the uuid of a vendor, needs ADMIN_SECRET
+ * @param {string} vendor - Name of the vendor
+ * @returns {object} Contains success or errors
+ */
+exports.admin_kick = async function(vendor) {
+    return await exports.base_api_post('/admin/kick', { vendor });
+};
+
+/**
+ * Pause all trading in the market, needs ADMIN_SECRET
+ * @returns {object} Contains success or errors
+ */
+exports.admin_pause = async function() {
+    return await exports.base_api_post('/admin/pause', {});
+};
+
+/**
+ * Start the market over from its config or scenario, needs ADMIN_SECRET
+ * @returns {object} Contains success or errors
+ */
+exports.admin_reset = async function() {
+    return await exports.base_api_post('/admin/reset', {});
+};
+
+/**
+ * Resume trading in the market, needs ADMIN_SECRET
+ * @returns {object} Contains success or errors
+ */
+exports.admin_resume = async function() {
+    return await exports.base_api_post('/admin/resume', {});
+};
+
+/**
+ * Buy offered shares of a vendor from a shareholder
+ * @param {string} issuer - Name of the vendor whose shares are bought
+ * @param {integer} count - The number of shares to buy
+ * @param {string} from - Name of the shareholder selling the shares
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains purchase receipt or errors
+ */
+exports.buy_shares = async function(issuer, count, from, uuid) {
+    payload = { issuer, count, from, uuid };
+    return await exports.base_api_post('/api/shares/buy', payload);
+};
+
+/**
+ * Borrow units of an item to sell short
+ * @param {string} item - The name of the item
+ * @param {integer} count - Amount to borrow
+ * @param {string} from - Name of the vendor to borrow from, empty for the pool
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains the loan id, collateral and fee or errors
+ */
+exports.borrow = async function(item, count, from, uuid) {
+    payload = { item, count, from, uuid };
+    return await exports.base_api_post('/api/borrow', payload);
+};
+
+/**
+ * Buy an intel report on a vendor or an item, for markets with limited
+ * visibility
+ * @param {string} uuid - Your UUID to pay for the report
+ * @param {string} vendor - Optional name of the vendor to report on
+ * @param {string} item - Optional name of the item to report on
+ * @returns {object} Contains the names, prices and stock in the report or errors
+ */
+exports.buy_intel = async function(uuid, vendor = undefined, item = undefined) {
+    payload = { uuid };
+    if (vendor !== undefined) { payload.vendor = vendor; }
+    if (item !== undefined) { payload.item = item; }
+    return await exports.base_api_post('/api/intel', payload);
+};
+
+/**
+ * Withdraw a supply contract that has not been accepted yet
+ * @param {integer} contract - The id of the contract
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains the contract id or errors
+ */
+exports.cancel_contract = async function(contract, uuid) {
+    payload = { contract, uuid };
+    return await exports.base_api_post('/api/contracts/cancel', payload);
+};
+
+/**
+ * Cancel a held escrow, refunding the buyer
+ * @param {integer} escrow - The id of the escrow
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains cancellation receipt or errors
+ */
+exports.cancel_escrow = async function(escrow, uuid) {
+    payload = { escrow, uuid };
+    return await exports.base_api_post('/api/escrow/cancel', payload);
+};
+
+/**
+ * Create a new market with its own ledger and vendors. Append the returned
+ * market url to BASE_URL to trade in it
+ * @param {string} market - The id of the new market
+ * @param {string} config - TOML overriding the server config for the market
+ * @returns {object} Contains the url of the market or errors
+ */
+exports.create_market = async function(market, config = '') {
+    payload = { id: market, config };
+    return await exports.base_api_post('/markets', payload);
+};
+
+/**
+ * Start a tournament between bots, each match played in its own market
+ * @param {string} tournament - The id of the tournament
+ * @param {string} entrants - Comma separated vendor names of the bots
+ * @param {string} format - Either round_robin or swiss
+ * @param {integer} rounds - The number of Swiss rounds
+ * @param {string} config - TOML overriding the server config for every match
+ * @returns {object} Contains the number of rounds or errors
+ */
+exports.create_tournament = async function(tournament, entrants, format = 'round_robin', rounds = undefined, config = '') {
+    payload = { id: tournament, entrants, format, config };
+    if (rounds !== undefined) { payload.rounds = rounds; }
+    return await exports.base_api_post('/tournament', payload);
+};
+
+/**
+ * Delete a market, the main market cannot be deleted
+ * @param {string} market - The id of the market
+ * @returns {object} Contains success or errors
+ */
+exports.delete_market = async function(market) {
+    return await exports.base_api_delete('/markets/'.concat(market));
+};
+
+/**
+ * Get every supply contract you are buying or selling in
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} A list of contracts
+ */
+exports.get_contracts = async function(uuid) {
+    payload = { uuid };
+    return await exports.base_api_post('/api/contracts', payload);
+};
+
+/**
+ * Get the items in each category
+ * @returns {object} A list of item names for each category
+ */
+exports.get_categories = async function() {
+    return await exports.base_api_get('/api/categories');
+};
+
+/**
+ * Get the ledger entries of your vendor, or every entry with a spectator token
+ * @param {string} uuid - Your UUID or spectator token for verification
+ * @returns {object} A list of ledger entries
+ */
+exports.get_entries = async function(uuid) {
+    payload = { uuid };
+    return await exports.base_api_post('/api/entries', payload);
+};
+
+/**
+ * Get every escrow you are buying or selling in
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} A list of escrow
+ */
+exports.get_escrow = async function(uuid) {
+    payload = { uuid };
+    return await exports.base_api_post('/api/escrow', payload);
+};
+
+/**
+ * Get every market event that has fired
+ * @returns {object} A list of market events and whether they are active
+ */
+exports.get_events = async function() {
+    return await exports.base_api_get('/api/events');
+};
+
+/**
+ * Get every trading halt still in effect
+ * @returns {object} A list of halted items and when they resume
+ */
+exports.get_halts = async function() {
+    return await exports.base_api_get('/api/halts');
+};
+
+/**
+ * Get the price index history of the market and of each category
+ * @returns {object} A list of index values for the market and each category
+ */
+exports.get_indices = async function() {
+    return await exports.base_api_get('/api/indices');
+};
+
+/**
+ * Get the vendor rankings and the history of rank changes
+ * @returns {object} The ranked vendors and rank changes by version
+ */
+exports.get_leaderboard = async function() {
+    return await exports.base_api_get('/api/leaderboard');
+};
+
 /**
  * Get the current ledger state
  * @param {string} uuid - Your UUID for verification
@@ -50,6 +320,114 @@ exports.get_ledger_state = async function(uuid) {
     return await exports.base_api_post('/api/ledger_state', payload);
 };
 
+/**
+ * Get all open loans and lending offers
+ * @returns {object} Lists of open loans and lending offers
+ */
+exports.get_loans = async function() {
+    return await exports.base_api_get('/api/loans');
+};
+
+/**
+ * Get every market on the server
+ * @returns {object} Contains the vendors, version and session phase by market id
+ */
+exports.get_markets = async function() {
+    return await exports.base_api_get('/markets');
+};
+
+/**
+ * Get the price of buying a number of units from a vendor
+ * @param {string} item - The name of the item
+ * @param {integer} count - Amount to quote
+ * @param {string} from - Name of the vendor to quote
+ * @returns {object} The total, effective unit price and stock or errors
+ */
+exports.get_quote = async function(item, count, from) {
+    return await exports.base_api_get(`/api/quote?item=${item}&count=${count}&from=${from}`);
+};
+
+/**
+ * Get the net worth of every vendor
+ * @returns {object} A mapping from vendor names to net worth
+ */
+exports.get_net_worth = async function() {
+    return await exports.base_api_get('/api/net_worth');
+};
+
+/**
+ * Get the progress of every vendor on its objectives
+ * @returns {object} A list of objective progress, final once the session ends
+ */
+exports.get_objectives = async function() {
+    return await exports.base_api_get('/api/objectives');
+};
+
+/**
+ * Get the vendors and price history as they were at a ledger version
+ * @param {integer} version - The ledger version to replay
+ * @returns {object} Contains the bits and units of each vendor and price history
+ */
+exports.get_replay = async function(version) {
+    return await exports.base_api_get('/api/replay?version='.concat(version));
+};
+
+/**
+ * Get the reputation score and track record of every vendor
+ * @returns {object} The reputation of each vendor by name
+ */
+exports.get_reputation = async function() {
+    return await exports.base_api_get('/api/reputation');
+};
+
+/**
+ * Get the phase and limits of the game session
+ * @returns {object} The session phase, limits and final standings
+ */
+exports.get_session = async function() {
+    return await exports.base_api_get('/api/session');
+};
+
+/**
+ * Get every shareholding, share offer and last traded share price
+ * @returns {object} Holdings, offers and prices
+ */
+exports.get_shares = async function() {
+    return await exports.base_api_get('/api/shares');
+};
+
+/**
+ * Get the vendors ranked by net worth, final once the session has ended
+ * @returns {object} A list of standings
+ */
+exports.get_standings = async function() {
+    return await exports.base_api_get('/api/standings');
+};
+
+/**
+ * Get every team with its members, treasury and combined net worth
+ * @returns {object} Contains the members, bits and net worth of each team
+ */
+exports.get_teams = async function() {
+    return await exports.base_api_get('/api/teams');
+};
+
+/**
+ * Get the current tournament and the ratings of every bot
+ * @returns {object} Contains the matches, points and ratings
+ */
+exports.get_tournament = async function() {
+    return await exports.base_api_get('/api/tournament');
+};
+
+/**
+ * Get the current turn and the results of the last resolved turn
+ * @returns {object} Contains the turn number, seconds left and last results
+ */
+exports.get_turn = async function() {
+    return await exports.base_api_get('/api/turn');
+};
+
 /**
  * Get a list of registered vendors
  * @returns {object} A list of currently registered vendors
@@ -66,16 +444,108 @@ exports.get_vendor_urls = async function() {
     return await exports.base_api_get('/api/vendor_urls');
 };
 
+/**
+ * Issue new shares of your vendor and offer them for sale
+ * @param {integer} count - The number of shares to issue
+ * @param {float} price - The price per share
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains issue receipt or errors
+ */
+exports.issue_shares = async function(count, price, uuid) {
+    payload = { count, price, uuid };
+    return await exports.base_api_post('/api/shares/issue', payload);
+};
+
+/**
+ * Join a team and share its treasury until the session ends, an empty team
+ * leaves your team
+ * @param {string} team - The name of the team
+ * @param {string} uuid - Your UUID for verification
+ * @param {string} token - The join token of an existing team
+ * @returns {object} Contains success and the team's join token or errors
+ */
+exports.join_team = async function(team, uuid, token = '') {
+    payload = { team, token, uuid };
+    return await exports.base_api_post('/api/team/join', payload);
+};
+
+/**
+ * Offer stored units of an item for other vendors to borrow
+ * @param {string} item - The name of the item
+ * @param {integer} count - Amount to offer, zero withdraws the offer
+ * @param {float} rate - Fee charged as a fraction of the loan value
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains lending receipt or errors
+ */
+exports.lend = async function(item, count, rate, uuid) {
+    payload = { item, count, rate, uuid };
+    return await exports.base_api_post('/api/lend', payload);
+};
+
+/**
+ * Load a scenario into a market, replacing its whole state
+ * @param {string} scenario - The scenario TOML, see scenarios/oldies.toml
+ * @param {string} market - The id of the market, the main market if empty
+ * @returns {object} Contains the number of vendors or errors
+ */
+exports.load_scenario = async function(scenario, market = '') {
+    payload = { scenario, market };
+    return await exports.base_api_post('/scenario', payload);
+};
+
+/**
+ * Offer shares you hold for sale
+ * @param {string} issuer - Name of the vendor whose shares are offered
+ * @param {integer} count - The number of shares to offer, zero withdraws
+ * @param {float} price - The price per share
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains offer receipt or errors
+ */
+exports.offer_shares = async function(issuer, count, price, uuid) {
+    payload = { issuer, count, price, uuid };
+    return await exports.base_api_post('/api/shares/offer', payload);
+};
+
+/**
+ * Pay a dividend per share to every other holder of your shares
+ * @param {float} amount - The bits paid per share
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains the total paid or errors
+ */
+exports.pay_dividend = async function(amount, uuid) {
+    payload = { amount, uuid };
+    return await exports.base_api_post('/api/shares/dividend', payload);
+};
+
+/**
+ * Propose a recurring supply contract to a seller
+ * @param {string} item - The name of the item
+ * @param {integer} count - Units delivered each time
+ * @param {float} price - Price paid per unit delivered
+ * @param {integer} every - Length of the interval between deliveries
+ * @param {string} interval - One of versions or seconds
+ * @param {integer} deliveries - Number of deliveries before the contract ends
+ * @param {float} penalty - Bits the seller pays you per missing unit
+ * @param {string} from - Name of the vendor to buy from
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains the contract id or errors
+ */
+exports.propose_contract = async function(item, count, price, every, interval, deliveries, penalty, from, uuid) {
+    payload = { item, count, price, every, interval, deliveries, penalty, from, uuid };
+    return await exports.base_api_post('/api/contracts/propose', payload);
+};
+
 /**
  * Purchase an item FROM the vendor TO the buyer
  * @param {string} item - The name of the item
  * @param {integer} count - Amount to purchase
  * @param {string} frm - Name of the vendor to purchase from
  * @param {string} to - Your UUID to verify the purchase
+ * @param {string} delivery - One of instant, escrow or shipment
  * @returns {object} Contains receipt or errors
  */
-exports.purchase = async function(item, count, frm, to) {
-    payload = { item, count, frm, to };
+exports.purchase = async function(item, count, frm, to, delivery = 'instant') {
+    payload = { item, count, from: frm, to, delivery };
     return await exports.base_api_post('/api/purchase', payload);
 };
 
@@ -83,13 +553,60 @@ exports.purchase = async function(item, count, frm, to) {
  * Register a new vendor with the market
  * @param {string} vendor_name - The name of the vendor
  * @param {string} vendor_url - Optional custom url for vendor
+ * @param {string} team - Optional team to join
+ * @param {string} team_token - Optional join token of an existing team
+ * @param {string} entrant_token - Optional join token of a tournament entrant
  * @returns {object} Contains new UUID or errors
  */
-exports.register_vendor = async function(vendor_name, vendor_url = '') {
-    payload = { vendor_name, vendor_url };
+exports.register_vendor = async function(vendor_name, vendor_url = '', team = '', team_token = '', entrant_token = '') {
+    payload = { vendor_name, vendor_url, team, team_token, entrant_token };
     return await exports.base_api_post('/register', payload);
 };
 
+/**
+ * Give units of an item to a teammate for free
+ * @param {string} item - The name of the item
+ * @param {integer} count - The number of units to give
+ * @param {string} to - Name of the teammate
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains the units moved or errors
+ */
+exports.transfer_item = async function(item, count, to, uuid) {
+    payload = { item, count, to, uuid };
+    return await exports.base_api_post('/api/team/transfer', payload);
+};
+
+/**
+ * Return borrowed units and reclaim the collateral
+ * @param {integer} loan - The id of the loan to return
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains the released collateral or errors
+ */
+exports.return_loan = async function(loan, uuid) {
+    payload = { loan, uuid };
+    return await exports.base_api_post('/api/return', payload);
+};
+
+/**
+ * Confirm shipment of units you sold, delivering them to the buyer
+ * @param {integer} escrow - The id of the escrow
+ * @param {string} uuid - Your UUID for verification
+ * @returns {object} Contains shipment receipt or errors
+ */
+exports.ship_escrow = async function(escrow, uuid) {
+    payload = { escrow, uuid };
+    return await exports.base_api_post('/api/escrow/ship', payload);
+};
+
+/**
+ * Get a spectator token, which reads the full market state through
+ * get_ledger_state and get_entries but cannot trade
+ * @returns {object} Contains the spectator token
+ */
+exports.spectate = async function() {
+    return await exports.base_api_post('/spectate', {});
+};
+
 /**
  * 
  * @param {string} name - The name of the item
@@ -98,9 +615,12 @@ exports.register_vendor = async function(vendor_name, vendor_url = '') {
  *                          stock, negative values will move items from the 
  *                          stock to the store
  * @param {string} uuid - Your UUID to verify the stock request
+ * @param {string} tiers - Optional bulk prices as comma separated min:price
+ *                         pairs, empty to clear
  * @returns {object} Contains stocking receipt or errors
  */
-exports.stock = async function(name, price, stock, uuid) {
+exports.stock = async function(name, price, stock, uuid, tiers = undefined) {
     payload = {name, price, stock, uuid};
+    if (tiers !== undefined) { payload.tiers = tiers; }
     return await exports.base_api_post('/api/stock', payload);
 };
\ No newline at end of file
@@ -4,6 +4,7 @@ import requests
 from typing import Optional
 
 BASE_URL = 'http://localhost:8000'
+ADMIN_SECRET = '' # Only needed for operator requests
 
 """CodeMarket Python API
 
@@ -33,10 +34,314 @@ def base_api_post(url: str, data: dict) -> dict:
     Returns:
         dict:   Contains the response content of the call
     """
-    headers = {'content-type': 'application/x-www-form-urlencoded'}
+    headers = {'content-type': 'application/x-www-form-urlencoded', 'x-admin-secret': ADMIN_SECRET}
     r = requests.post(BASE_URL + url, data=data, headers=headers)
     return json.loads(r.content.decode())
 
+def base_api_delete(url: str) -> dict:
+    """Base API DELETE request to remove data from the market
+    
+    Args:
+        url   (str):    The url to send the request to
+
+    Returns:
+        dict:   Contains the response content of the call
+    """
+    r = requests.delete(BASE_URL + url, headers={'x-admin-secret': ADMIN_SECRET})
+    return json.loads(r.content.decode())
+
+def accept_contract(contract: int, uuid: str) -> dict:
+    """Accept a supply contract proposed to you
+
+    Args:
+        contract (int):     The id of the contract
+        uuid     (str):     Your UUID to verify the acceptance
+
+    Returns:
+        dict:   Contains the contract id or errors
+    """
+    payload = {
+        'contract': contract,
+        'uuid': uuid
+    }
+    return base_api_post('/api/contracts/accept', data=payload)
+
+def admin_adjust(vendor: str, bits: Optional[float] = None, item: Optional[str] = None, count: Optional[int] = None) -> dict:
+    """Change the bits of a vendor and the units of an item it holds, needs
+    ADMIN_SECRET
+
+    Args:
+        vendor  (str):      Name of the vendor
+        bits    (float):    The change in bits
+        item    (str):      The name of the item to change
+        count   (int):      The change in units of the item
+
+    Returns:
+        dict:   Contains the new bits and units or errors
+    """
+    payload = {'vendor': vendor}
+    if bits is not None:
+        payload['bits'] = bits
+    if item is not None and count is not None:
+        payload['item'] = item
+        payload['count'] = count
+    return base_api_post('/admin/adjust', data=payload)
+
+def admin_freeze(vendor: str, frozen: Optional[bool] = True) -> dict:
+    """Stop a vendor from trading, or let it trade again, needs ADMIN_SECRET
+
+    Args:
+        vendor  (str):      Name of the vendor
+        frozen  (bool):     False to unfreeze the vendor
+
+    Returns:
+        dict:   Contains success or errors
+    """
+    payload = {'vendor': vendor, 'frozen': str(frozen).lower()}
+    return base_api_post('/admin/freeze', data=payload)
+
+def admin_inject(vendor: str, item: str, count: int, price: float) -> dict:
+    """Add new units of an item to the store of a vendor, needs ADMIN_SECRET
+
+    Args:
+        vendor  (str):      Name of the vendor
+        item    (str):      The name of the item
+        count   (int):      The number of units to add
+        price   (float):    The price of the item if the vendor does not have it
+
+    Returns:
+        dict:   Contains the units the vendor holds or errors
+    """
+    payload = {'vendor': vendor, 'item': item, 'count': count, 'price': price}
+    return base_api_post('/admin/inject', data=payload)
+
+def admin_kick(vendor: str) -> dict:
+    """Revoke the uuid of a vendor, needs ADMIN_SECRET
+
+    Args:
+        vendor  (str):      Name of the vendor
+
+    Returns:
+        dict:   Contains success or errors
+    """
+    return base_api_post('/admin/kick', data={'vendor': vendor})
+
+def admin_pause() -> dict:
+    """Pause all trading in the market, needs ADMIN_SECRET
+
+    Returns:
+        dict:   Contains success or errors
+    """
+    return base_api_post('/admin/pause', data={})
+
+def admin_reset() -> dict:
+    """Start the market over from its config or scenario, needs ADMIN_SECRET
+
+    Returns:
+        dict:   Contains success or errors
+    """
+    return base_api_post('/admin/reset', data={})
+
+def admin_resume() -> dict:
+    """Resume trading in the market, needs ADMIN_SECRET
+
+    Returns:
+        dict:   Contains success or errors
+    """
+    return base_api_post('/admin/resume', data={})
+
+def borrow(item: str, count: int, frm: str, uuid: str) -> dict:
+    """Borrow units of an item to sell short
+
+    Args:
+        item  (str):    The name of the item
+        count (int):    Amount to borrow
+        frm   (str):    Name of the vendor to borrow from, empty for the
+                        lending pool
+        uuid  (str):    Your UUID to verify the loan
+
+    Returns:
+        dict:   Contains the loan id, collateral and fee or errors
+    """
+    payload = {
+        'item': item,
+        'count': count,
+        'from': frm,
+        'uuid': uuid
+    }
+    return base_api_post('/api/borrow', data=payload)
+
+def buy_intel(uuid: str, vendor: Optional[str] = None, item: Optional[str] = None) -> dict:
+    """Buy an intel report on a vendor or an item, for markets with limited
+    visibility
+
+    Args:
+        uuid   (str):   Your UUID to pay for the report
+        vendor (str):   Optional name of the vendor to report on
+        item   (str):   Optional name of the item to report on
+
+    Returns:
+        dict:   Contains the names, prices and stock in the report or errors
+    """
+    payload = {'uuid': uuid}
+    if vendor is not None:
+        payload['vendor'] = vendor
+    if item is not None:
+        payload['item'] = item
+    return base_api_post('/api/intel', data=payload)
+
+def cancel_contract(contract: int, uuid: str) -> dict:
+    """Withdraw a supply contract that has not been accepted yet
+
+    Args:
+        contract (int):     The id of the contract
+        uuid     (str):     Your UUID to verify the cancellation
+
+    Returns:
+        dict:   Contains the contract id or errors
+    """
+    payload = {
+        'contract': contract,
+        'uuid': uuid
+    }
+    return base_api_post('/api/contracts/cancel', data=payload)
+
+def cancel_escrow(escrow: int, uuid: str) -> dict:
+    """Cancel a held escrow, refunding the buyer
+
+    Args:
+        escrow (int):   The id of the escrow
+        uuid   (str):   Your UUID to verify the cancellation
+
+    Returns:
+        dict:   Contains cancellation receipt or errors
+    """
+    payload = {
+        'escrow': escrow,
+        'uuid': uuid
+    }
+    return base_api_post('/api/escrow/cancel', data=payload)
+
+def create_market(market: str, config: Optional[str] = '') -> dict:
+    """Create a new market with its own ledger and vendors. Set BASE_URL to
+    the returned market url under the server to trade in it
+    
+    Args:
+        market  (str):  The id of the new market
+        config  (str):  TOML overriding the server config for the market
+
+    Returns:
+        dict:   Contains the url of the market or errors
+    """
+    payload = {'id': market, 'config': config}
+    return base_api_post('/markets', payload)
+
+def create_tournament(tournament: str, entrants: str, format: Optional[str] = 'round_robin', rounds: Optional[int] = None, config: Optional[str] = '') -> dict:
+    """Start a tournament between bots, each match played in its own market
+    
+    Args:
+        tournament  (str):  The id of the tournament
+        entrants    (str):  Comma separated vendor names of the bots
+        format      (str):  Either round_robin or swiss
+        rounds      (int):  The number of Swiss rounds
+        config      (str):  TOML overriding the server config for every match
+
+    Returns:
+        dict:   Contains the number of rounds or errors
+    """
+    payload = {'id': tournament, 'entrants': entrants, 'format': format, 'config': config}
+    if rounds is not None:
+        payload['rounds'] = rounds
+    return base_api_post('/tournament', payload)
+
+def delete_market(market: str) -> dict:
+    """Delete a market, the main market cannot be deleted
+    
+    Args:
+        market  (str):  The id of the market
+
+    Returns:
+        dict:   Contains success or errors
+    """
+    return base_api_delete('/markets/' + market)
+
+def get_contracts(uuid: str) -> dict:
+    """Get every supply contract you are buying or selling in
+
+    Args:
+        uuid (str):     Your UUID to verify the request
+
+    Returns:
+        dict:   Contains a list of contracts
+    """
+    payload = { 'uuid': uuid }
+    return base_api_post('/api/contracts', data=payload)
+
+def get_categories() -> dict:
+    """Get the items in each category
+
+    Returns:
+        dict:   Contains a list of item names for each category
+    """
+    return base_api_get('/api/categories')
+
+def get_entries(uuid: str) -> dict:
+    """Get the ledger entries of your vendor, or every entry with a spectator
+    token
+
+    Args:
+        uuid (str):     Your UUID or spectator token to verify the request
+
+    Returns:
+        dict:   Contains a list of ledger entries
+    """
+    payload = { 'uuid': uuid }
+    return base_api_post('/api/entries', data=payload)
+
+def get_escrow(uuid: str) -> dict:
+    """Get every escrow you are buying or selling in
+
+    Args:
+        uuid (str):     Your UUID to verify the request
+
+    Returns:
+        dict:   Contains a list of escrow
+    """
+    payload = { 'uuid': uuid }
+    return base_api_post('/api/escrow', data=payload)
+
+def get_events() -> dict:
+    """Get every market event that has fired
+
+    Returns:
+        dict:   Contains a list of market events and whether they are active
+    """
+    return base_api_get('/api/events')
+
+def get_halts() -> dict:
+    """Get every trading halt still in effect
+
+    Returns:
+        dict:   Contains a list of halted items and when they resume
+    """
+    return base_api_get('/api/halts')
+
+def get_indices() -> dict:
+    """Get the price index history of the market and of each category
+
+    Returns:
+        dict:   Contains a list of index values for the market and each category
+    """
+    return base_api_get('/api/indices')
+
+def get_leaderboard() -> dict:
+    """Get the vendor rankings and the history of rank changes
+
+    Returns:
+        dict:   Contains the ranked vendors and rank changes by version
+    """
+    return base_api_get('/api/leaderboard')
+
 def get_ledger_state(uuid: str) -> dict:
     """Get the current ledger state
     
@@ -49,6 +354,138 @@ def get_ledger_state(uuid: str) -> dict:
     payload = { 'uuid': uuid }
     return base_api_post('/api/ledger_state', data=payload)
 
+def get_loans() -> dict:
+    """Get all open loans and lending offers
+
+    Returns:
+        dict:   Contains lists of open loans and lending offers
+    """
+    return base_api_get('/api/loans')
+
+def get_markets() -> dict:
+    """Get every market on the server
+
+    Returns:
+        dict:   Contains the vendors, version and session phase by market id
+    """
+    return base_api_get('/markets')
+
+def buy_shares(issuer: str, count: int, frm: str, uuid: str) -> dict:
+    """Buy offered shares of a vendor from a shareholder
+
+    Args:
+        issuer (str):   Name of the vendor whose shares are bought
+        count  (int):   The number of shares to buy
+        frm    (str):   Name of the shareholder selling the shares
+        uuid   (str):   Your UUID to verify the purchase
+
+    Returns:
+        dict:   Contains purchase receipt or errors
+    """
+    payload = {
+        'issuer': issuer,
+        'count': count,
+        'from': frm,
+        'uuid': uuid
+    }
+    return base_api_post('/api/shares/buy', data=payload)
+
+def get_quote(item: str, count: int, frm: str) -> dict:
+    """Get the price of buying a number of units from a vendor
+
+    Args:
+        item  (str):    The name of the item
+        count (int):    Amount to quote
+        frm   (str):    Name of the vendor to quote
+
+    Returns:
+        dict:   Contains the total, effective unit price and stock or errors
+    """
+    return base_api_get('/api/quote?item={}&count={}&from={}'.format(item, count, frm))
+
+def get_net_worth() -> dict:
+    """Get the net worth of every vendor
+
+    Returns:
+        dict:   Contains a mapping from vendor names to net worth
+    """
+    return base_api_get('/api/net_worth')
+
+def get_objectives() -> dict:
+    """Get the progress of every vendor on its objectives
+
+    Returns:
+        dict:   Contains a list of objective progress, final once the session ends
+    """
+    return base_api_get('/api/objectives')
+
+def get_replay(version: int) -> dict:
+    """Get the vendors and price history as they were at a ledger version
+    
+    Args:
+        version (int):  The ledger version to replay
+
+    Returns:
+        dict:   Contains the bits and units of each vendor and price history
+    """
+    return base_api_get('/api/replay?version=' + str(version))
+
+def get_reputation() -> dict:
+    """Get the reputation score and track record of every vendor
+
+    Returns:
+        dict:   Contains the reputation of each vendor by name
+    """
+    return base_api_get('/api/reputation')
+
+def get_session() -> dict:
+    """Get the phase and limits of the game session
+
+    Returns:
+        dict:   Contains the session phase, limits and final standings
+    """
+    return base_api_get('/api/session')
+
+def get_shares() -> dict:
+    """Get every shareholding, share offer and last traded share price
+
+    Returns:
+        dict:   Contains holdings, offers and prices
+    """
+    return base_api_get('/api/shares')
+
+def get_standings() -> dict:
+    """Get the vendors ranked by net worth, final once the session has ended
+
+    Returns:
+        dict:   Contains a list of standings
+    """
+    return base_api_get('/api/standings')
+
+def get_teams() -> dict:
+    """Get every team with its members, treasury and combined net worth
+
+    Returns:
+        dict:   Contains the members, bits and net worth of each team
+    """
+    return base_api_get('/api/teams')
+
+def get_tournament() -> dict:
+    """Get the current tournament and the ratings of every bot
+
+    Returns:
+        dict:   Contains the matches, points and ratings
+    """
+    return base_api_get('/api/tournament')
+
+def get_turn() -> dict:
+    """Get the current turn and the results of the last resolved turn
+
+    Returns:
+        dict:   Contains the turn number, seconds left and last results
+    """
+    return base_api_get('/api/turn')
+
 def get_vendor_names() -> dict:
     """Get a list of registered vendor names
 
@@ -65,14 +502,147 @@ def get_vendor_urls() -> dict:
     """
     return base_api_get('/api/vendor_urls')
 
-def purchase(item: str, count: int, frm: str, to: str) -> dict:
+def issue_shares(count: int, price: float, uuid: str) -> dict:
+    """Issue new shares of your vendor and offer them for sale
+
+    Args:
+        count   (int):  The number of shares to issue
+        price (float):  The price per share
+        uuid    (str):  Your UUID to verify the issue
+
+    Returns:
+        dict:   Contains issue receipt or errors
+    """
+    payload = {
+        'count': count,
+        'price': price,
+        'uuid': uuid
+    }
+    return base_api_post('/api/shares/issue', data=payload)
+
+def join_team(team: str, uuid: str, token: Optional[str] = '') -> dict:
+    """Join a team and share its treasury until the session ends, an empty
+    team leaves your team
+    
+    Args:
+        team              (str):    The name of the team
+        uuid              (str):    Your UUID for verification
+        token   (Optional[str]):    The join token of an existing team
+
+    Returns:
+        dict:   Contains success and the team's join token or errors
+    """
+    payload = {'team': team, 'token': token, 'uuid': uuid}
+    return base_api_post('/api/team/join', payload)
+
+def lend(item: str, count: int, rate: float, uuid: str) -> dict:
+    """Offer stored units of an item for other vendors to borrow
+
+    Args:
+        item   (str):   The name of the item
+        count  (int):   Amount to offer, zero withdraws the offer
+        rate (float):   Fee charged as a fraction of the loan value
+        uuid   (str):   Your UUID to verify the offer
+
+    Returns:
+        dict:   Contains lending receipt or errors
+    """
+    payload = {
+        'item': item,
+        'count': count,
+        'rate': rate,
+        'uuid': uuid
+    }
+    return base_api_post('/api/lend', data=payload)
+
+def load_scenario(scenario: str, market: Optional[str] = '') -> dict:
+    """Load a scenario into a market, replacing its whole state
+
+    Args:
+        scenario    (str):  The scenario TOML, see scenarios/oldies.toml
+        market      (str):  The id of the market, the main market if empty
+
+    Returns:
+        dict:   Contains the number of vendors or errors
+    """
+    payload = {'scenario': scenario, 'market': market}
+    return base_api_post('/scenario', payload)
+
+def offer_shares(issuer: str, count: int, price: float, uuid: str) -> dict:
+    """Offer shares you hold for sale
+
+    Args:
+        issuer  (str):  Name of the vendor whose shares are offered
+        count   (int):  The number of shares to offer, zero withdraws the offer
+        price (float):  The price per share
+        uuid    (str):  Your UUID to verify the offer
+
+    Returns:
+        dict:   Contains offer receipt or errors
+    """
+    payload = {
+        'issuer': issuer,
+        'count': count,
+        'price': price,
+        'uuid': uuid
+    }
+    return base_api_post('/api/shares/offer', data=payload)
+
+def pay_dividend(amount: float, uuid: str) -> dict:
+    """Pay a dividend per share to every other holder of your shares
+
+    Args:
+        amount (float): The bits paid per share
+        uuid     (str): Your UUID to verify the dividend
+
+    Returns:
+        dict:   Contains the total paid or errors
+    """
+    payload = {
+        'amount': amount,
+        'uuid': uuid
+    }
+    return base_api_post('/api/shares/dividend', data=payload)
+
+def propose_contract(item: str, count: int, price: float, every: int, interval: str, deliveries: int, penalty: float, frm: str, uuid: str) -> dict:
+    """Propose a recurring supply contract to a seller
+
+    Args:
+        item       (str):   The name of the item
+        count      (int):   Units delivered each time
+        price      (float): Price paid per unit delivered
+        every      (int):   Length of the interval between deliveries
+        interval   (str):   One of versions or seconds
+        deliveries (int):   Number of deliveries before the contract ends
+        penalty    (float): Bits the seller pays you per missing unit
+        frm        (str):   Name of the vendor to buy from
+        uuid       (str):   Your UUID to verify the proposal
+
+    Returns:
+        dict:   Contains the contract id or errors
+    """
+    payload = {
+        'item': item,
+        'count': count,
+        'price': price,
+        'every': every,
+        'interval': interval,
+        'deliveries': deliveries,
+        'penalty': penalty,
+        'from': frm,
+        'uuid': uuid
+    }
+    return base_api_post('/api/contracts/propose', data=payload)
+
+def purchase(item: str, count: int, frm: str, to: str, delivery: Optional[str] = 'instant') -> dict:
     """Purchase an item FROM the vendor TO the buyer
 
     Args:
-        item  (str):    The name of the item
-        count (int):    Amount to purchase
-        frm   (str):    Name of the vendor to purchase from
-        to    (str):    Your UUID to verify the purchase
+        item                (str):  The name of the item
+        count               (int):  Amount to purchase
+        frm                 (str):  Name of the vendor to purchase from
+        to                  (str):  Your UUID to verify the purchase
+        delivery  (Optional[str]):  One of instant, escrow or shipment
     
     Returns:
         dict:   Contains purchase receipt or errors
@@ -81,36 +651,101 @@ def purchase(item: str, count: int, frm: str, to: str) -> dict:
         'item': item,
         'count': count,
         'from': frm,
-        'to': to
+        'to': to,
+        'delivery': delivery
     }
     return base_api_post('/api/purchase', data=payload)
 
-def register_vendor(vendor_name: str, vendor_url: Optional[str] = '') -> dict:
+def register_vendor(vendor_name: str, vendor_url: Optional[str] = '', team: Optional[str] = '', team_token: Optional[str] = '', entrant_token: Optional[str] = '') -> dict:
     """Register a new vendor with the market
 
     Args:
         vendor_name          (str):     The name of the vendor
         vendor_url (Optional[str]):     The url of the vendor
+        team       (Optional[str]):     The team to join
+        team_token (Optional[str]):     The join token of an existing team
+        entrant_token (Optional[str]):  The join token of a tournament entrant
     
     Returns:
         dict:   Contains new vendor's UUID or errors
     """
     payload = { 
         'vendor_name': vendor_name,
-        'vendor_url': vendor_url
+        'vendor_url': vendor_url,
+        'team': team,
+        'team_token': team_token,
+        'entrant_token': entrant_token
     }
     return base_api_post('/register', data=payload)
 
-def stock(item: str, price: float, stock: int, uuid: str) -> dict:
+def transfer_item(item: str, count: int, to: str, uuid: str) -> dict:
+    """Give units of an item to a teammate for free
+    
+    Args:
+        item    (str):  The name of the item
+        count   (int):  The number of units to give
+        to      (str):  Name of the teammate
+        uuid    (str):  Your UUID for verification
+
+    Returns:
+        dict:   Contains the units moved or errors
+    """
+    payload = {'item': item, 'count': count, 'to': to, 'uuid': uuid}
+    return base_api_post('/api/team/transfer', payload)
+
+def return_loan(loan: int, uuid: str) -> dict:
+    """Return borrowed units and reclaim the collateral
+
+    Args:
+        loan (int):     The id of the loan to return
+        uuid (str):     Your UUID to verify the return
+
+    Returns:
+        dict:   Contains the released collateral or errors
+    """
+    payload = {
+        'loan': loan,
+        'uuid': uuid
+    }
+    return base_api_post('/api/return', data=payload)
+
+def ship_escrow(escrow: int, uuid: str) -> dict:
+    """Confirm shipment of units you sold, delivering them to the buyer
+
+    Args:
+        escrow (int):   The id of the escrow
+        uuid   (str):   Your UUID to verify the shipment
+
+    Returns:
+        dict:   Contains shipment receipt or errors
+    """
+    payload = {
+        'escrow': escrow,
+        'uuid': uuid
+    }
+    return base_api_post('/api/escrow/ship', data=payload)
+
+def spectate() -> dict:
+    """Get a spectator token, which reads the full market state through
+    get_ledger_state and get_entries but cannot trade
+
+    Returns:
+        dict:   Contains the spectator token
+    """
+    return base_api_post('/spectate', data={})
+
+def stock(item: str, price: float, stock: int, uuid: str, tiers: Optional[str] = None) -> dict:
     """Stock/store item within your a shop
 
     Args:
-        item    (str):  The name of the item
-        price (float):  Amount to price of item
-        stock   (int):  The amount of items to go from the store to the stock,
-                        negative values will move items from the stock to the
-                        store
-        uuid    (str):  Your UUID to verify the stock request
+        item             (str):     The name of the item
+        price          (float):     Amount to price of item
+        stock            (int):     The amount of items to go from the store
+                                    to the stock, negative values will move
+                                    items from the stock to the store
+        uuid             (str):     Your UUID to verify the stock request
+        tiers  (Optional[str]):     Bulk prices as comma separated min:price
+                                    pairs, empty to clear, None to keep
     
     Returns:
         dict:   Contains stocking receipt or errors
@@ -121,4 +756,6 @@ def stock(item: str, price: float, stock: int, uuid: str) -> dict:
         'stock': stock,
         'uuid': uuid
     }
+    if tiers is not None:
+        payload['tiers'] = tiers
     return base_api_post('/api/stock', data=payload)
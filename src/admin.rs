@@ -0,0 +1,310 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use rocket::Request;
+use rocket::http::Status;
+use rocket::request::{self, Form, FormError, FromRequest};
+use rocket::response::content;
+use rocket::{Outcome, State};
+use serde_json::{json, to_string};
+use super::ledger::{Ledger, LedgerError};
+use super::markets::MarketLedger;
+use super::scenario;
+use super::util;
+
+//Request guard for operator endpoints, passes when the `X-Admin-Secret` header
+//matches the managed `AdminSecret`. Without a secret every request fails
+pub struct Admin;
+
+//The secret operator requests have to send, managed by the server
+pub struct AdminSecret(pub String);
+
+impl AdminSecret {
+    /// Creates the operator secret from `admin_secret` in the config, empty
+    /// when it is not set
+    pub fn from_config() -> AdminSecret {
+        AdminSecret(super::get_config::<String>("admin_secret").unwrap_or_default())
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for Admin {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let secret = request.guard::<State<AdminSecret>>().succeeded();
+        match (secret, request.headers().get_one("X-Admin-Secret")) {
+            (Some(secret), Some(given)) if !secret.0.is_empty() && secrets_match(&secret.0, given) => Outcome::Success(Admin),
+            _ => Outcome::Failure((Status::Unauthorized, ()))
+        }
+    }
+}
+
+/// Compares the admin secret with the one sent, taking the same time wherever
+/// they differ so the secret cannot be guessed one byte at a time
+///
+/// # Arguments
+///
+/// * `secret`  - The admin secret of the server
+/// * `given`   - The secret sent with the request
+fn secrets_match(secret: &str, given: &str) -> bool {
+    secret.len() == given.len() && secret.bytes().zip(given.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+#[derive(Debug, FromForm)]
+pub struct AdminVendor {
+    pub vendor: String,
+    pub frozen: Option<bool>
+}
+
+//Change to the bits and optionally the units of an item held by a vendor
+#[derive(Debug, FromForm)]
+pub struct Adjustment {
+    pub vendor: String,
+    pub bits: Option<f64>,
+    pub item: Option<String>,
+    pub count: Option<i32>
+}
+
+//New units of an item placed in the store of a vendor, priced for vendors that
+//do not have the item yet
+#[derive(Debug, FromForm)]
+pub struct Injection {
+    pub vendor: String,
+    pub item: String,
+    pub count: u32,
+    pub price: f64
+}
+
+/// Converts a ledger error from an admin operation into a readable message
+///
+/// # Arguments
+///
+/// * `err` - The error returned by the ledger
+fn admin_error(err: LedgerError) -> String {
+    match err {
+        LedgerError::InsufficientStock => "not enough units to take away".to_string(),
+        LedgerError::InvalidAmount => "invalid amount, item or price".to_string(),
+        _ => "could not be completed".to_string()
+    }
+}
+
+/// Endpoint for changing the bits of a vendor and the units of one of its
+/// items via http request, each change is recorded in the ledger. An item
+/// needs a count and a count needs an item
+///
+/// # Arguments
+///
+/// * `adjustment`  - The DTO for the adjustment
+/// * `ledger`      - The current ledger state
+#[post("/admin/adjust", data="<adjustment>")]
+pub fn http_adjust(_admin: Admin, adjustment: Result<Form<Adjustment>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let adjustment = match adjustment {
+        Ok(a) if a.item.is_some() == a.count.is_some() => a.into_inner(),
+        _ => {
+            output_vars.insert("Format".to_string(), Box::new("incorrect"));
+            return util::construct_json(&output_vars);
+        }
+    };
+
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let mut ledger = arc_ledger.write().unwrap();
+    let vendor_id = match ledger.find_vendor(&adjustment.vendor) {
+        Some(pos) => pos,
+        None => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("vendor".to_string(), Box::new("not found".to_string()));
+            return util::construct_json(&output_vars);
+        }
+    };
+    if adjustment.bits.map_or(false, |b| !b.is_finite()) {
+        output_vars.insert("success".to_string(), Box::new(false));
+        output_vars.insert("bits".to_string(), Box::new(admin_error(LedgerError::InvalidAmount)));
+        return util::construct_json(&output_vars);
+    }
+    let items = adjustment.item.zip(adjustment.count);
+    if let Some(Err(e)) = items.as_ref().map(|(item, count)| ledger.check_items(vendor_id, item, *count, None)) {
+        output_vars.insert("success".to_string(), Box::new(false));
+        output_vars.insert("item".to_string(), Box::new(admin_error(e)));
+        return util::construct_json(&output_vars);
+    }
+
+    if let Some((item, count)) = items {
+        if let Ok(total) = ledger.adjust_items(vendor_id, item, count, None) {
+            output_vars.insert("units".to_string(), Box::new(total));
+        }
+    }
+    if let Some(Ok(total)) = adjustment.bits.map(|bits| ledger.adjust_bits(vendor_id, bits)) {
+        output_vars.insert("bits".to_string(), Box::new(total));
+    }
+    output_vars.insert("success".to_string(), Box::new(true));
+    util::construct_json(&output_vars)
+}
+
+/// Endpoint for freezing a vendor, or unfreezing it with `frozen=false`, via
+/// http request. Frozen vendors cannot trade
+///
+/// # Arguments
+///
+/// * `admin_vendor`    - The DTO naming the vendor
+/// * `ledger`          - The current ledger state
+#[post("/admin/freeze", data="<admin_vendor>")]
+pub fn http_freeze(_admin: Admin, admin_vendor: Result<Form<AdminVendor>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    vendor_action(admin_vendor, ledger, |ledger, id, frozen| ledger.freeze_vendor(id, frozen.unwrap_or(true)))
+}
+
+/// Endpoint for adding new units of an item to the store of a vendor via http
+/// request, recorded in the ledger
+///
+/// # Arguments
+///
+/// * `injection`   - The DTO for the injected items
+/// * `ledger`      - The current ledger state
+#[post("/admin/inject", data="<injection>")]
+pub fn http_inject(_admin: Admin, injection: Result<Form<Injection>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let injection = match injection {
+        Ok(i) => i.into_inner(),
+        Err(_) => {
+            output_vars.insert("Format".to_string(), Box::new("incorrect"));
+            return util::construct_json(&output_vars);
+        }
+    };
+
+    if injection.count > i32::MAX as u32 {
+        output_vars.insert("success".to_string(), Box::new(false));
+        output_vars.insert("count".to_string(), Box::new(format!("is over {}", i32::MAX)));
+        return util::construct_json(&output_vars);
+    }
+
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let mut ledger = arc_ledger.write().unwrap();
+    let vendor_id = match ledger.find_vendor(&injection.vendor) {
+        Some(pos) => pos,
+        None => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("vendor".to_string(), Box::new("not found".to_string()));
+            return util::construct_json(&output_vars);
+        }
+    };
+    match ledger.adjust_items(vendor_id, injection.item, injection.count as i32, Some(injection.price)) {
+        Ok(total) => {
+            output_vars.insert("success".to_string(), Box::new(true));
+            output_vars.insert("units".to_string(), Box::new(total));
+        },
+        Err(e) => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("item".to_string(), Box::new(admin_error(e)));
+        }
+    }
+    util::construct_json(&output_vars)
+}
+
+/// Endpoint for kicking a vendor out of the market via http request. Its UUID
+/// stops working, while its items and standing remain
+///
+/// # Arguments
+///
+/// * `admin_vendor`    - The DTO naming the vendor
+/// * `ledger`          - The current ledger state
+#[post("/admin/kick", data="<admin_vendor>")]
+pub fn http_kick(_admin: Admin, admin_vendor: Result<Form<AdminVendor>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    vendor_action(admin_vendor, ledger, |ledger, id, _| ledger.kick_vendor(id))
+}
+
+/// Endpoint for pausing trading in the market via http request
+///
+/// # Arguments
+///
+/// * `ledger`  - The current ledger state
+#[post("/admin/pause")]
+pub fn http_pause(_admin: Admin, ledger: MarketLedger) -> content::Json<String> { set_paused(ledger, true) }
+
+/// Endpoint for starting the market over via http request. Markets created
+/// from a config or scenario are rebuilt from it, others get fresh generated
+/// vendors. Answers with the UUID of every new vendor by name
+///
+/// # Arguments
+///
+/// * `ledger`  - The current ledger state
+#[post("/admin/reset")]
+pub fn http_reset(_admin: Admin, ledger: MarketLedger) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let config = arc_ledger.read().unwrap().get_market_config();
+    let fresh = match config {
+        Some(c) => scenario::build(c),
+        None => {
+            let mut l = Ledger::new();
+            let ids = l.generate_vendors();
+            Ok((l, ids))
+        }
+    };
+    match fresh {
+        Ok((l, ids)) => {
+            let vendors = scenario::vendor_uuids(&l, ids);
+            *arc_ledger.write().unwrap() = l;
+            content::Json(to_string(&json!({ "success": "true", "vendors": vendors })).unwrap())
+        },
+        Err(e) => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("reset".to_string(), Box::new(e));
+            util::construct_json(&output_vars)
+        }
+    }
+}
+
+/// Endpoint for resuming trading in the market via http request
+///
+/// # Arguments
+///
+/// * `ledger`  - The current ledger state
+#[post("/admin/resume")]
+pub fn http_resume(_admin: Admin, ledger: MarketLedger) -> content::Json<String> { set_paused(ledger, false) }
+
+/// Pauses or resumes trading in the market
+///
+/// # Arguments
+///
+/// * `ledger`  - The current ledger state
+/// * `paused`  - True to pause trading, false to resume it
+fn set_paused(ledger: MarketLedger, paused: bool) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    ledger.inner().session_ledger.write().unwrap().set_paused(paused);
+    output_vars.insert("success".to_string(), Box::new(true));
+    output_vars.insert("paused".to_string(), Box::new(paused));
+    util::construct_json(&output_vars)
+}
+
+/// Applies an operator action to the vendor named in the request
+///
+/// # Arguments
+///
+/// * `admin_vendor`    - The DTO naming the vendor
+/// * `ledger`          - The current ledger state
+/// * `action`          - The action, given the ledger, vendor ID and `frozen`
+fn vendor_action<F>(admin_vendor: Result<Form<AdminVendor>, FormError<'_>>, ledger: MarketLedger, action: F) -> content::Json<String>
+    where F: Fn(&Ledger, usize, Option<bool>) {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let admin_vendor = match admin_vendor {
+        Ok(v) => v.into_inner(),
+        Err(_) => {
+            output_vars.insert("Format".to_string(), Box::new("incorrect"));
+            return util::construct_json(&output_vars);
+        }
+    };
+
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = arc_ledger.read().unwrap();
+    match ledger.find_vendor(&admin_vendor.vendor) {
+        Some(pos) => {
+            action(&ledger, pos, admin_vendor.frozen);
+            output_vars.insert("success".to_string(), Box::new(true));
+        },
+        None => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("vendor".to_string(), Box::new("not found".to_string()));
+        }
+    }
+    util::construct_json(&output_vars)
+}
@@ -3,12 +3,19 @@ use std::fmt::Display;
 
 use rocket::response::content;
 use rocket::request::{Form, FormError};
-use rocket::State;
+use rocket_contrib::templates::Template;
+use super::admin::Admin;
+use super::ledger::Ledger;
+use super::markets::MarketLedger;
 
+//Registration of a new vendor, optionally joining a team straight away
 #[derive(Debug, FromForm)]
 pub struct Registration {
     pub vendor_name: String,
-    pub vendor_url: String
+    pub vendor_url: String,
+    pub team: Option<String>,
+    pub team_token: Option<String>,
+    pub entrant_token: Option<String>
 }
 
 /// The endpoint for registering new vendors using a JSON object. When a valid
@@ -20,7 +27,7 @@ pub struct Registration {
 /// * `registration_data`   - JSON object with registration info
 /// * `ledger`              - The current ledger state
 #[post("/register", data="<registration>")]
-pub fn register(registration: Result<Form<Registration>, FormError<'_>>, ledger: State<super::ledger::MutLedger>) -> content::Json<String> {
+pub fn register(registration: Result<Form<Registration>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
     let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
 
     let registration = match registration {
@@ -47,6 +54,11 @@ pub fn register(registration: Result<Form<Registration>, FormError<'_>>, ledger:
                 output_vars.insert("vendor_name".to_string(), Box::new("is in use".to_string()));
             }
         }
+        match ledger.check_entrant(&registration.vendor_name, registration.entrant_token.as_ref()) {
+            Err(super::ledger::LedgerError::InvalidVendor) => { output_vars.insert("vendor_name".to_string(), Box::new("is not an entrant of this market".to_string())); },
+            Err(_) => { output_vars.insert("entrant_token".to_string(), Box::new("is missing or incorrect".to_string())); },
+            Ok(()) => {}
+        }
     }
     if output_vars.len() > 0 {
         return super::util::construct_json(&output_vars)
@@ -55,10 +67,101 @@ pub fn register(registration: Result<Form<Registration>, FormError<'_>>, ledger:
             true => None, false => Some(registration.vendor_url)
         };
         let mut ledger = (*arc_ledger).write().unwrap();
+        let team = registration.team.filter(|t| !t.is_empty());
+        let token = registration.team_token;
+        let next_id = ledger.get_vendors().len();
+        if let Err(e) = team.as_ref().map_or(Ok(()), |t| ledger.check_team(next_id, Some(t), token.as_ref())) {
+            output_vars.insert("team".to_string(), Box::new(super::teams::team_error(e)));
+            return super::util::construct_json(&output_vars)
+        }
         match ledger.register_vendor(registration.vendor_name, url) {
-            Ok(uuid) => output_vars.insert("uuid".to_string(), Box::new(uuid)),
+            Ok(uuid) => {
+                if team.is_some() {
+                    let vendor_id = ledger.verify_uuid(uuid.clone()).unwrap();
+                    if let Ok(Some(token)) = ledger.set_team(vendor_id, team) {
+                        output_vars.insert("team_token".to_string(), Box::new(token));
+                    }
+                }
+                output_vars.insert("uuid".to_string(), Box::new(uuid))
+            },
+            Err(super::ledger::LedgerError::SessionClosed) => output_vars.insert("session".to_string(), Box::new("has ended")),
             Err(_) => output_vars.insert("error".to_string(), Box::new("registration unsuccessful, aborting"))
         };
         return super::util::construct_json(&output_vars)
     }
-}
\ No newline at end of file
+}
+
+/// Checks that a vendor is not frozen, returning the response for a request
+/// from a frozen vendor
+///
+/// # Arguments
+///
+/// * `ledger`      - The current ledger state
+/// * `vendor_id`   - The ID of the vendor making the request
+pub fn check_frozen(ledger: &Ledger, vendor_id: usize) -> Result<(), BTreeMap<String, Box<dyn Display>>> {
+    if !ledger.is_frozen(vendor_id) { return Ok(()); }
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    output_vars.insert("success".to_string(), Box::new(false));
+    output_vars.insert("UUID".to_string(), Box::new("is frozen".to_string()));
+    Err(output_vars)
+}
+
+/// Checks that trading is open for a vendor, returning the response for a
+/// trading request when it is closed or the vendor is frozen
+///
+/// # Arguments
+///
+/// * `ledger`      - The current ledger state
+/// * `vendor_id`   - The ID of the vendor making the request
+pub fn check_trading(ledger: &Ledger, vendor_id: usize) -> Result<(), BTreeMap<String, Box<dyn Display>>> {
+    if ledger.trading_open_for(vendor_id) { return Ok(()); }
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    output_vars.insert("success".to_string(), Box::new(false));
+    output_vars.insert("session".to_string(), Box::new("trading is closed".to_string()));
+    Err(output_vars)
+}
+
+/// Returns the response for a trading request made with a spectator token,
+/// which may only read the market
+pub fn reject_spectator() -> BTreeMap<String, Box<dyn Display>> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    output_vars.insert("success".to_string(), Box::new(false));
+    output_vars.insert("UUID".to_string(), Box::new("is a read-only spectator token".to_string()));
+    output_vars
+}
+
+/// Returns the response for a request, without a spectator token, for the
+/// items or bits of other vendors in a market with limited visibility
+pub fn reject_hidden() -> BTreeMap<String, Box<dyn Display>> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    output_vars.insert("success".to_string(), Box::new(false));
+    output_vars.insert("visibility".to_string(), Box::new("is limited, only spectators can see this before the session ends".to_string()));
+    output_vars
+}
+
+/// Renders the page shown in place of one revealing the items or bits of
+/// other vendors in a market with limited visibility
+///
+/// # Arguments
+///
+/// * `path`    - The path of the hidden page
+pub fn hidden_page(path: &str) -> Template {
+    let mut map = super::HashMap::new();
+    map.insert("path", path);
+    Template::render("error/403", map)
+}
+
+/// The endpoint for issuing spectator tokens. A spectator token can read the
+/// full state of the market through endpoints such as `/api/ledger_state` and
+/// `/api/entries`, but cannot stock or purchase. Requires the admin secret
+/// 
+/// # Arguments
+/// 
+/// * `ledger`  - The current ledger state
+#[post("/spectate")]
+pub fn spectate(_admin: Admin, ledger: MarketLedger) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let token = ledger.inner().session_ledger.read().unwrap().add_spectator();
+    output_vars.insert("token".to_string(), Box::new(token));
+    super::util::construct_json(&output_vars)
+}
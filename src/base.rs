@@ -1,7 +1,7 @@
-use rocket::State;
+use super::markets::MarketLedger;
 use rocket_contrib::templates::Template;
 use serde_json::to_value;
-use super::ledger::MutLedger;
+use super::session::Phase;
 
 /// 400 error catcher
 /// 
@@ -15,6 +15,18 @@ pub fn bad_request(req: &super::Request<'_>) -> Template {
     Template::render("error/400", &map)
 }
 
+/// 401 error catcher
+/// 
+/// # Arguments
+/// 
+/// * `req` - The current http request information
+#[catch(401)]
+pub fn unauthorized(req: &super::Request<'_>) -> Template {
+    let mut map = super::HashMap::new();
+    map.insert("path", req.uri().path());
+    Template::render("error/401", &map)
+}
+
 /// Index page for the application. Contains overview information for the
 /// current market state and price distribution.
 /// 
@@ -22,7 +34,7 @@ pub fn bad_request(req: &super::Request<'_>) -> Template {
 /// 
 /// * `ledger`  - The current ledger state
 #[get("/")]
-pub fn index(ledger: State<MutLedger>) -> Template {
+pub fn index(ledger: MarketLedger) -> Template {
     let mut map = super::HashMap::new();
     let arc_ledger = ledger.inner().session_ledger.clone();
     let ledger = &*arc_ledger.read().unwrap();
@@ -32,9 +44,25 @@ pub fn index(ledger: State<MutLedger>) -> Template {
     types.sort();
     map.insert("types", to_value(types).unwrap());
     map.insert("history", to_value(ledger.get_price_history()).unwrap());
-    map.insert("ticker_items", to_value(vec!["Welcome to CodeMarket!".to_string(), 
-                                             "Your one-stop shop for types from all over the Internet!".to_string(), 
-                                             "Don't forget to inform your local ledger with every purchase!".to_string()]).unwrap());
+    map.insert("indices", to_value(ledger.get_price_indices()).unwrap());
+    let mut ticker_items = vec!["Welcome to CodeMarket!".to_string(), 
+                                "Your one-stop shop for types from all over the Internet!".to_string(), 
+                                "Don't forget to inform your local ledger with every purchase!".to_string()];
+    for event in ledger.get_active_events() {
+        ticker_items.push(event.description);
+    }
+    for halt in ledger.get_active_halts() {
+        ticker_items.push(halt.describe());
+    }
+    let session = ledger.get_session();
+    match session.phase {
+        Phase::Lobby => ticker_items.push(format!("Trading opens once {} vendors have registered!", session.min_vendors)),
+        Phase::Ended => if let Some(winner) = ledger.get_standings().first() {
+            ticker_items.push(format!("The session is over! {} wins with a net worth of {:.2} bits", winner.vendor, winner.net_worth));
+        },
+        Phase::Running => {}
+    }
+    map.insert("ticker_items", to_value(ticker_items).unwrap());
     Template::render("index", &map)
 }
 
@@ -0,0 +1,47 @@
+use rocket::response::content;
+use super::markets::MarketLedger;
+use serde_json::to_string;
+
+//Trading halt on an item after its average price moved more than the
+//`circuit_breaker` fraction in one step, lifted once the ledger reaches `until`
+#[derive(Clone, Debug, Serialize)]
+pub struct Halt {
+    pub item: String,
+    pub before: f64,
+    pub after: f64,
+    pub from: u32,
+    pub until: u32
+}
+
+impl Halt {
+    /// Describes the halt for announcements on the ticker
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current halt
+    pub fn describe(&self) -> String {
+        format!("Circuit breaker! {} trading halted until version {} after moving from {:.2} to {:.2}", self.item, self.until, self.before, self.after)
+    }
+}
+
+/// Checks a price against the band around a reference price. Prices may be at
+/// most `band` times above or below the reference, and anything goes while
+/// there is no reference price or no band
+///
+/// # Arguments
+///
+/// * `price`       - The proposed price
+/// * `reference`   - The reference price for the item
+/// * `band`        - The allowed multiple of the reference price
+pub fn within_band(price: f64, reference: f64, band: f64) -> bool {
+    if band <= 1.0 || reference <= 0.0 { return true; }
+    price >= reference / band && price <= reference * band
+}
+
+/// Endpoint to get every trading halt still in effect via http request
+#[get("/halts")]
+pub fn request_halts(ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    content::Json(to_string(&ledger.get_active_halts()).unwrap())
+}
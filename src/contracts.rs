@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use rocket::response::content;
+use rocket::request::{Form, FormError};
+use super::markets::MarketLedger;
+use serde_json::to_string;
+use super::ledger::{LedgerError, UUID};
+use super::util::{self, HasUuid};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Interval {
+    Versions,
+    Seconds
+}
+
+impl Interval {
+    /// Parses the delivery interval unit of a contract proposal
+    ///
+    /// # Arguments
+    ///
+    /// * `unit`    - The interval unit, `versions` or `seconds`
+    pub fn parse(unit: &str) -> Option<Interval> {
+        match unit {
+            "versions" => Some(Interval::Versions),
+            "seconds" => Some(Interval::Seconds),
+            _ => None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractState {
+    Proposed,
+    Active,
+    Completed,
+    Cancelled
+}
+
+//Standing agreement for the seller to deliver `count` units to the buyer
+//every `every` versions or seconds at a fixed unit price. Each missing unit
+//costs the seller `penalty` bits, paid to the buyer
+#[derive(Clone, Debug, Serialize)]
+pub struct Contract {
+    pub id: usize,
+    pub seller: String,
+    pub buyer: String,
+    pub item: String,
+    pub count: u32,
+    pub price: f64,
+    pub every: u64,
+    pub interval: Interval,
+    pub penalty: f64,
+    pub remaining: u32,
+    pub due: u64,
+    pub delivered: u32,
+    pub defaulted: u32,
+    pub state: ContractState
+}
+
+//Contract proposal from a buyer to the seller named in `from`
+#[derive(Debug, FromForm)]
+pub struct ContractProposal {
+    pub item: String,
+    pub count: u32,
+    pub price: f64,
+    pub every: u64,
+    pub interval: String,
+    pub deliveries: u32,
+    pub penalty: f64,
+    pub from: String,
+    pub uuid: String
+}
+
+#[derive(Debug, FromForm)]
+pub struct AuthContract {
+    pub contract: usize,
+    pub uuid: String
+}
+
+/// Converts a ledger error from a contract operation into a readable message
+///
+/// # Arguments
+///
+/// * `err` - The error returned by the ledger
+fn contract_error(err: LedgerError) -> String {
+    match err {
+        LedgerError::InvalidContract => "not found or not allowed".to_string(),
+        LedgerError::InvalidVendor => "seller not found".to_string(),
+        LedgerError::InvalidAmount => "terms must be positive numbers".to_string(),
+        _ => "could not be completed".to_string()
+    }
+}
+
+/// Formats the result of a contract operation with the contract ID
+///
+/// # Arguments
+///
+/// * `result`  - The result of the contract operation
+fn contract_output(result: Result<usize, LedgerError>) -> BTreeMap<String, Box<dyn Display>> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    match result {
+        Ok(id) => {
+            output_vars.insert("success".to_string(), Box::new(true));
+            output_vars.insert("contract".to_string(), Box::new(id));
+        },
+        Err(e) => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("contract".to_string(), Box::new(contract_error(e)));
+        }
+    }
+    output_vars
+}
+
+impl HasUuid for ContractProposal { fn uuid(&self) -> &str { &self.uuid } }
+impl HasUuid for AuthContract { fn uuid(&self) -> &str { &self.uuid } }
+
+/// Endpoint for buyers to propose a supply contract to a seller via http
+/// request. The interval is either `versions` or `seconds`
+///
+/// # Arguments
+///
+/// * `proposal`    - The DTO for the contract proposal
+/// * `ledger`      - The current ledger state
+#[post("/contracts/propose", data="<proposal>")]
+pub fn http_propose(proposal: Result<Form<ContractProposal>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(proposal, ledger, |ledger, id, proposal| contract_output(ledger.propose_contract(id, proposal)))
+}
+
+/// Endpoint for sellers to accept a proposed contract via http request. The
+/// first delivery is due one interval after acceptance
+///
+/// # Arguments
+///
+/// * `auth_contract`   - The DTO naming the contract
+/// * `ledger`          - The current ledger state
+#[post("/contracts/accept", data="<auth_contract>")]
+pub fn http_accept(auth_contract: Result<Form<AuthContract>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(auth_contract, ledger, |ledger, id, c| contract_output(ledger.accept_contract(id, c.contract)))
+}
+
+/// Endpoint for either party to withdraw a contract that has not been
+/// accepted yet via http request
+///
+/// # Arguments
+///
+/// * `auth_contract`   - The DTO naming the contract
+/// * `ledger`          - The current ledger state
+#[post("/contracts/cancel", data="<auth_contract>")]
+pub fn http_cancel(auth_contract: Result<Form<AuthContract>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(auth_contract, ledger, |ledger, id, c| contract_output(ledger.cancel_contract(id, c.contract)))
+}
+
+/// Endpoint to get every contract the vendor is party to via http request
+///
+/// # Arguments
+///
+/// * `uuid`    - The unique user ID of the vendor
+/// * `ledger`  - The current ledger state
+#[post("/contracts", data="<uuid>")]
+pub fn request_contracts(uuid: Result<Form<UUID>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let uuid = match uuid {
+        Ok(u) => u.into_inner().uuid,
+        Err(_) => {
+            output_vars.insert("Form".to_string(), Box::new("incorrectly formatted".to_string()));
+            return util::construct_json(&output_vars);
+        }
+    };
+    if let Err(limited) = super::limits::admit(ledger.inner(), &uuid, super::limits::Call::Read) {
+        return util::construct_json(&limited);
+    }
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    match ledger.verify_uuid(uuid) {
+        Ok(id) => content::Json(to_string(&ledger.get_vendor_contracts(id)).unwrap()),
+        Err(_) => {
+            output_vars.insert("UUID".to_string(), Box::new("not found".to_string()));
+            util::construct_json(&output_vars)
+        }
+    }
+}
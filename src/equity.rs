@@ -0,0 +1,186 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use rocket::response::content;
+use rocket::request::{Form, FormError};
+use super::markets::MarketLedger;
+use serde_json::{to_string, to_value};
+use super::ledger::LedgerError;
+use super::util::{self, HasUuid};
+
+//Shares of an issuing vendor held by a vendor, issuers hold their unsold
+//shares themselves
+#[derive(Clone, Debug, Serialize)]
+pub struct Holding {
+    pub issuer: String,
+    pub holder: String,
+    pub count: u32
+}
+
+//Standing offer to sell held shares at a fixed price per share
+#[derive(Clone, Debug, Serialize)]
+pub struct ShareOffer {
+    pub issuer: String,
+    pub seller: String,
+    pub count: u32,
+    pub price: f64
+}
+
+#[derive(Debug, FromForm)]
+pub struct AuthIssue {
+    pub count: u32,
+    pub price: f64,
+    pub uuid: String
+}
+
+#[derive(Debug, FromForm)]
+pub struct AuthShareOffer {
+    pub issuer: String,
+    pub count: u32,
+    pub price: f64,
+    pub uuid: String
+}
+
+#[derive(Debug, FromForm)]
+pub struct ShareOrder {
+    pub issuer: String,
+    pub count: u32,
+    pub from: String,
+    pub uuid: String
+}
+
+#[derive(Debug, FromForm)]
+pub struct AuthDividend {
+    pub amount: f64,
+    pub uuid: String
+}
+
+/// Converts a ledger error from a share operation into a readable message
+///
+/// # Arguments
+///
+/// * `err` - The error returned by the ledger
+fn share_error(err: LedgerError) -> String {
+    match err {
+        LedgerError::InsufficientFunds => "cannot be afforded".to_string(),
+        LedgerError::InsufficientStock => "not enough shares available".to_string(),
+        LedgerError::InvalidAmount => "counts and prices must be positive numbers".to_string(),
+        LedgerError::InvalidVendor => "vendor not found".to_string(),
+        _ => "could not be completed".to_string()
+    }
+}
+
+/// Formats the result of a share operation, with the bits that changed hands
+/// for operations that move bits
+///
+/// # Arguments
+///
+/// * `result`  - The result of the share operation
+fn share_output(result: Result<Option<f64>, LedgerError>) -> BTreeMap<String, Box<dyn Display>> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    match result {
+        Ok(total) => {
+            output_vars.insert("success".to_string(), Box::new(true));
+            if let Some(total) = total { output_vars.insert("total".to_string(), Box::new(total)); }
+        },
+        Err(e) => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("shares".to_string(), Box::new(share_error(e)));
+        }
+    }
+    output_vars
+}
+
+impl HasUuid for AuthIssue { fn uuid(&self) -> &str { &self.uuid } }
+impl HasUuid for AuthShareOffer { fn uuid(&self) -> &str { &self.uuid } }
+impl HasUuid for ShareOrder { fn uuid(&self) -> &str { &self.uuid } }
+impl HasUuid for AuthDividend { fn uuid(&self) -> &str { &self.uuid } }
+
+/// Endpoint for issuing new shares of the calling vendor via http request.
+/// The shares are held by the issuer and offered for sale at the given price
+///
+/// # Arguments
+///
+/// * `issue`   - The DTO for the share issue
+/// * `ledger`  - The current ledger state
+#[post("/shares/issue", data="<issue>")]
+pub fn http_issue(issue: Result<Form<AuthIssue>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(issue, ledger, |ledger, id, issue| share_output(ledger.issue_shares(id, issue.count, issue.price).map(|_| None)))
+}
+
+/// Endpoint for offering held shares for sale via http request. A count of
+/// zero withdraws the offer
+///
+/// # Arguments
+///
+/// * `offer`   - The DTO for the share offer
+/// * `ledger`  - The current ledger state
+#[post("/shares/offer", data="<offer>")]
+pub fn http_offer(offer: Result<Form<AuthShareOffer>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(offer, ledger, |ledger, id, offer| share_output(ledger.offer_shares(id, offer.issuer, offer.count, offer.price).map(|_| None)))
+}
+
+/// Endpoint for buying offered shares from a holder via http request
+///
+/// # Arguments
+///
+/// * `order`   - The DTO for the share order
+/// * `ledger`  - The current ledger state
+#[post("/shares/buy", data="<order>")]
+pub fn http_buy(order: Result<Form<ShareOrder>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(order, ledger, |ledger, id, order| share_output(ledger.buy_shares(id, order.issuer, order.from, order.count).map(Some)))
+}
+
+/// Endpoint for paying a dividend per share to every other shareholder via
+/// http request
+///
+/// # Arguments
+///
+/// * `dividend`    - The DTO for the dividend
+/// * `ledger`      - The current ledger state
+#[post("/shares/dividend", data="<dividend>")]
+pub fn http_dividend(dividend: Result<Form<AuthDividend>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(dividend, ledger, |ledger, id, dividend| share_output(ledger.pay_dividend(id, dividend.amount).map(Some)))
+}
+
+/// Endpoint to get every shareholding, share offer and last traded share
+/// price via http request
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/shares?<token>")]
+pub fn request_shares(token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) {
+        return util::construct_json(&super::authorization::reject_hidden());
+    }
+    let mut shares = BTreeMap::new();
+    shares.insert("holdings", to_value(ledger.get_holdings()).unwrap());
+    shares.insert("offers", to_value(ledger.get_share_offers()).unwrap());
+    shares.insert("prices", to_value(ledger.get_share_prices()).unwrap());
+    content::Json(to_string(&shares).unwrap())
+}
+
+/// Endpoint to get the net worth of every vendor via http request, counting
+/// bits, items at their average price and shares at their last traded price
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/net_worth?<token>")]
+pub fn request_net_worth(token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) {
+        return util::construct_json(&super::authorization::reject_hidden());
+    }
+    let worth: BTreeMap<String, f64> = ledger.get_vendor_names().into_iter()
+                                             .enumerate()
+                                             .map(|(i, name)| (name, ledger.net_worth(i)))
+                                             .collect();
+    content::Json(to_string(&worth).unwrap())
+}
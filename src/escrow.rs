@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use rocket::response::content;
+use rocket::request::{Form, FormError};
+use super::markets::MarketLedger;
+use serde_json::to_string;
+use super::ledger::{Ledger, LedgerError, UUID};
+use super::util::{self, HasUuid};
+
+//How purchased units reach the buyer. Escrowed units are released after
+//`escrow_delay` versions, shipped units once the seller confirms shipment
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Delivery {
+    Instant,
+    Escrow,
+    Shipment
+}
+
+impl Delivery {
+    /// Parses the delivery mode of a purchase order, defaulting to instant
+    ///
+    /// # Arguments
+    ///
+    /// * `mode`    - The optional delivery mode from the order
+    pub fn parse(mode: &Option<String>) -> Option<Delivery> {
+        match mode.as_deref() {
+            None | Some("") | Some("instant") => Some(Delivery::Instant),
+            Some("escrow") => Some(Delivery::Escrow),
+            Some("shipment") => Some(Delivery::Shipment),
+            Some(_) => None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EscrowState {
+    Held,
+    Delivered,
+    Cancelled
+}
+
+//Purchased units and their payment, held by the market until delivery
+#[derive(Clone, Debug, Serialize)]
+pub struct Escrow {
+    pub id: usize,
+    pub seller: String,
+    pub buyer: String,
+    pub item: String,
+    pub count: u32,
+    pub price: f64,
+    pub delivery: Delivery,
+    pub release: Option<u32>,
+    pub state: EscrowState
+}
+
+impl Escrow {
+    /// Returns true if the vendor may cancel this escrow. Sellers may cancel
+    /// anything still held, buyers only shipments the seller has not confirmed
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current escrow object
+    /// * `vendor`  - The name of the vendor asking to cancel
+    pub fn cancellable_by(&self, vendor: &str) -> bool {
+        self.state == EscrowState::Held &&
+        (self.seller == vendor || (self.buyer == vendor && self.delivery == Delivery::Shipment))
+    }
+}
+
+#[derive(Debug, FromForm)]
+pub struct AuthEscrow {
+    pub escrow: usize,
+    pub uuid: String
+}
+
+/// Converts a ledger error from an escrow operation into a readable message
+///
+/// # Arguments
+///
+/// * `err` - The error returned by the ledger
+fn escrow_error(err: LedgerError) -> String {
+    match err {
+        LedgerError::InvalidEscrow => "not found or not allowed".to_string(),
+        _ => "could not be completed".to_string()
+    }
+}
+
+/// Endpoint to get every escrow the vendor is buying or selling in via http
+/// request
+///
+/// # Arguments
+///
+/// * `uuid`    - The unique user ID of the vendor
+/// * `ledger`  - The current ledger state
+#[post("/escrow", data="<uuid>")]
+pub fn request_escrow(uuid: Result<Form<UUID>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let uuid = match uuid {
+        Ok(u) => u.into_inner().uuid,
+        Err(_) => {
+            output_vars.insert("Form".to_string(), Box::new("incorrectly formatted".to_string()));
+            return util::construct_json(&output_vars);
+        }
+    };
+    if let Err(limited) = super::limits::admit(ledger.inner(), &uuid, super::limits::Call::Read) {
+        return util::construct_json(&limited);
+    }
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    match ledger.verify_uuid(uuid) {
+        Ok(id) => content::Json(to_string(&ledger.get_vendor_escrow(id)).unwrap()),
+        Err(_) => {
+            output_vars.insert("UUID".to_string(), Box::new("not found".to_string()));
+            util::construct_json(&output_vars)
+        }
+    }
+}
+
+/// Applies a seller shipment confirmation or a cancellation to an escrow
+///
+/// # Arguments
+///
+/// * `ledger`      - The current ledger state
+/// * `vendor_id`   - The ID of the vendor changing the escrow
+/// * `escrow`      - The ID of the escrow being changed
+/// * `ship`        - True to confirm shipment, false to cancel
+fn settle(ledger: &mut Ledger, vendor_id: usize, escrow: usize, ship: bool) -> BTreeMap<String, Box<dyn Display>> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let result = match ship {
+        true => ledger.ship_escrow(vendor_id, escrow),
+        false => ledger.cancel_escrow(vendor_id, escrow)
+    };
+    match result {
+        Ok(_) => { output_vars.insert("success".to_string(), Box::new(true)); },
+        Err(e) => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("escrow".to_string(), Box::new(escrow_error(e)));
+        }
+    }
+    output_vars
+}
+
+impl HasUuid for AuthEscrow { fn uuid(&self) -> &str { &self.uuid } }
+
+/// Endpoint for sellers to confirm shipment of escrowed units, delivering
+/// them to the buyer, via http request
+///
+/// # Arguments
+///
+/// * `auth_escrow` - The DTO naming the escrow to ship
+/// * `ledger`      - The current ledger state
+#[post("/escrow/ship", data="<auth_escrow>")]
+pub fn http_ship(auth_escrow: Result<Form<AuthEscrow>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(auth_escrow, ledger, |ledger, id, e| settle(ledger, id, e.escrow, true))
+}
+
+/// Endpoint for cancelling a held escrow via http request. The buyer is
+/// refunded and the units go back to the seller's stock
+///
+/// # Arguments
+///
+/// * `auth_escrow` - The DTO naming the escrow to cancel
+/// * `ledger`      - The current ledger state
+#[post("/escrow/cancel", data="<auth_escrow>")]
+pub fn http_cancel(auth_escrow: Result<Form<AuthEscrow>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(auth_escrow, ledger, |ledger, id, e| settle(ledger, id, e.escrow, false))
+}
@@ -0,0 +1,82 @@
+use rand::Rng;
+use rocket::response::content;
+use super::markets::MarketLedger;
+use serde_json::to_string;
+
+//A shock to the market, applied to the ledger when its event fires
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Shock {
+    SupplyGlut { item: String, count: u32 },
+    DemandSpike { item: String, count: u32, premium: f64 },
+    FeeHoliday,
+    PriceFreeze { item: String }
+}
+
+impl Shock {
+    /// Generates a random shock for one of the given items
+    ///
+    /// # Arguments
+    ///
+    /// * `items`   - The items currently tracked by the ledger
+    /// * `rng`     - The random number generator of the market
+    pub fn random<R: Rng>(items: &[String], rng: &mut R) -> Shock {
+        if items.is_empty() { return Shock::FeeHoliday; }
+        let item = items[rng.gen_range(0, items.len())].clone();
+        match rng.gen_range(0, 4) {
+            0 => Shock::SupplyGlut { item, count: rng.gen_range(5, 50) },
+            1 => Shock::DemandSpike { item, count: rng.gen_range(1, 10), premium: rng.gen_range(0.1, 1.0) },
+            2 => Shock::FeeHoliday,
+            _ => Shock::PriceFreeze { item }
+        }
+    }
+
+    /// Describes the shock for announcements on the ticker
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current shock
+    pub fn describe(&self) -> String {
+        match self {
+            Shock::SupplyGlut { item, count } => format!("Supply glut! Every {} vendor receives {} extra units", item, count),
+            Shock::DemandSpike { item, count, premium } => format!("Demand spike! The market buys up to {} stocked {} from each vendor at a {:.0}% premium", count, item, premium * 100.0),
+            Shock::FeeHoliday => "Fee holiday! Market fees are waived".to_string(),
+            Shock::PriceFreeze { item } => format!("Price freeze! {} prices cannot be changed", item)
+        }
+    }
+}
+
+//An event from `Config.toml`, fires once the ledger reaches `version` or
+//`seconds` have passed since the ledger was created, whichever comes first
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ScheduledEvent {
+    pub version: Option<u32>,
+    pub seconds: Option<u64>,
+    pub duration: Option<u32>,
+    pub shock: Shock
+}
+
+//An event that has fired, active until the ledger reaches `expires`
+#[derive(Clone, Debug, Serialize)]
+pub struct MarketEvent {
+    pub id: usize,
+    pub shock: Shock,
+    pub description: String,
+    pub fired: u32,
+    pub expires: u32
+}
+
+/// Endpoint to get every fired market event via http request, with whether
+/// the event is still in effect
+#[get("/events")]
+pub fn request_events(ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    let version = ledger.get_version();
+    let events: Vec<serde_json::Value> = ledger.get_events().iter().map(|e| {
+        let mut value = serde_json::to_value(e).unwrap();
+        value["active"] = serde_json::Value::Bool(e.expires > version);
+        value
+    }).collect();
+    content::Json(to_string(&events).unwrap())
+}
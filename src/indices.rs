@@ -0,0 +1,49 @@
+use rocket::response::content;
+use super::markets::MarketLedger;
+use serde_json::to_string;
+use super::util;
+
+/// Computes a price index over a group of items from their price history. At
+/// every step the index is 100 times the mean ratio of each item's average
+/// price to the first price it traded at, and it holds its last value while
+/// none of the items have a price
+///
+/// # Arguments
+///
+/// * `history` - The average price history of every item in the catalog
+/// * `members` - The catalog indices of the items in the group
+pub fn price_index(history: &[Vec<f64>], members: &[usize]) -> Vec<f64> {
+    let valid = |p: f64| p.is_finite() && p > 0.0;
+    let steps = history.iter().map(|h| h.len()).max().unwrap_or(0);
+    let mut bases: Vec<Option<f64>> = vec![None; members.len()];
+    let mut index = Vec::with_capacity(steps);
+    let mut last = 100.0;
+    for t in 0..steps {
+        let mut ratios = vec![];
+        for (m, &item) in members.iter().enumerate() {
+            let price = match history[item].get(t) { Some(&p) if valid(p) => p, _ => continue };
+            let base = *bases[m].get_or_insert(price);
+            ratios.push(price / base);
+        }
+        if !ratios.is_empty() {
+            last = 100.0 * ratios.iter().sum::<f64>() / ratios.len() as f64;
+        }
+        index.push(last);
+    }
+    index
+}
+
+/// Endpoint to get the items in each category via http request
+#[get("/categories")]
+pub fn request_categories() -> content::Json<String> {
+    content::Json(to_string(&util::get_categories()).unwrap())
+}
+
+/// Endpoint to get the price index history of the whole market and of every
+/// category via http request
+#[get("/indices")]
+pub fn request_indices(ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    content::Json(to_string(&ledger.get_price_indices()).unwrap())
+}
@@ -0,0 +1,75 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use rocket::response::content;
+use rocket::request::{Form, FormError};
+use serde_json::to_string;
+use super::authorization;
+use super::ledger::LedgerError;
+use super::limits::Call;
+use super::markets::MarketLedger;
+use super::util::{self, HasUuid};
+
+//What an intel report is on, the position of a vendor or the name of an item
+#[derive(Clone, Debug)]
+pub enum Intel {
+    Vendor(usize),
+    Item(String)
+}
+
+//Request for an intel report, naming either a vendor or an item
+#[derive(Debug, FromForm)]
+pub struct AuthIntel {
+    pub vendor: Option<String>,
+    pub item: Option<String>,
+    pub uuid: String
+}
+
+impl HasUuid for AuthIntel { fn uuid(&self) -> &str { &self.uuid } }
+
+/// Endpoint for buying an intel report on a vendor or an item via http
+/// request. The report has the same layout as the ledger state and costs
+/// `intel_cost` bits
+///
+/// # Arguments
+///
+/// * `auth_intel`  - The DTO naming the vendor or item
+/// * `ledger`      - The current ledger state
+#[post("/intel", data="<auth_intel>")]
+pub fn http_buy(auth_intel: Result<Form<AuthIntel>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_call(auth_intel, ledger, Call::Read, authorization::check_frozen, |ledger, vendor_id, auth_intel| {
+        let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+        let target = match (auth_intel.vendor, auth_intel.item) {
+            (Some(name), None) => match ledger.find_vendor(&name) {
+                Some(pos) => Intel::Vendor(pos),
+                None => {
+                    output_vars.insert("success".to_string(), Box::new(false));
+                    output_vars.insert("vendor".to_string(), Box::new("not found".to_string()));
+                    return util::construct_json(&output_vars);
+                }
+            },
+            (None, Some(item)) if util::get_rust_types().contains(&item.as_str()) => Intel::Item(item),
+            (None, Some(_)) => {
+                output_vars.insert("success".to_string(), Box::new(false));
+                output_vars.insert("item".to_string(), Box::new("not found".to_string()));
+                return util::construct_json(&output_vars);
+            },
+            _ => {
+                output_vars.insert("success".to_string(), Box::new(false));
+                output_vars.insert("intel".to_string(), Box::new("name either a vendor or an item".to_string()));
+                return util::construct_json(&output_vars);
+            }
+        };
+        match ledger.buy_intel(vendor_id, target) {
+            Ok(report) => content::Json(to_string(&report).unwrap()),
+            Err(e) => {
+                output_vars.insert("success".to_string(), Box::new(false));
+                output_vars.insert("bits".to_string(), Box::new(match e {
+                    LedgerError::InsufficientFunds => "not enough bits for the report",
+                    _ => "could not be completed"
+                }));
+                util::construct_json(&output_vars)
+            }
+        }
+    })
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+
+use rocket::response::content;
+use super::markets::MarketLedger;
+use rocket_contrib::templates::Template;
+use serde_json::{to_string, to_value};
+
+//A vendor's standing on the leaderboard, with its rank by each measure
+#[derive(Clone, Debug, Serialize)]
+pub struct LeaderboardRow {
+    pub vendor: String,
+    pub bits: f64,
+    pub net_worth: f64,
+    pub volume: f64,
+    pub bits_rank: usize,
+    pub net_worth_rank: usize,
+    pub volume_rank: usize
+}
+
+//Ranking of the vendors by net worth, recorded whenever the order changes
+#[derive(Clone, Debug, Serialize)]
+pub struct RankChange {
+    pub version: u32,
+    pub ranking: Vec<String>
+}
+
+/// Ranks values from highest to lowest, returning the rank of each value in
+/// its original position
+///
+/// # Arguments
+///
+/// * `values`  - The values to rank
+pub fn ranks(values: &[f64]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[b].partial_cmp(&values[a]).unwrap_or(std::cmp::Ordering::Equal));
+    let mut retval = vec![0; values.len()];
+    for (rank, &i) in order.iter().enumerate() {
+        retval[i] = rank + 1;
+    }
+    retval
+}
+
+/// Endpoint to get the leaderboard and the history of rank changes via http
+/// request
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/leaderboard?<token>")]
+pub fn request_leaderboard(token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) {
+        return super::util::construct_json(&super::authorization::reject_hidden());
+    }
+    let mut leaderboard = BTreeMap::new();
+    leaderboard.insert("vendors", to_value(ledger.get_leaderboard()).unwrap());
+    leaderboard.insert("history", to_value(ledger.get_rank_history()).unwrap());
+    content::Json(to_string(&leaderboard).unwrap())
+}
+
+/// Page showing the leaderboard, ordered by net worth, and the rank changes
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/leaderboard?<token>")]
+pub fn leaderboard_page(token: Option<String>, ledger: MarketLedger) -> Template {
+    let mut map = super::HashMap::new();
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) { return super::authorization::hidden_page("/leaderboard"); }
+    let mut rows = ledger.get_leaderboard();
+    rows.sort_by_key(|r| r.net_worth_rank);
+    let mut history = ledger.get_rank_history();
+    history.reverse();
+    map.insert("rows", to_value(rows).unwrap());
+    map.insert("history", to_value(history).unwrap());
+    Template::render("leaderboard", &map)
+}
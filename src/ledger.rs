@@ -1,20 +1,58 @@
 use rocket::response::content;
 use rocket::request::{Form, FormError};
-use rocket::State;
+use super::markets::MarketLedger;
+use config::Config;
+use rand::Rng;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::de::DeserializeOwned;
 use serde_json::to_string;
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Display;
 use std::sync::{Arc, RwLock};
-use super::shop::{Vendor, Item};
+use std::time::Instant;
+use super::breakers::{self, Halt};
+use super::contracts::{Contract, ContractProposal, ContractState, Interval};
+use super::equity::{Holding, ShareOffer};
+use super::escrow::{Delivery, Escrow, EscrowState};
+use super::events::{MarketEvent, ScheduledEvent, Shock};
+use super::indices;
+use super::intel::Intel;
+use super::limits::{Call, Limited, Usage};
+use super::leaderboard::{self, LeaderboardRow, RankChange};
+use super::objectives::{Goal, Objective, Progress};
+use super::replay::ReplayVendor;
+use super::reputation::Reputation;
+use super::session::{Phase, Session, Standing};
+use super::shop::{self, Vendor, Item, Tier};
+use super::short::{LendOffer, Loan};
+use super::teams::Team;
+use super::turns::{Action, QueuedAction, Turn, TurnResult};
 use super::{nanoid, util};
 
 #[derive(Debug)]
 pub enum LedgerError {
     ExistingVendor,
     ExistingUrl,
-    InvalidVendor
+    InsufficientFunds,
+    InsufficientStock,
+    InvalidAmount,
+    InvalidContract,
+    InvalidEscrow,
+    InvalidLoan,
+    InvalidTeam,
+    InvalidToken,
+    InvalidVendor,
+    NoReferencePrice,
+    OutsidePriceBand,
+    SessionClosed,
+    TradingHalted
 }
 
+//Names, prices and stocked counts of the items of a vendor, in the list
+//structure of the ledger state
+pub type VendorState = (Vec<String>, Vec<f64>, Vec<u32>);
+
 #[derive(FromForm)]
 pub struct UUID {
     pub uuid: String
@@ -27,13 +65,24 @@ struct Entry {
     vendor: String,
     attribute: String,
     change: i32,
-    price: f64
+    price: f64,
+    #[serde(skip)]
+    call: bool
 }
 
 impl Entry {
     fn new(id: u32, vendor: String, attribute: String, change: i32, price: f64) -> Entry {
-        Entry { id, vendor, attribute, change, price }
+        Entry { id, vendor, attribute, change, price, call: false }
+    }
+
+    fn call(id: u32, vendor: String, attribute: String, price: f64) -> Entry {
+        Entry { id, vendor, attribute, change: 0, price, call: true }
     }
+
+    /// Returns true if the entry happened after the given version was
+    /// recorded. Call charges share the version of the change before them,
+    /// so they come after it
+    fn after(&self, version: u32) -> bool { self.id > version || (self.call && self.id == version) }
 }
 
 //Collection of asynchronously mutable data of transactions in the market
@@ -45,23 +94,556 @@ pub struct Ledger {
     entries: RwLock<Vec<Entry>>,
     vendor_ids: RwLock<Vec<String>>,
     vendor_versions: RwLock<Vec<u32>>,
+    registered: RwLock<Vec<u32>>,
+    reputation: RwLock<Vec<Reputation>>,
     ledger_items: RwLock<HashSet<String>>,
-    price_history: RwLock<Vec<Vec<f64>>>
+    price_history: RwLock<Vec<Vec<f64>>>,
+    price_versions: RwLock<Vec<u32>>,
+    loans: RwLock<Vec<Loan>>,
+    lend_offers: RwLock<Vec<LendOffer>>,
+    events: RwLock<Vec<MarketEvent>>,
+    escrow: RwLock<Vec<Escrow>>,
+    halts: RwLock<Vec<Halt>>,
+    contracts: RwLock<Vec<Contract>>,
+    holdings: RwLock<Vec<Holding>>,
+    share_offers: RwLock<Vec<ShareOffer>>,
+    share_prices: RwLock<HashMap<String, f64>>,
+    scheduled_events: RwLock<Vec<ScheduledEvent>>,
+    objectives: Vec<Objective>,
+    session: RwLock<Session>,
+    rank_history: RwLock<Vec<RankChange>>,
+    turn: RwLock<Turn>,
+    paused: bool,
+    frozen: RwLock<HashSet<String>>,
+    #[serde(skip)]
+    spectators: RwLock<Vec<String>>,
+    #[serde(skip)]
+    team_tokens: RwLock<HashMap<String, String>>,
+    #[serde(skip)]
+    entrants: RwLock<HashMap<String, String>>,
+    #[serde(skip)]
+    usage: RwLock<HashMap<String, Usage>>,
+    #[serde(skip)]
+    config: Option<Config>,
+    #[serde(skip)]
+    rng: RwLock<StdRng>,
+    #[serde(skip)]
+    started: Instant
 }
 
 impl Ledger {
     pub fn new() -> Ledger {
-        Ledger { 
+        let mut ledger = Ledger { 
             version: 0, 
             entries: RwLock::new(vec![]),
             vendors: RwLock::new(vec![]),
             vendor_ids: RwLock::new(vec![]),
             vendor_versions: RwLock::new(vec![]),
+            registered: RwLock::new(vec![]),
+            reputation: RwLock::new(vec![]),
             ledger_items: RwLock::new(HashSet::new()),
-            price_history: RwLock::new(vec![vec![]; util::get_rust_types(0).len()])
+            price_history: RwLock::new(vec![vec![]; util::get_rust_types().len()]),
+            price_versions: RwLock::new(vec![]),
+            loans: RwLock::new(vec![]),
+            lend_offers: RwLock::new(vec![]),
+            events: RwLock::new(vec![]),
+            escrow: RwLock::new(vec![]),
+            halts: RwLock::new(vec![]),
+            contracts: RwLock::new(vec![]),
+            holdings: RwLock::new(vec![]),
+            share_offers: RwLock::new(vec![]),
+            share_prices: RwLock::new(HashMap::new()),
+            scheduled_events: RwLock::new(super::get_config::<Vec<ScheduledEvent>>("events").unwrap_or_default()),
+            objectives: super::get_config::<Vec<Objective>>("objectives").unwrap_or_default(),
+            session: RwLock::new(Session::from_config()),
+            rank_history: RwLock::new(vec![]),
+            turn: RwLock::new(Turn::new(super::get_config::<u64>("turn_seconds").unwrap_or(0))),
+            paused: false,
+            frozen: RwLock::new(HashSet::new()),
+            spectators: RwLock::new(super::get_config::<Vec<String>>("spectator_tokens").unwrap_or_default()),
+            team_tokens: RwLock::new(HashMap::new()),
+            entrants: RwLock::new(HashMap::new()),
+            usage: RwLock::new(HashMap::new()),
+            config: None,
+            rng: RwLock::new(util::seeded_rng(super::get_config::<u64>("seed"))),
+            started: Instant::now()
+        };
+        ledger.update_session();
+        ledger
+    }
+
+    /// Creates a ledger for a market whose settings override `Config.toml`
+    /// 
+    /// # Arguments
+    /// 
+    /// * `config`  - The settings of the market
+    pub fn with_config(config: Config) -> Ledger {
+        let mut ledger = Ledger::new();
+        ledger.config = Some(config);
+        *ledger.rng.write().unwrap() = util::seeded_rng(ledger.config::<u64>("seed"));
+        *ledger.scheduled_events.write().unwrap() = ledger.config::<Vec<ScheduledEvent>>("events").unwrap_or_default();
+        ledger.objectives = ledger.config::<Vec<Objective>>("objectives").unwrap_or_default();
+        *ledger.session.write().unwrap() = Session::new(
+            ledger.config::<usize>("session_vendors").unwrap_or(0),
+            ledger.config::<u64>("session_seconds").unwrap_or(0),
+            ledger.config::<u32>("session_versions").unwrap_or(0)
+        );
+        *ledger.turn.write().unwrap() = Turn::new(ledger.turn_seconds());
+        *ledger.spectators.write().unwrap() = ledger.config::<Vec<String>>("spectator_tokens").unwrap_or_default();
+        ledger.update_session();
+        ledger
+    }
+
+    /// Accepts a proposed supply contract on the seller's behalf, the first
+    /// delivery is due one interval later
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The location of the seller in the internal vendor list
+    /// * `contract_id` - The ID of the contract to accept
+    pub fn accept_contract(&mut self, vendor_id: usize, contract_id: usize) -> Result<usize, LedgerError> {
+        let name = self.get_vendor(vendor_id).name;
+        let now = (self.version, self.started.elapsed().as_secs());
+        let mut contracts = self.contracts.write().unwrap();
+        match contracts.get_mut(contract_id) {
+            Some(c) if c.seller == name && c.state == ContractState::Proposed => {
+                c.due = c.every + match c.interval {
+                    Interval::Versions => now.0 as u64,
+                    Interval::Seconds => now.1
+                };
+                c.state = ContractState::Active;
+                Ok(contract_id)
+            },
+            _ => Err(LedgerError::InvalidContract)
+        }
+    }
+
+    /// Changes the bits of a vendor by an amount, recording an audit entry,
+    /// and returns the new balance
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The ID of the vendor
+    /// * `amount`      - The change in bits, negative to take bits away
+    pub fn adjust_bits(&mut self, vendor_id: usize, amount: f64) -> Result<f64, LedgerError> {
+        if !amount.is_finite() { return Err(LedgerError::InvalidAmount); }
+        let (name, bits);
+        {
+            let mut vendors = self.vendors.write().unwrap();
+            vendors[vendor_id].bits += amount;
+            name = vendors[vendor_id].name.clone();
+            bits = vendors[vendor_id].bits;
+        }
+        self.record(name, "admin bits".to_string(), 0, amount);
+        Ok(bits)
+    }
+
+    /// Adds units of an item to the store of a vendor, or takes them away from
+    /// the store and then the stock, recording an audit entry. Items the vendor
+    /// does not have yet need a price. Returns the units the vendor now holds
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The ID of the vendor
+    /// * `item`        - The name of the item
+    /// * `count`       - The change in units, negative to take units away
+    /// * `price`       - The price of the item if the vendor does not have it
+    pub fn adjust_items(&mut self, vendor_id: usize, item: String, count: i32, price: Option<f64>) -> Result<u32, LedgerError> {
+        self.check_items(vendor_id, &item, count, price)?;
+        let (name, total);
+        {
+            let mut vendors = self.vendors.write().unwrap();
+            let held = vendors[vendor_id].get_item(&item).map(|i| i.price);
+            match held.or(price) {
+                Some(price) if count > 0 => { vendors[vendor_id].add_item(Item::new(item.clone(), price, 0, count as u32), true); },
+                _ => { vendors[vendor_id].remove_item(&item, count.unsigned_abs()); }
+            }
+            name = vendors[vendor_id].name.clone();
+            total = vendors[vendor_id].get_item(&item).map_or(0, |i| i.total());
+        }
+        self.ledger_items.write().unwrap().insert(item.clone());
+        self.record(name, item, count, 0.0);
+        self.update_avg_price(util::convert_minimal_to_full(self.calculate_avg_prices()));
+        Ok(total)
+    }
+
+    /// Issues a new spectator token, which may read the full state of the
+    /// market but not trade, and returns it
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn add_spectator(&self) -> String {
+        let token = nanoid::simple();
+        self.spectators.write().unwrap().push(token.clone());
+        token
+    }
+
+    /// Borrows units of an item for short selling. The borrower pays a fee to
+    /// the lender and locks collateral proportional to the reference value of
+    /// the units, and the units are moved into the borrower's store
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`            - A mutable reference to the current ledger object
+    /// * `borrower_pos`    - The location of the borrower in the internal vendor list
+    /// * `lender_pos`      - The location of the lender, None for the lending pool
+    /// * `item`            - The name of the item to borrow
+    /// * `count`           - The number of units to borrow
+    pub fn borrow(&mut self, borrower_pos: usize, lender_pos: Option<usize>, item: String, count: u32) -> Result<Loan, LedgerError> {
+        let reference = self.get_reference_price(&item);
+        if reference <= 0.0 { return Err(LedgerError::NoReferencePrice); }
+
+        let value = reference * count as f64;
+        let collateral = value * self.config::<f64>("short_collateral").unwrap_or(1.5);
+        let lender = lender_pos.map(|pos| self.get_vendor(pos).name);
+        let rate = match &lender {
+            Some(name) => {
+                let offers = self.lend_offers.read().unwrap();
+                let offer = offers.iter().find(|o| &o.lender == name && o.item == item && o.count >= count);
+                let stored = self.get_vendor(lender_pos.unwrap()).get_item(&item).map_or(0, |i| i.get_stored());
+                match offer {
+                    Some(o) if stored >= count => o.rate,
+                    _ => return Err(LedgerError::InsufficientStock)
+                }
+            },
+            None => {
+                let pool = self.config::<u32>("lending_pool").unwrap_or(0);
+                let lent: u32 = self.loans.read().unwrap().iter()
+                                    .filter(|l| l.open && l.lender.is_none() && l.item == item)
+                                    .map(|l| l.count)
+                                    .sum();
+                if lent + count > pool { return Err(LedgerError::InsufficientStock); }
+                self.config::<f64>("borrow_fee").unwrap_or(0.05)
+            }
+        };
+        let fee = if self.fees_waived() { 0.0 } else { value * rate };
+
+        let borrower = self.get_vendor(borrower_pos).name;
+        if self.get_vendor(borrower_pos).bits < collateral + fee { return Err(LedgerError::InsufficientFunds); }
+
+        {
+            let mut mut_vendors = self.vendors.write().unwrap();
+            if let Some(pos) = lender_pos {
+                mut_vendors[pos].remove_item(&item, count);
+                mut_vendors[pos].bits += fee;
+                for o in self.lend_offers.write().unwrap().iter_mut().filter(|o| Some(&o.lender) == lender.as_ref() && o.item == item) {
+                    o.count -= count;
+                }
+            }
+            mut_vendors[borrower_pos].add_item(Item::new(item.clone(), reference, 0, count), true);
+            mut_vendors[borrower_pos].bits -= collateral + fee;
+            self.ledger_items.write().unwrap().insert(item.clone());
+        }
+
+        if let Some(name) = &lender {
+            self.record(name.clone(), item.clone(), -(count as i32), fee);
+        }
+        self.record(borrower.clone(), item.clone(), count as i32, -(collateral + fee));
+
+        let mut loans = self.loans.write().unwrap();
+        let loan = Loan { id: loans.len(), lender, borrower, item, count, collateral, fee, open: true };
+        loans.push(loan.clone());
+        Ok(loan)
+    }
+
+    /// Returns the bits a kind of API call costs, set in `call_costs`
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `call`    - The kind of call
+    pub fn call_cost(&self, call: Call) -> f64 { self.config::<f64>(&format!("call_costs.{}", call.name())).unwrap_or(0.0).max(0.0) }
+
+    /// Charges the vendor behind a uuid the cost of an API call, and returns
+    /// the bits charged. The charge is recorded in the ledger at the current
+    /// version without advancing it, so calls do not use up `session_versions`,
+    /// and replaying that version leaves the charge out
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - A mutable reference to the current ledger object
+    /// * `uuid`    - The unique user ID sent with the call
+    /// * `call`    - The kind of call
+    pub fn charge_call(&mut self, uuid: &str, call: Call) -> Result<f64, LedgerError> {
+        let cost = self.call_cost(call);
+        let vendor_id = match self.verify_uuid(uuid.to_string()) {
+            Ok(id) if cost > 0.0 => id,
+            _ => return Ok(0.0)
+        };
+        let name;
+        {
+            let mut vendors = self.vendors.write().unwrap();
+            if vendors[vendor_id].bits < cost { return Err(LedgerError::InsufficientFunds); }
+            vendors[vendor_id].bits -= cost;
+            name = vendors[vendor_id].name.clone();
+        }
+        self.entries.write().unwrap().push(Entry::call(self.version, name, format!("{} call", call.name()), -cost));
+        Ok(cost)
+    }
+
+    /// Checks that a vendor may register under a name. Markets without
+    /// entrants are open to every name, otherwise only an entrant with its
+    /// join token may register
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `name`    - The name the vendor registers under
+    /// * `token`   - The join token of the entrant
+    pub fn check_entrant(&self, name: &str, token: Option<&String>) -> Result<(), LedgerError> {
+        let entrants = self.entrants.read().unwrap();
+        if entrants.is_empty() { return Ok(()); }
+        match entrants.get(name) {
+            None => Err(LedgerError::InvalidVendor),
+            Some(t) if Some(t) != token => Err(LedgerError::InvalidToken),
+            Some(_) => Ok(())
+        }
+    }
+
+    /// Checks that units of an item can be added to or taken away from the
+    /// store of a vendor, the way `adjust_items` would change them
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The ID of the vendor
+    /// * `item`        - The name of the item
+    /// * `count`       - The change in units, negative to take units away
+    /// * `price`       - The price of the item if the vendor does not have it
+    pub fn check_items(&self, vendor_id: usize, item: &str, count: i32, price: Option<f64>) -> Result<(), LedgerError> {
+        if !util::get_rust_types().contains(&item) || count == 0 || price.map_or(false, |p| !p.is_finite() || p < 0.0) {
+            return Err(LedgerError::InvalidAmount);
+        }
+        let held = self.vendors.read().unwrap()[vendor_id].get_item(&item.to_string()).map(|i| (i.price, i.total()));
+        match held {
+            _ if count > 0 && held.is_none() && price.is_none() => Err(LedgerError::InvalidAmount),
+            _ if count > 0 => Ok(()),
+            Some((_, t)) if t >= count.unsigned_abs() => Ok(()),
+            _ => Err(LedgerError::InsufficientStock)
+        }
+    }
+
+    /// Checks that an item can be repriced, it must not be halted and the new
+    /// price must be inside the `price_band` around the reference price
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `item`    - The name of the item
+    /// * `price`   - The proposed price
+    pub fn check_price(&self, item: &str, price: f64) -> Result<(), LedgerError> {
+        if self.is_halted(item) { return Err(LedgerError::TradingHalted); }
+        let band = self.config::<f64>("price_band").unwrap_or(0.0);
+        match breakers::within_band(price, self.get_reference_price(item), band) {
+            true => Ok(()),
+            false => Err(LedgerError::OutsidePriceBand)
+        }
+    }
+
+    /// Checks that a vendor may join or leave a team: teams change until the
+    /// session ends, an existing team takes its join token, and a full team
+    /// takes no one
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The ID of the vendor, or the next ID when registering
+    /// * `team`        - The name of the team to join, None to leave
+    /// * `token`       - The join token of the team
+    pub fn check_team(&self, vendor_id: usize, team: Option<&String>, token: Option<&String>) -> Result<(), LedgerError> {
+        if self.session.read().unwrap().phase == Phase::Ended { return Err(LedgerError::SessionClosed); }
+        if let Some(name) = team {
+            let members = self.team_room(vendor_id, name)?;
+            if members > 0 && token != self.team_tokens.read().unwrap().get(name) { return Err(LedgerError::InvalidToken); }
+        }
+        Ok(())
+    }
+
+    /// Buys an intel report on a vendor or an item for `intel_cost` bits,
+    /// recorded in the ledger. A vendor report lists every item of the vendor
+    /// and an item report lists the offer of every vendor holding the item
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The ID of the vendor buying the report
+    /// * `target`      - The vendor or item the report is on
+    pub fn buy_intel(&mut self, vendor_id: usize, target: Intel) -> Result<HashMap<String, VendorState>, LedgerError> {
+        let cost = self.config::<f64>("intel_cost").unwrap_or(10.0);
+        let mut report = HashMap::new();
+        match &target {
+            Intel::Vendor(pos) => { report.insert(self.get_vendor(*pos).name, self.serialize_items(*pos, None)); },
+            Intel::Item(item) => {
+                for (i, v) in self.get_vendors().iter().enumerate() {
+                    if v.get_item(item).is_some() { report.insert(v.name.clone(), self.serialize_items(i, Some(item))); }
+                }
+            }
+        }
+        let name;
+        {
+            let mut vendors = self.vendors.write().unwrap();
+            if vendors[vendor_id].bits < cost { return Err(LedgerError::InsufficientFunds); }
+            vendors[vendor_id].bits -= cost;
+            name = vendors[vendor_id].name.clone();
+        }
+        let attribute = match target {
+            Intel::Vendor(pos) => format!("intel on {}", self.get_vendor(pos).name),
+            Intel::Item(item) => format!("intel on {}", item)
+        };
+        self.record(name, attribute, 0, -cost);
+        Ok(report)
+    }
+
+    /// Buys shares of an issuer from a holder's offer at the offered price,
+    /// and returns the bits paid
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `buyer_pos`   - The location of the buyer in the internal vendor list
+    /// * `issuer`      - The name of the vendor whose shares are bought
+    /// * `seller`      - The name of the holder selling the shares
+    /// * `count`       - The number of shares to buy
+    pub fn buy_shares(&mut self, buyer_pos: usize, issuer: String, seller: String, count: u32) -> Result<f64, LedgerError> {
+        let buyer = self.get_vendor(buyer_pos);
+        let seller_pos = match self.find_vendor(&seller) { Some(p) if p != buyer_pos => p, _ => return Err(LedgerError::InvalidVendor) };
+        let price = match self.share_offers.read().unwrap().iter().find(|o| o.issuer == issuer && o.seller == seller) {
+            Some(o) if o.count >= count && count > 0 => o.price,
+            _ => return Err(LedgerError::InsufficientStock)
+        };
+        let total = price * count as f64;
+        if buyer.bits < total { return Err(LedgerError::InsufficientFunds); }
+
+        {
+            let mut mut_vendors = self.vendors.write().unwrap();
+            mut_vendors[buyer_pos].bits -= total;
+            mut_vendors[seller_pos].bits += total;
+        }
+        self.move_shares(&issuer, &seller, &buyer.name, count);
+        for o in self.share_offers.write().unwrap().iter_mut().filter(|o| o.issuer == issuer && o.seller == seller) {
+            o.count -= count;
+        }
+        self.share_prices.write().unwrap().insert(issuer.clone(), price);
+
+        let attribute = format!("{} shares", issuer);
+        self.record(seller, attribute.clone(), -(count as i32), total);
+        self.record(buyer.name, attribute, count as i32, -total);
+        Ok(total)
+    }
+
+    /// Withdraws a supply contract that has not been accepted yet, either
+    /// party may cancel
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The location of the cancelling vendor in the internal vendor list
+    /// * `contract_id` - The ID of the contract to cancel
+    pub fn cancel_contract(&mut self, vendor_id: usize, contract_id: usize) -> Result<usize, LedgerError> {
+        let name = self.get_vendor(vendor_id).name;
+        let mut contracts = self.contracts.write().unwrap();
+        match contracts.get_mut(contract_id) {
+            Some(c) if (c.seller == name || c.buyer == name) && c.state == ContractState::Proposed => {
+                c.state = ContractState::Cancelled;
+                Ok(contract_id)
+            },
+            _ => Err(LedgerError::InvalidContract)
+        }
+    }
+
+    /// Cancels a held escrow, refunding the buyer and returning the units to
+    /// the seller's stock
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The location of the cancelling vendor in the internal vendor list
+    /// * `escrow_id`   - The ID of the escrow to cancel
+    pub fn cancel_escrow(&mut self, vendor_id: usize, escrow_id: usize) -> Result<Escrow, LedgerError> {
+        let name = self.get_vendor(vendor_id).name;
+        let escrow = match self.escrow.read().unwrap().get(escrow_id) {
+            Some(e) if e.cancellable_by(&name) => e.clone(),
+            _ => return Err(LedgerError::InvalidEscrow)
+        };
+        let refund = escrow.count as f64 * escrow.price;
+        {
+            let mut mut_vendors = self.vendors.write().unwrap();
+            if let Some(seller) = mut_vendors.iter_mut().find(|v| v.name == escrow.seller) {
+                seller.add_item(Item::new(escrow.item.clone(), escrow.price, escrow.count, 0), false);
+            }
+            if let Some(buyer) = mut_vendors.iter_mut().find(|v| v.name == escrow.buyer) {
+                buyer.bits += refund;
+            }
         }
+        self.record(escrow.seller.clone(), escrow.item.clone(), escrow.count as i32, 0.0);
+        self.record(escrow.buyer.clone(), escrow.item.clone(), 0, refund);
+        self.escrow.write().unwrap()[escrow_id].state = EscrowState::Cancelled;
+        Ok(escrow)
+    }
+
+    /// Returns the bits a vendor may spend beyond their balance, set by
+    /// `reputation_credit` bits of credit per point of reputation
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The location of the vendor in the internal vendor list
+    pub fn credit_limit(&self, vendor_id: usize) -> f64 {
+        let rate = self.config::<f64>("reputation_credit").unwrap_or(0.0);
+        rate.max(0.0) * self.reputation.read().unwrap()[vendor_id].score()
+    }
+
+    /// Ends the session, freezing trading and recording the final standings
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - A mutable reference to the current ledger object
+    pub fn end_session(&mut self) {
+        if self.session.read().unwrap().phase == Phase::Ended { return; }
+        let standings = self.rank_vendors();
+        let objectives = self.track_objectives();
+        let mut session = self.session.write().unwrap();
+        session.phase = Phase::Ended;
+        session.ended_version = Some(self.version);
+        session.standings = standings;
+        session.objectives = objectives;
+    }
+
+    /// Returns true while a fee holiday event is in effect
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn fees_waived(&self) -> bool {
+        self.get_active_events().iter().any(|e| matches!(e.shock, Shock::FeeHoliday))
+    }
+
+    /// Publishes the results of a resolved turn
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `number`  - The number of the resolved turn
+    /// * `results` - The outcome of every action queued for the turn
+    pub fn finish_turn(&self, number: u32, results: Vec<TurnResult>) {
+        let mut turn = self.turn.write().unwrap();
+        turn.resolved = Some(number);
+        turn.resolved_version = self.version;
+        turn.results = results;
     }
 
+    /// Finds the location of a vendor in the internal vendor list by name
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `name`    - The name of the vendor
+    pub fn find_vendor(&self, name: &str) -> Option<usize> { self.vendors.read().unwrap().iter().position(|v| v.name == name) }
+
     /// Gets the history of average prices for an item
     /// 
     /// # Arguments
@@ -70,12 +652,106 @@ impl Ledger {
     /// * `item`    - The name of the item requested
     pub fn get_item_history(&self, item: String) -> Vec<f64> { self.price_history.read().unwrap()[util::get_rust_type_index(item)].clone() }
 
+    /// Returns a copy of every nonzero shareholding
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_holdings(&self) -> Vec<Holding> { self.holdings.read().unwrap().iter().filter(|h| h.count > 0).cloned().collect() }
+
+    /// Returns a copy of the trading halts that are still in effect
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_active_halts(&self) -> Vec<Halt> {
+        self.halts.read().unwrap().iter().filter(|h| h.until > self.version).cloned().collect()
+    }
+
+    /// Returns a copy of the market events that are still in effect
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_active_events(&self) -> Vec<MarketEvent> {
+        self.events.read().unwrap().iter().filter(|e| e.expires > self.version).cloned().collect()
+    }
+
+    /// Returns a copy of every market event that has fired
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_events(&self) -> Vec<MarketEvent> { self.events.read().unwrap().clone() }
+
+    /// Ranks every vendor by bits, net worth and trade volume
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_leaderboard(&self) -> Vec<LeaderboardRow> {
+        let vendors = self.get_vendors();
+        let reputation = self.get_reputation();
+        let bits: Vec<f64> = vendors.iter().map(|v| v.bits).collect();
+        let net_worth: Vec<f64> = (0..vendors.len()).map(|i| self.net_worth(i)).collect();
+        let volume: Vec<f64> = reputation.iter().map(|r| r.volume).collect();
+        let (bits_ranks, net_worth_ranks, volume_ranks) = (leaderboard::ranks(&bits), leaderboard::ranks(&net_worth), leaderboard::ranks(&volume));
+        vendors.iter().enumerate().map(|(i, v)| LeaderboardRow {
+            vendor: v.name.clone(),
+            bits: bits[i],
+            net_worth: net_worth[i],
+            volume: volume[i],
+            bits_rank: bits_ranks[i],
+            net_worth_rank: net_worth_ranks[i],
+            volume_rank: volume_ranks[i]
+        }).collect()
+    }
+
+    /// Returns a copy of every lending offer with units still available
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_lend_offers(&self) -> Vec<LendOffer> { self.lend_offers.read().unwrap().iter().filter(|o| o.count > 0).cloned().collect() }
+
+    /// Returns the settings the market was created with, if it has its own
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_market_config(&self) -> Option<Config> { self.config.clone() }
+
+    /// Returns the progress of every vendor on its objectives, as scored when
+    /// the session ended once it has
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_objectives(&self) -> Vec<Progress> {
+        let session = self.session.read().unwrap();
+        match session.phase {
+            Phase::Ended => session.objectives.clone(),
+            _ => self.track_objectives()
+        }
+    }
+
+    /// Returns a copy of every loan that has not been returned or bought in
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_open_loans(&self) -> Vec<Loan> { self.loans.read().unwrap().iter().filter(|l| l.open).cloned().collect() }
+
     /// Gets the names of all of the items currently tracked by the ledger
     /// 
     /// # Arguments
     /// 
     /// * `self`    - The current ledger object
-    pub fn get_ledger_items(&self) -> Vec<String> { self.ledger_items.read().unwrap().clone().into_iter().collect::<Vec<String>>() }
+    pub fn get_ledger_items(&self) -> Vec<String> {
+        let mut items = self.ledger_items.read().unwrap().clone().into_iter().collect::<Vec<String>>();
+        items.sort();
+        items
+    }
 
     /// Gets the history of all of the items in the ledger
     /// 
@@ -84,190 +760,1019 @@ impl Ledger {
     /// * `self`    - The current ledger object
     pub fn get_price_history(&self) -> Vec<Vec<f64>> { self.price_history.read().unwrap().clone() }
 
-    /// Returns a copy of the vendor at the given index
+    /// Gets the history of all of the items in the ledger up to a version
     /// 
     /// # Arguments
     /// 
     /// * `self`    - The current ledger object
-    /// * `index`   - The index of the vendor in the internal session list
-    pub fn get_vendor(&self, index: usize) -> Vendor { self.vendors.read().unwrap()[index].clone() }
+    /// * `version` - The last ledger version to include
+    pub fn get_price_history_at(&self, version: u32) -> Vec<Vec<f64>> {
+        let samples = self.price_versions.read().unwrap().iter().take_while(|&&v| v <= version).count();
+        self.price_history.read().unwrap().iter().map(|h| h[..samples.min(h.len())].to_vec()).collect()
+    }
 
-    /// Returns a copy of the vendors in the ledger
+    /// Gets the price index history of the whole market, under `market`, and
+    /// of every item category
     /// 
     /// # Arguments
     /// 
     /// * `self`    - The current ledger object
-    pub fn get_vendors(&self) -> Vec<Vendor> { self.vendors.read().unwrap().clone() }
+    pub fn get_price_indices(&self) -> BTreeMap<String, Vec<f64>> {
+        let history = self.price_history.read().unwrap();
+        let mut retval = BTreeMap::new();
+        retval.insert("market".to_string(), indices::price_index(&history, &(0..history.len()).collect::<Vec<usize>>()));
+        for (category, items) in util::get_categories() {
+            let members: Vec<usize> = items.into_iter().map(util::get_rust_type_index).collect();
+            retval.insert(category, indices::price_index(&history, &members));
+        }
+        retval
+    }
 
-    /// Returns a list containing the names of all of the vendors
+    /// Returns every change in the ranking of vendors by net worth
     /// 
     /// # Arguments
     /// 
     /// * `self`    - The current ledger object
-    pub fn get_vendor_names(&self) -> Vec<String> {
-        let mut retval: Vec<String> = vec![];
-        for v in self.vendors.read().unwrap().iter() {
-            retval.push(v.name.clone());
+    pub fn get_rank_history(&self) -> Vec<RankChange> { self.rank_history.read().unwrap().clone() }
+
+    /// Gets the latest average price of an item, or zero if the item has no
+    /// price yet
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `item`    - The name of the item requested
+    pub fn get_reference_price(&self, item: &str) -> f64 {
+        if !util::get_rust_types().contains(&item) { return 0.0; }
+        match self.get_item_history(item.to_string()).last() {
+            Some(p) if p.is_finite() => *p,
+            _ => 0.0
+        }
+    }
+
+    /// Returns the reputation of every vendor, in the order of the internal
+    /// vendor list
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_reputation(&self) -> Vec<Reputation> { self.reputation.read().unwrap().clone() }
+
+    /// Returns a copy of the session state
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_session(&self) -> Session { self.session.read().unwrap().clone() }
+
+    /// Returns a copy of every share offer with shares still available
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_share_offers(&self) -> Vec<ShareOffer> { self.share_offers.read().unwrap().iter().filter(|o| o.count > 0).cloned().collect() }
+
+    /// Returns the last traded share price of every issuer
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_share_prices(&self) -> HashMap<String, f64> { self.share_prices.read().unwrap().clone() }
+
+    /// Returns the final standings once the session has ended, or the current
+    /// standings while it is still going
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_standings(&self) -> Vec<Standing> {
+        let session = self.session.read().unwrap();
+        match session.phase {
+            Phase::Ended => session.standings.clone(),
+            _ => self.rank_vendors()
+        }
+    }
+
+    /// Returns every team with its members, treasury and combined net worth
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_teams(&self) -> BTreeMap<String, Team> {
+        let mut teams: BTreeMap<String, Team> = BTreeMap::new();
+        for (i, v) in self.get_vendors().iter().enumerate() {
+            if let Some(name) = &v.team {
+                let team = teams.entry(name.clone()).or_default();
+                team.members.push(v.name.clone());
+                team.bits += v.bits;
+                team.net_worth += self.net_worth(i);
+            }
+        }
+        teams
+    }
+
+    /// Returns a copy of the vendor at the given index
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `index`   - The index of the vendor in the internal session list
+    pub fn get_vendor(&self, index: usize) -> Vendor { self.vendors.read().unwrap()[index].clone() }
+
+    /// Returns every supply contract the vendor is buying or selling in
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The location of the vendor in the internal vendor list
+    pub fn get_vendor_contracts(&self, vendor_id: usize) -> Vec<Contract> {
+        let name = self.get_vendor(vendor_id).name;
+        self.contracts.read().unwrap().iter().filter(|c| c.buyer == name || c.seller == name).cloned().collect()
+    }
+
+    /// Returns a copy of every escrow the vendor is buying or selling in
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The location of the vendor in the internal vendor list
+    pub fn get_vendor_escrow(&self, vendor_id: usize) -> Vec<Escrow> {
+        let name = self.get_vendor(vendor_id).name;
+        self.escrow.read().unwrap().iter().filter(|e| e.buyer == name || e.seller == name).cloned().collect()
+    }
+
+    /// Returns a copy of the vendors in the ledger
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_vendors(&self) -> Vec<Vendor> { self.vendors.read().unwrap().clone() }
+
+    /// Returns a list containing the names of all of the vendors
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_vendor_names(&self) -> Vec<String> {
+        let mut retval: Vec<String> = vec![];
+        for v in self.vendors.read().unwrap().iter() {
+            retval.push(v.name.clone());
+        }
+        retval
+    }
+
+    /// Returns the current turn with its queued actions and the results of the
+    /// last resolved turn
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_turn(&self) -> Turn { self.turn.read().unwrap().clone() }
+
+    /// Registers the `generated_vendors` AI vendors of the market under random
+    /// names, and returns their IDs
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - A mutable reference to the current ledger object
+    pub fn generate_vendors(&mut self) -> Vec<String> {
+        let count = self.config::<usize>("generated_vendors").unwrap_or(4);
+        let mut ids = vec![];
+        for _ in 0..count * 4 {
+            if ids.len() == count { break; }
+            if let Ok(id) = self.register_vendor(self.random_name(), None) { ids.push(id); }
+        }
+        ids
+    }
+
+    /// Returns a list containing the urls of all of the vendors
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_vendor_urls(&self) -> Vec<String> {
+        let mut retval: Vec<String> = vec![];
+        for v in self.vendors.read().unwrap().iter() {
+            retval.push(v.url.clone());
+        }
+        retval
+    }
+    
+    /// Returns the current latest version of the ledger
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn get_version(&self) -> u32 { self.version }
+
+    /// Stops a vendor from trading, or lets it trade again. A frozen vendor can
+    /// still read the market
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The ID of the vendor
+    /// * `frozen`      - True to freeze the vendor, false to unfreeze it
+    pub fn freeze_vendor(&self, vendor_id: usize, frozen: bool) {
+        let name = self.get_vendor(vendor_id).name;
+        let mut frozen_vendors = self.frozen.write().unwrap();
+        if frozen { frozen_vendors.insert(name); } else { frozen_vendors.remove(&name); }
+    }
+
+    /// Issues new shares of a vendor, held by the vendor and offered for sale
+    /// at the given price
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The location of the issuer in the internal vendor list
+    /// * `count`       - The number of shares to issue
+    /// * `price`       - The price per share of the offer
+    pub fn issue_shares(&mut self, vendor_id: usize, count: u32, price: f64) -> Result<(), LedgerError> {
+        if count == 0 || !price.is_finite() || price < 0.0 { return Err(LedgerError::InvalidAmount); }
+        let issuer = self.get_vendor(vendor_id).name;
+        {
+            let mut holdings = self.holdings.write().unwrap();
+            match holdings.iter_mut().find(|h| h.issuer == issuer && h.holder == issuer) {
+                Some(h) => h.count += count,
+                None => holdings.push(Holding { issuer: issuer.clone(), holder: issuer.clone(), count })
+            }
+        }
+        let held = self.holdings.read().unwrap().iter().find(|h| h.issuer == issuer && h.holder == issuer).map_or(0, |h| h.count);
+        self.offer_shares(vendor_id, issuer.clone(), held, price)?;
+        self.record(issuer.clone(), format!("{} shares", issuer), count as i32, 0.0);
+        Ok(())
+    }
+
+    /// Returns true if an operator has frozen the vendor
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The ID of the vendor
+    pub fn is_frozen(&self, vendor_id: usize) -> bool { self.frozen.read().unwrap().contains(&self.get_vendor(vendor_id).name) }
+
+    /// Returns true while a circuit breaker has halted trading in the item
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `item`    - The name of the item
+    pub fn is_halted(&self, item: &str) -> bool { self.get_active_halts().iter().any(|h| h.item == item) }
+
+    /// Returns true while a price freeze event is in effect for the item
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `item`    - The name of the item
+    pub fn is_price_frozen(&self, item: &str) -> bool {
+        self.get_active_events().iter().any(|e| match &e.shock {
+            Shock::PriceFreeze { item: frozen } => frozen == item,
+            _ => false
+        })
+    }
+
+    /// Returns true if the token is a spectator token rather than a vendor ID
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `token`   - The token to check
+    pub fn is_spectator(&self, token: &str) -> bool { self.spectators.read().unwrap().iter().any(|t| t == token) }
+
+    /// Removes a vendor from play by replacing its UUID with one that was never
+    /// given out, and freezes it. Its items and standing stay in the market
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The ID of the vendor
+    pub fn kick_vendor(&self, vendor_id: usize) {
+        self.vendor_ids.write().unwrap()[vendor_id] = nanoid::simple();
+        self.freeze_vendor(vendor_id, true);
+    }
+
+    /// Moves a vendor into a team, or out of its team when no team is given,
+    /// until the session ends. Joining an existing team takes the join token
+    /// of the team, which a new team gets from its first member.
+    /// Returns the join token of the team joined
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The ID of the vendor
+    /// * `team`        - The name of the team to join, None to leave
+    /// * `token`       - The join token of the team
+    pub fn join_team(&mut self, vendor_id: usize, team: Option<String>, token: Option<String>) -> Result<Option<String>, LedgerError> {
+        self.check_team(vendor_id, team.as_ref(), token.as_ref())?;
+        self.set_team(vendor_id, team)
+    }
+
+    /// Gets a vendor's net worth, counting bits, every held unit at its
+    /// reference price and shares of other vendors at their last traded price
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The location of the vendor in the internal vendor list
+    pub fn net_worth(&self, vendor_id: usize) -> f64 {
+        let vendor = self.get_vendor(vendor_id);
+        let items: f64 = vendor.get_items().iter().map(|i| i.total() as f64 * self.get_reference_price(&i.name)).sum();
+        let prices = self.share_prices.read().unwrap();
+        let shares: f64 = self.holdings.read().unwrap().iter()
+                              .filter(|h| h.holder == vendor.name && h.issuer != vendor.name)
+                              .map(|h| h.count as f64 * prices.get(&h.issuer).cloned().unwrap_or(0.0))
+                              .sum();
+        vendor.bits + items + shares
+    }
+
+    /// Offers held shares of an issuer for sale at a price per share, a count
+    /// of zero withdraws the offer
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The location of the holder in the internal vendor list
+    /// * `issuer`      - The name of the vendor whose shares are offered
+    /// * `count`       - The number of shares to offer
+    /// * `price`       - The price per share
+    pub fn offer_shares(&mut self, vendor_id: usize, issuer: String, count: u32, price: f64) -> Result<(), LedgerError> {
+        let seller = self.get_vendor(vendor_id).name;
+        let held = self.holdings.read().unwrap().iter().find(|h| h.issuer == issuer && h.holder == seller).map_or(0, |h| h.count);
+        if !price.is_finite() || price < 0.0 { return Err(LedgerError::InvalidAmount); }
+        if held < count { return Err(LedgerError::InsufficientStock); }
+        let mut offers = self.share_offers.write().unwrap();
+        offers.retain(|o| !(o.issuer == issuer && o.seller == seller));
+        if count > 0 {
+            offers.push(ShareOffer { issuer, seller, count, price });
+        }
+        Ok(())
+    }
+
+    /// Pays a dividend per share out of the issuer's bits to every other
+    /// shareholder, and returns the total paid
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The location of the issuer in the internal vendor list
+    /// * `amount`      - The bits paid per share
+    pub fn pay_dividend(&mut self, vendor_id: usize, amount: f64) -> Result<f64, LedgerError> {
+        if !amount.is_finite() || amount <= 0.0 { return Err(LedgerError::InvalidAmount); }
+        let issuer = self.get_vendor(vendor_id).name;
+        let payouts: Vec<(String, f64)> = self.get_holdings().into_iter()
+                                              .filter(|h| h.issuer == issuer && h.holder != issuer)
+                                              .map(|h| (h.holder, h.count as f64 * amount))
+                                              .collect();
+        let total: f64 = payouts.iter().map(|p| p.1).sum();
+        if self.get_vendor(vendor_id).bits < total { return Err(LedgerError::InsufficientFunds); }
+
+        {
+            let mut mut_vendors = self.vendors.write().unwrap();
+            mut_vendors[vendor_id].bits -= total;
+            for (holder, payout) in payouts.iter() {
+                if let Some(v) = mut_vendors.iter_mut().find(|v| &v.name == holder) {
+                    v.bits += payout;
+                }
+            }
+        }
+        let attribute = format!("{} dividend", issuer);
+        self.record(issuer, attribute.clone(), 0, -total);
+        for (holder, payout) in payouts {
+            self.record(holder, attribute.clone(), 0, payout);
+        }
+        Ok(total)
+    }
+
+    /// Records a supply contract proposed by the buyer to the seller named in
+    /// the proposal, pending the seller's acceptance
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `buyer_pos`   - The location of the buyer in the internal vendor list
+    /// * `proposal`    - The terms of the contract
+    pub fn propose_contract(&mut self, buyer_pos: usize, proposal: ContractProposal) -> Result<usize, LedgerError> {
+        let seller_pos = match self.find_vendor(&proposal.from) {
+            Some(pos) if pos != buyer_pos => pos,
+            _ => return Err(LedgerError::InvalidVendor)
+        };
+        let interval = match Interval::parse(&proposal.interval) {
+            Some(i) if util::get_rust_types().contains(&proposal.item.as_str()) => i,
+            _ => return Err(LedgerError::InvalidContract)
+        };
+        if proposal.count == 0 || proposal.every == 0 || proposal.deliveries == 0 ||
+           !proposal.price.is_finite() || proposal.price < 0.0 ||
+           !proposal.penalty.is_finite() || proposal.penalty < 0.0 {
+            return Err(LedgerError::InvalidAmount);
+        }
+
+        let (seller, buyer) = (self.get_vendor(seller_pos).name, self.get_vendor(buyer_pos).name);
+        let mut contracts = self.contracts.write().unwrap();
+        let id = contracts.len();
+        contracts.push(Contract {
+            id,
+            seller,
+            buyer,
+            item: proposal.item,
+            count: proposal.count,
+            price: proposal.price,
+            every: proposal.every,
+            interval,
+            penalty: proposal.penalty,
+            remaining: proposal.deliveries,
+            due: 0,
+            delivered: 0,
+            defaulted: 0,
+            state: ContractState::Proposed
+        });
+        Ok(id)
+    }
+
+    /// Performs a purchase transaction where the buyer purchases stocked items
+    /// from the seller for a fixed price. Confirmed purchases are final and
+    /// recorded in the ledger. Units sold are priced by the seller's bulk
+    /// tiers for the item. A buyer in a team draws what it lacks from its
+    /// teammates before paying. Escrowed and shipped purchases take payment
+    /// and stock immediately, but hold both until delivery, and return the
+    /// ID of the escrow alongside the understock
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `order`       - The purchase order for the transaction, buyer and seller
+    ///                   already confirmed
+    /// * `seller_pos`  - The location of the seller in the internal vendor list
+    /// * `buyer_pos`   - The location of the buyer in the internal vendor list
+    pub fn purchase(&mut self, order: super::purchase::Order, seller_pos: usize, buyer_pos: usize) -> (u32, Option<usize>) {
+        let delivery = Delivery::parse(&order.delivery).unwrap_or(Delivery::Instant);
+        let cost = self.get_vendor(seller_pos).get_item(&order.item).map_or(0.0, |i| i.cost(order.count.min(i.get_count())));
+        self.draw_team_bits(buyer_pos, cost);
+        let understock: u32;
+        let mut escrow_id = None;
+        {
+            let mut mut_vendors = self.vendors.write().unwrap();
+            let mut entries = self.entries.write().unwrap();
+            understock = match mut_vendors[seller_pos].purchase_item(&order.item, order.count) { Ok(u) => u, Err(_) => 0 };
+            let sold = order.count - understock;
+            let total = mut_vendors[seller_pos].get_item(&order.item).map_or(0.0, |i| i.cost(sold));
+            let item_price = if sold > 0 { total / sold as f64 } else { 0.0 };
+            if order.count > 0 {
+                let mut reputation = self.reputation.write().unwrap();
+                reputation[seller_pos].order(understock);
+                reputation[seller_pos].volume += total;
+                reputation[buyer_pos].volume += total;
+                if sold > 0 {
+                    reputation[seller_pos].trades += 1;
+                    reputation[buyer_pos].trades += 1;
+                }
+            }
+
+            if delivery == Delivery::Instant {
+                mut_vendors[seller_pos].bits += total;
+                entries.push(Entry::new(self.version + 1, mut_vendors[seller_pos].name.clone(), order.item.clone(), -1 * sold as i32, total));
+
+                mut_vendors[buyer_pos].add_item(Item::new(order.item.clone(), item_price, sold, 0), false);
+                mut_vendors[buyer_pos].bits -= total;
+                entries.push(Entry::new(self.version + 2, mut_vendors[buyer_pos].name.clone(), order.item.clone(), sold as i32, -1.0 * total));
+            } else {
+                entries.push(Entry::new(self.version + 1, mut_vendors[seller_pos].name.clone(), order.item.clone(), -(sold as i32), 0.0));
+
+                mut_vendors[buyer_pos].bits -= total;
+                entries.push(Entry::new(self.version + 2, mut_vendors[buyer_pos].name.clone(), order.item.clone(), 0, -1.0 * total));
+
+                let mut escrow = self.escrow.write().unwrap();
+                let release = match delivery {
+                    Delivery::Escrow => Some(self.version + 2 + self.config::<u32>("escrow_delay").unwrap_or(10)),
+                    _ => None
+                };
+                let id = escrow.len();
+                escrow_id = Some(id);
+                escrow.push(Escrow {
+                    id,
+                    seller: mut_vendors[seller_pos].name.clone(),
+                    buyer: mut_vendors[buyer_pos].name.clone(),
+                    item: order.item.clone(),
+                    count: sold,
+                    price: item_price,
+                    delivery,
+                    release,
+                    state: EscrowState::Held
+                });
+            }
+        }
+
+        self.version += 2;
+        self.update_avg_price(util::convert_minimal_to_full(self.calculate_avg_prices()));
+        self.settle_loans();
+        self.tick();
+        (understock, escrow_id)
+    }
+
+    /// Queues an action of a vendor for the current turn and returns the
+    /// number of the turn
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The ID of the submitting vendor
+    /// * `action`      - The stock or purchase action
+    pub fn queue_action(&self, vendor_id: usize, action: Action) -> u32 {
+        let vendor = self.get_vendor(vendor_id).name;
+        let mut turn = self.turn.write().unwrap();
+        turn.queue.push(QueuedAction { vendor, action });
+        turn.number
+    }
+
+    /// Generates a random vendor name with the market's random number generator
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn random_name(&self) -> String { util::name_generator(&mut *self.rng.write().unwrap()) }
+
+    /// Rebuilds the bits and inventory of every vendor registered by a ledger
+    /// version, by undoing every entry recorded after it
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `version` - The ledger version to replay
+    pub fn replay(&self, version: u32) -> Vec<ReplayVendor> {
+        let entries = self.entries.read().unwrap();
+        let registered = self.registered.read().unwrap();
+        let catalog = util::get_rust_types();
+        self.vendors.read().unwrap().iter().enumerate().filter_map(|(pos, v)| {
+            if registered[pos] > version { return None; }
+            let mine: Vec<&Entry> = entries.iter().filter(|e| e.vendor == v.name).collect();
+            let mut vendor = ReplayVendor {
+                name: v.name.clone(),
+                bits: v.bits,
+                items: v.get_items().iter().map(|i| (i.name.clone(), i.total() as i64)).collect()
+            };
+            for e in mine.iter().filter(|e| e.after(version)) {
+                vendor.bits -= e.price;
+                if let Some(count) = vendor.items.get_mut(&e.attribute) {
+                    *count -= e.change as i64;
+                } else if catalog.contains(&e.attribute.as_str()) {
+                    vendor.items.insert(e.attribute.clone(), -(e.change as i64));
+                }
+            }
+            vendor.items.retain(|_, count| *count != 0);
+            Some(vendor)
+        }).collect()
+    }
+
+    /// Creates a new vendor in the ledger, and assigns initial distribution of stocked goods
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `name`    - The name of the new vendor
+    /// * `url`     - An optional string to use for the url
+    pub fn register_vendor(&mut self, name: String, url: Option<String>) -> Result<String, LedgerError> {
+        let url = self.check_new_vendor(&name, url)?;
+
+        let initial_bits = match self.config::<f64>("initial_bits") { Some(ib) => ib, None => 1000.0 };
+        let initial_items = match self.config::<usize>("initial_items") { Some(ii) => ii, None => 4 };
+        let item_count = self.config::<u32>("item_count").unwrap_or(50);
+
+        let mut items = vec![];
+        {
+            let mut rng = self.rng.write().unwrap();
+            for t in util::get_catalog(initial_items, &mut *rng).iter() {
+                items.push(Item::new(t.name.clone(), t.initial_price(&mut *rng), 0, t.initial_count(item_count)));
+            }
+        }
+        Ok(self.add_vendor(Vendor::new(name, url, initial_bits), items))
+    }
+
+    /// Registers a vendor with exactly the given bits and items instead of
+    /// random ones from the catalog, as declared by a scenario
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - A mutable reference to the current ledger object
+    /// * `name`    - The name of the vendor
+    /// * `url`     - The url of the vendor, derived from the name if None
+    /// * `bits`    - The starting bits of the vendor
+    /// * `items`   - The starting items of the vendor
+    pub fn register_vendor_with(&mut self, name: String, url: Option<String>, bits: f64, items: Vec<Item>) -> Result<String, LedgerError> {
+        let catalog = util::get_rust_types();
+        if !bits.is_finite() || items.iter().any(|i| !catalog.contains(&i.name.as_str()) || !i.price.is_finite() || i.price < 0.0 || !shop::valid_tiers(&i.get_tiers())) {
+            return Err(LedgerError::InvalidAmount);
+        }
+        let url = self.check_new_vendor(&name, url)?;
+        Ok(self.add_vendor(Vendor::new(name, url, bits), items))
+    }
+
+    /// Returns borrowed units to the lender and releases the collateral back
+    /// to the borrower. The borrower must hold every borrowed unit
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`            - A mutable reference to the current ledger object
+    /// * `borrower_pos`    - The location of the borrower in the internal vendor list
+    /// * `loan_id`         - The ID of the loan being returned
+    pub fn return_loan(&mut self, borrower_pos: usize, loan_id: usize) -> Result<Loan, LedgerError> {
+        let borrower = self.get_vendor(borrower_pos);
+        let loan = match self.loans.read().unwrap().get(loan_id) {
+            Some(l) if l.open && l.borrower == borrower.name => l.clone(),
+            _ => return Err(LedgerError::InvalidLoan)
+        };
+        if borrower.get_item(&loan.item).map_or(0, |i| i.total()) < loan.count {
+            return Err(LedgerError::InsufficientStock);
+        }
+
+        {
+            let mut mut_vendors = self.vendors.write().unwrap();
+            mut_vendors[borrower_pos].remove_item(&loan.item, loan.count);
+            mut_vendors[borrower_pos].bits += loan.collateral;
+            if let Some(name) = &loan.lender {
+                if let Some(lender) = mut_vendors.iter_mut().find(|v| &v.name == name) {
+                    lender.add_item(Item::new(loan.item.clone(), 0.0, 0, loan.count), true);
+                }
+            }
+        }
+
+        self.record(loan.borrower.clone(), loan.item.clone(), -(loan.count as i32), loan.collateral);
+        if let Some(name) = &loan.lender {
+            self.record(name.clone(), loan.item.clone(), loan.count as i32, 0.0);
+        }
+        self.loans.write().unwrap()[loan_id].open = false;
+        Ok(loan)
+    }
+
+    /// Checks whether a request may see the items and bits of every vendor,
+    /// which with `visibility` set to `limited` only spectators may until the
+    /// session has ended
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `token`   - The spectator token sent with the request, if any
+    pub fn reveals_all(&self, token: Option<&str>) -> bool {
+        self.config::<String>("visibility").as_deref() != Some("limited") ||
+        self.session.read().unwrap().phase == Phase::Ended ||
+        token.map_or(false, |t| self.is_spectator(t))
+    }
+
+    /// Moves a vendor into a team or out of its team, at any point of the
+    /// session, for teams declared by the operator. A vendor starting a team
+    /// gives it a new join token. Returns the join token of the team joined
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The ID of the vendor
+    /// * `team`        - The name of the team, None to leave
+    pub fn set_team(&mut self, vendor_id: usize, team: Option<String>) -> Result<Option<String>, LedgerError> {
+        if let Some(name) = &team {
+            let members = self.team_room(vendor_id, name)?;
+            if members == 0 { self.team_tokens.write().unwrap().insert(name.clone(), nanoid::simple()); }
+        }
+        let token = team.as_ref().and_then(|name| self.team_tokens.read().unwrap().get(name).cloned());
+        self.vendors.write().unwrap()[vendor_id].team = team;
+        Ok(token)
+    }
+
+    /// Restricts registration to the given entrants, each with its join token
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `entrants`    - The join token of each entrant, by vendor name
+    pub fn set_entrants(&self, entrants: HashMap<String, String>) { *self.entrants.write().unwrap() = entrants; }
+
+    /// Pauses or resumes trading in the whole market
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - A mutable reference to the current ledger object
+    /// * `paused`  - True to pause trading, false to resume it
+    pub fn set_paused(&mut self, paused: bool) { self.paused = paused; }
+
+    /// Adds an event to the schedule, to fire once its version or time is
+    /// reached
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - A mutable reference to the current ledger object
+    /// * `event`   - The event to schedule
+    pub fn schedule_event(&mut self, event: ScheduledEvent) { self.scheduled_events.write().unwrap().push(event); }
+
+    /// Sets the number of stored units of an item a vendor is willing to lend
+    /// and the fee rate charged on the value of each loan
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The location of the lender in the internal vendor list
+    /// * `item`        - The name of the item on offer
+    /// * `count`       - The number of units on offer, zero withdraws the offer
+    /// * `rate`        - The fee as a fraction of the loan value
+    pub fn set_lend_offer(&mut self, vendor_id: usize, item: String, count: u32, rate: f64) {
+        let lender = self.get_vendor(vendor_id).name;
+        let mut offers = self.lend_offers.write().unwrap();
+        offers.retain(|o| !(o.lender == lender && o.item == item));
+        if count > 0 {
+            offers.push(LendOffer { lender, item, count, rate });
+        }
+    }
+
+    /// Confirms shipment of a held escrow by its seller, delivering the units
+    /// to the buyer immediately
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - A mutable reference to the current ledger object
+    /// * `vendor_id`   - The location of the seller in the internal vendor list
+    /// * `escrow_id`   - The ID of the escrow to ship
+    pub fn ship_escrow(&mut self, vendor_id: usize, escrow_id: usize) -> Result<Escrow, LedgerError> {
+        let name = self.get_vendor(vendor_id).name;
+        match self.escrow.read().unwrap().get(escrow_id) {
+            Some(e) if e.seller == name && e.state == EscrowState::Held => {},
+            _ => return Err(LedgerError::InvalidEscrow)
+        };
+        Ok(self.deliver_escrow(escrow_id))
+    }
+
+    /// Serializes the ledger entries of a vendor, or every entry when no
+    /// vendor is given
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The vendor whose entries to serialize
+    pub fn serialize_entries(&self, vendor_id: Option<usize>) -> String {
+        let name = vendor_id.map(|id| self.get_vendor(id).name);
+        let entries = self.entries.read().unwrap();
+        let visible: Vec<&Entry> = entries.iter().filter(|e| name.as_ref().map_or(true, |n| &e.vendor == n)).collect();
+        to_string(&visible).unwrap()
+    }
+
+    /// Serializes the ledger state into a mapping from vendor names to their
+    /// list of items with parallel lists for price and stock of that item
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn serialize_state(&self) -> HashMap<String, VendorState> {
+        let mut retval = HashMap::new();
+        for (i, vendor) in self.get_vendors().iter().enumerate() {
+            retval.insert(vendor.name.clone(), self.serialize_items(i, None));
+        }
+        retval
+    }
+
+    /// Serializes the ledger state as a vendor may see it. With `visibility`
+    /// set to `limited` the vendor sees its own items and, from everyone else,
+    /// only the cheapest stocked offer of each item
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The vendor the state is for
+    pub fn serialize_state_for(&self, vendor_id: usize) -> HashMap<String, VendorState> {
+        if self.config::<String>("visibility").as_deref() != Some("limited") { return self.serialize_state(); }
+        let vendors = self.get_vendors();
+        let mut best: HashMap<String, (usize, f64, u32)> = HashMap::new();
+        for (i, v) in vendors.iter().enumerate().filter(|(i, _)| *i != vendor_id) {
+            for item in v.get_items().iter().filter(|item| item.get_count() > 0) {
+                let offer = best.entry(item.name.clone()).or_insert((i, item.price, item.get_count()));
+                if item.price < offer.1 { *offer = (i, item.price, item.get_count()); }
+            }
+        }
+        let mut retval: HashMap<String, VendorState> = HashMap::new();
+        retval.insert(vendors[vendor_id].name.clone(), self.serialize_items(vendor_id, None));
+        for (item, (i, price, count)) in best {
+            let state = retval.entry(vendors[i].name.clone()).or_default();
+            state.0.push(item);
+            state.1.push(price);
+            state.2.push(count);
+        }
+        retval
+    }
+
+    /// Serializes a vendor using the same list structure in the ledger state
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The vendor to serialize
+    pub fn serialize_vendor(&self, vendor_id: usize) -> (Vec<String>, Vec<f64>, Vec<u32>) {
+        let mut item_names = vec![];
+        let mut item_store = vec![];
+        for item in self.get_vendor(vendor_id).get_items().iter() {
+            item_names.push(item.name.clone());
+            item_store.push(item.get_stored());
         }
-        retval
+        (item_names, vec![], item_store)
     }
 
-    /// Returns a list containing the urls of all of the vendors
+    /// Checks whether the session is due to start or end, so that it only
+    /// needs a write lock to move along when it is
     /// 
     /// # Arguments
     /// 
     /// * `self`    - The current ledger object
-    pub fn get_vendor_urls(&self) -> Vec<String> {
-        let mut retval: Vec<String> = vec![];
-        for v in self.vendors.read().unwrap().iter() {
-            retval.push(v.url.clone());
-        }
-        retval
+    pub fn session_due(&self) -> bool {
+        let session = self.session.read().unwrap();
+        (session.phase == Phase::Lobby && self.vendor_ids.read().unwrap().len() >= session.min_vendors) ||
+        session.is_over(self.version, self.started.elapsed().as_secs())
     }
-    
-    /// Returns the current latest version of the ledger
+
+    /// Prints the current average prices for all items in the ledger
     /// 
     /// # Arguments
     /// 
     /// * `self`    - The current ledger object
-    pub fn get_version(&self) -> u32 { self.version }
+    pub fn show_avg_prices(&self) { println!("{:#?}", self.calculate_avg_prices()) }
 
-    /// Performs a purchase transaction where the buyer purchases stocked items
-    /// from the seller for a fixed price. Confirmed purchases are final and
-    /// recorded in the ledger
+    /// Counts a request against the rate limit and action budget of the vendor
+    /// behind a uuid. The budget renews every `budget_seconds` seconds, or
+    /// every turn in turn-based markets
     /// 
     /// # Arguments
     /// 
-    /// * `self`        - A mutable reference to the current ledger object
-    /// * `order`       - The purchase order for the transaction, buyer and seller
-    ///                   already confirmed
-    /// * `seller_pos`  - The location of the seller in the internal vendor list
-    /// * `buyer_pos`   - The location of the buyer in the internal vendor list
-    /// * `item_price`  - The price of the item in the transaction
-    pub fn purchase(&mut self, order: super::purchase::Order, seller_pos: usize, buyer_pos: usize, item_price: f64) -> u32 {
-        let understock: u32;
-        {
-            let mut mut_vendors = self.vendors.write().unwrap();
-            let mut entries = self.entries.write().unwrap();
-            understock = match mut_vendors[seller_pos].purchase_item(&order.item, order.count) { Ok(u) => u, Err(_) => 0 };
-            let sold = order.count - understock;
-            entries.push(Entry::new(self.version + 1, mut_vendors[seller_pos].name.clone(), order.item.clone(), -1 * sold as i32, sold as f64 * item_price));
-
-            mut_vendors[buyer_pos].add_item(Item::new(order.item.clone(), item_price, order.count, 0), false);
-            mut_vendors[buyer_pos].bits -= sold as f64 * item_price;
-            entries.push(Entry::new(self.version + 2, mut_vendors[buyer_pos].name.clone(), order.item.clone(), sold as i32, -1.0 * sold as f64 * item_price));
-        }
-
-        self.version += 2;
-        self.update_avg_price(util::convert_minimal_to_full(self.calculate_avg_prices()));
-        understock
+    /// * `self`    - The current ledger object
+    /// * `uuid`    - The unique user ID sent with the request
+    /// * `action`  - True if the request changes the market
+    pub fn throttle(&self, uuid: &str, action: bool) -> Result<(), Limited> {
+        let rate_limit = self.config::<u32>("rate_limit").unwrap_or(0);
+        let budget = self.config::<u32>("action_budget").unwrap_or(0);
+        if (rate_limit == 0 && budget == 0) || !self.vendor_ids.read().unwrap().iter().any(|id| id == uuid) { return Ok(()); }
+        let tick = if self.turn_based() {
+            (self.turn.read().unwrap().number as u64, self.turn_closes_in() as f64)
+        } else {
+            let length = self.config::<f64>("budget_seconds").unwrap_or(1.0).max(0.001);
+            let elapsed = self.started.elapsed().as_secs_f64();
+            ((elapsed / length) as u64, length - elapsed % length)
+        };
+        self.usage.write().unwrap().entry(uuid.to_string()).or_default().count(rate_limit, budget, action, tick)
     }
 
-    /// Creates a new vendor in the ledger, and assigns initial distribution of stocked goods
+    /// Moves the session along and, while it is running, records changes in
+    /// the ranking of vendors, fires every scheduled event that is due,
+    /// delivers escrow whose delay has passed, executes supply contract
+    /// deliveries that are due, rolls for a random event when
+    /// `random_events` is set to a chance per tick, and forces a buy-in on
+    /// every loan that has fallen below its margin
     /// 
     /// # Arguments
     /// 
-    /// * `self`    - The current ledger object
-    /// * `name`    - The name of the new vendor
-    /// * `url`     - An optional string to use for the url
-    pub fn register_vendor(&mut self, name: String, url: Option<String>) -> Result<String, LedgerError> {
-        let mut url = url;
-
-        {
-            let market = self.vendors.read().unwrap();
-            if let Some(_) = market.iter().find(|x| x.name == name) {
-                return Err(LedgerError::ExistingVendor);
-            }
-            if let Some(u) = &url {
-                if let Some(_) = market.iter().find(|x| &x.url == u) {
-                    return Err(LedgerError::ExistingUrl);
-                }
-            } else {
-                url = Some(name.to_lowercase().replace(" ", "_"));
-            }
+    /// * `self`    - A mutable reference to the current ledger object
+    pub fn tick(&mut self) {
+        self.pool_team_bits();
+        self.update_session();
+        if !self.trading_open() { return; }
+        self.record_ranks();
+        let elapsed = self.started.elapsed().as_secs();
+        let version = self.version;
+        let due: Vec<ScheduledEvent> = {
+            let mut scheduled = self.scheduled_events.write().unwrap();
+            let is_due = |e: &ScheduledEvent| e.version.map_or(false, |v| v <= version) || e.seconds.map_or(false, |s| s <= elapsed);
+            let due = scheduled.iter().filter(|e| is_due(e)).cloned().collect();
+            scheduled.retain(|e| !is_due(e));
+            due
+        };
+        let default_duration = self.config::<u32>("event_duration").unwrap_or(20);
+        for event in due {
+            self.fire_event(event.shock, event.duration.unwrap_or(default_duration));
         }
 
-        let initial_bits = match super::get_config::<f64>("initial_bits") { Some(ib) => ib, None => 1000.0 };
-        let initial_items = match super::get_config::<usize>("initial_items") { Some(ii) => ii, None => 4 };
-        let item_count = match super::get_config::<u32>("item_count") { Some(ic) => ic, None => 50 };
-
-        let mut retval = Vendor::new(name.clone(), url.unwrap(), initial_bits);
-        {
-            let mut entries = self.entries.write().unwrap();
+        let released: Vec<usize> = self.escrow.read().unwrap().iter()
+            .filter(|e| e.state == EscrowState::Held && e.release.map_or(false, |r| r <= version))
+            .map(|e| e.id)
+            .collect();
+        for id in released {
+            self.deliver_escrow(id);
+        }
 
-            for (i, t) in util::get_rust_types(initial_items).iter().enumerate() {
-                {
-                    self.ledger_items.write().unwrap().insert(t.to_string());
-                }
-                let i1 = Item::new(t.to_string(), 0.0, 0, item_count);
-                entries.push(Entry::new(self.version + 1 + i as u32, retval.name.clone(), i1.name.clone(), i1.get_count() as i32, i1.price));
-                &retval.add_item(i1, false);
+        let mut delivered = false;
+        loop {
+            let due = self.contracts.read().unwrap().iter()
+                .find(|c| c.state == ContractState::Active && c.due <= match c.interval {
+                    Interval::Versions => version as u64,
+                    Interval::Seconds => elapsed
+                })
+                .map(|c| c.id);
+            match due {
+                Some(id) => { self.deliver_contract(id); delivered = true; },
+                None => break
             }
         }
-
-        self.version += initial_items as u32;
-
-        self.update_avg_price(util::convert_minimal_to_full(self.calculate_avg_prices()));
-
-        {
-            self.vendors.write().unwrap().push(retval);
+        if delivered {
+            self.pool_team_bits();
+            self.update_avg_price(util::convert_minimal_to_full(self.calculate_avg_prices()));
         }
 
-        {
-            self.vendor_versions.write().unwrap().push(0);
+        let chance = self.config::<f64>("random_events").unwrap_or(0.0);
+        if chance > 0.0 && self.rng.write().unwrap().gen::<f64>() < chance {
+            let shock = Shock::random(&self.get_ledger_items(), &mut *self.rng.write().unwrap());
+            self.fire_event(shock, default_duration);
         }
+        self.settle_loans();
+    }
 
-        {
-            let vendor_id = nanoid::simple();
-            self.vendor_ids.write().unwrap().push(vendor_id.clone());
-            Ok(vendor_id)
+    /// Closes the current turn if it is due, opening the next one, and returns
+    /// the number of the closed turn with its queued actions in random order
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn take_due_turn(&self) -> Option<(u32, Vec<QueuedAction>)> {
+        let elapsed = self.started.elapsed().as_secs();
+        let mut turn = self.turn.write().unwrap();
+        if !self.turn_based() || elapsed < turn.closes { return None; }
+        turn.closes = elapsed + self.turn_seconds();
+        turn.number += 1;
+        let mut queue: Vec<QueuedAction> = turn.queue.drain(..).collect();
+        queue.shuffle(&mut *self.rng.write().unwrap());
+        Some((turn.number - 1, queue))
+    }
+
+    /// Returns the bits a vendor can spend, which are the combined bits of its
+    /// team when it has one
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The ID of the vendor
+    pub fn team_bits(&self, vendor_id: usize) -> f64 {
+        let vendors = self.vendors.read().unwrap();
+        match &vendors[vendor_id].team {
+            Some(team) => vendors.iter().filter(|v| v.team.as_ref() == Some(team)).map(|v| v.bits).sum(),
+            None => vendors[vendor_id].bits
         }
     }
 
-    /// Serializes the ledger state into a mapping from vendor names to their
-    /// list of items with parallel lists for price and stock of that item
+    /// Returns true if the session is running, has not reached its limits and
+    /// is not paused by an operator
     /// 
     /// # Arguments
     /// 
     /// * `self`    - The current ledger object
-    pub fn serialize_state(&self) -> HashMap<String, (Vec<String>, Vec<f64>, Vec<u32>)> {
-        let mut retval = HashMap::new();
-        for vendor in self.vendors.read().unwrap().iter() {
-            let mut item_names = vec![];
-            let mut item_prices = vec![];
-            let mut item_stock = vec![];
-            for item in vendor.get_items().iter() {
-                item_names.push(item.name.clone());
-                item_prices.push(item.price);
-                item_stock.push(item.get_count());
-            }
-            retval.insert(vendor.name.clone(), (item_names, item_prices, item_stock));
-        }
-        retval
+    pub fn trading_open(&self) -> bool {
+        let session = self.session.read().unwrap();
+        !self.paused && session.phase == Phase::Running && !session.is_over(self.version, self.started.elapsed().as_secs())
     }
 
-    /// Serializes a vendor using the same list structure in the ledger state
+    /// Returns true if trading is open and the vendor is not frozen
     /// 
     /// # Arguments
     /// 
     /// * `self`        - The current ledger object
-    /// * `vendor_id`   - The vendor to serialize
-    pub fn serialize_vendor(&self, vendor_id: usize) -> (Vec<String>, Vec<f64>, Vec<u32>) {
-        let mut item_names = vec![];
-        let mut item_store = vec![];
-        for item in self.get_vendor(vendor_id).get_items().iter() {
-            item_names.push(item.name.clone());
-            item_store.push(item.get_stored());
+    /// * `vendor_id`   - The ID of the vendor
+    pub fn trading_open_for(&self, vendor_id: usize) -> bool { self.trading_open() && !self.is_frozen(vendor_id) }
+
+    /// Moves units of an item to a teammate for free, taking them from the
+    /// store before the stock and placing them in the teammate's store.
+    /// Returns the number of units moved
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current ledger object
+    /// * `vendor_id`   - The ID of the vendor giving the units
+    /// * `to`          - The ID of the teammate receiving the units
+    /// * `item`        - The name of the item
+    /// * `count`       - The number of units to move
+    pub fn transfer_item(&mut self, vendor_id: usize, to: usize, item: String, count: u32) -> Result<u32, LedgerError> {
+        let (from_name, to_name);
+        {
+            let mut vendors = self.vendors.write().unwrap();
+            if vendor_id == to || vendors[vendor_id].team.is_none() || vendors[vendor_id].team != vendors[to].team {
+                return Err(LedgerError::InvalidTeam);
+            }
+            let price = match vendors[vendor_id].get_item(&item) {
+                Some(i) if i.total() >= count && count > 0 => i.price,
+                _ => return Err(LedgerError::InsufficientStock)
+            };
+            vendors[vendor_id].remove_item(&item, count);
+            vendors[to].add_item(Item::new(item.clone(), price, 0, count), true);
+            from_name = vendors[vendor_id].name.clone();
+            to_name = vendors[to].name.clone();
         }
-        (item_names, vec![], item_store)
+        self.record(from_name, item.clone(), -(count as i32), 0.0);
+        self.record(to_name, item, count as i32, 0.0);
+        Ok(count)
     }
 
-    /// Prints the current average prices for all items in the ledger
+    /// Returns true if the market trades in turns of `turn_seconds` rather
+    /// than in real time
     /// 
     /// # Arguments
     /// 
     /// * `self`    - The current ledger object
-    pub fn show_avg_prices(&self) { println!("{:#?}", self.calculate_avg_prices()) }
+    pub fn turn_based(&self) -> bool { self.turn_seconds() > 0 }
+
+    /// Returns the number of seconds until the current turn closes
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    pub fn turn_closes_in(&self) -> u64 { self.turn.read().unwrap().closes.saturating_sub(self.started.elapsed().as_secs()) }
 
     /// Updates a single item in the ledger
     /// 
@@ -278,7 +1783,43 @@ impl Ledger {
     /// * `price`       - New price of the item
     /// * `count`       - The change from store to stock
     pub fn update_item(&mut self, vendor_id: usize, item: String, price: f64, count: i32) {
+        if let Some(before) = self.get_vendor(vendor_id).get_item(&item).map(|i| i.price) {
+            self.reputation.write().unwrap()[vendor_id].reprice(before, price);
+        }
         self.vendors.write().unwrap()[vendor_id].update_item(item, price, count);
+        self.tick();
+    }
+
+    /// Starts the session once enough vendors have registered, and ends it
+    /// once it reaches its time or version limit
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - A mutable reference to the current ledger object
+    pub fn update_session(&mut self) {
+        let elapsed = self.started.elapsed().as_secs();
+        let vendors = self.vendor_ids.read().unwrap().len();
+        let over = {
+            let mut session = self.session.write().unwrap();
+            if session.phase == Phase::Lobby && vendors >= session.min_vendors {
+                session.phase = Phase::Running;
+                session.started_version = Some(self.version);
+                session.started_seconds = Some(elapsed);
+            }
+            session.is_over(self.version, elapsed)
+        };
+        if over { self.end_session(); }
+    }
+
+    /// Replaces the bulk price breakpoints of a single item in the ledger
+    /// 
+    /// # Arguments
+    /// 
+    /// * `vendor_id`   - The ID of the vendor whose item needs changing
+    /// * `item`        - The item to change
+    /// * `tiers`       - The new price schedule
+    pub fn update_tiers(&mut self, vendor_id: usize, item: String, tiers: Vec<Tier>) {
+        self.vendors.write().unwrap()[vendor_id].update_tiers(item, tiers);
     }
 
     /// Verifies the nanoid of a user request and returns internal vendor list
@@ -321,6 +1862,243 @@ impl Ledger {
         retval
     }
 
+    /// Adds a validated vendor with its starting items to the market,
+    /// recording an entry for each item, and returns the ID of the vendor
+    fn add_vendor(&mut self, vendor: Vendor, items: Vec<Item>) -> String {
+        let mut vendor = vendor;
+        let count = items.len();
+        {
+            let mut entries = self.entries.write().unwrap();
+            for (i, item) in items.into_iter().enumerate() {
+                {
+                    self.ledger_items.write().unwrap().insert(item.name.clone());
+                }
+                entries.push(Entry::new(self.version + 1 + i as u32, vendor.name.clone(), item.name.clone(), item.get_count() as i32, item.price));
+                vendor.add_item(item, false);
+            }
+        }
+
+        self.version += count as u32;
+
+        self.update_avg_price(util::convert_minimal_to_full(self.calculate_avg_prices()));
+
+        {
+            self.vendors.write().unwrap().push(vendor);
+        }
+
+        {
+            self.vendor_versions.write().unwrap().push(0);
+            self.registered.write().unwrap().push(self.version);
+            self.reputation.write().unwrap().push(Reputation::default());
+        }
+
+        let vendor_id = nanoid::simple();
+        self.vendor_ids.write().unwrap().push(vendor_id.clone());
+        self.update_session();
+        vendor_id
+    }
+
+    /// Checks that a new vendor may join the market under the name and url,
+    /// and returns the url to use
+    fn check_new_vendor(&self, name: &str, url: Option<String>) -> Result<String, LedgerError> {
+        if self.session.read().unwrap().phase == Phase::Ended {
+            return Err(LedgerError::SessionClosed);
+        }
+
+        let market = self.vendors.read().unwrap();
+        if market.iter().any(|x| x.name == name) {
+            return Err(LedgerError::ExistingVendor);
+        }
+        match url {
+            Some(u) => {
+                if market.iter().any(|x| x.url == u) {
+                    return Err(LedgerError::ExistingUrl);
+                }
+                Ok(u)
+            },
+            None => Ok(name.to_lowercase().replace(" ", "_"))
+        }
+    }
+
+    /// Reads a setting of this market, falling back to `Config.toml`
+    fn config<T: DeserializeOwned>(&self, key: &str) -> Option<T> {
+        match self.config.as_ref().map(|c| c.get::<T>(key)) {
+            Some(Ok(val)) => Some(val),
+            _ => super::get_config::<T>(key)
+        }
+    }
+
+    /// Executes one delivery of a supply contract through the seller's stock.
+    /// The buyer pays the agreed price for the units it can afford, and each
+    /// unit the seller could not deliver costs the seller the contract penalty
+    fn deliver_contract(&mut self, contract_id: usize) {
+        let contract = self.contracts.read().unwrap()[contract_id].clone();
+        let (seller_pos, buyer_pos) = match (self.find_vendor(&contract.seller), self.find_vendor(&contract.buyer)) {
+            (Some(s), Some(b)) => (s, b),
+            _ => return
+        };
+        let (sold, shortfall, payment, penalty);
+        let budget = self.team_bits(buyer_pos) + self.credit_limit(buyer_pos);
+        {
+            let mut mut_vendors = self.vendors.write().unwrap();
+            let understock = mut_vendors[seller_pos].purchase_item(&contract.item, contract.count).unwrap_or(contract.count);
+            let affordable = match contract.price > 0.0 {
+                true => (budget.max(0.0) / contract.price).floor() as u32,
+                false => u32::MAX
+            };
+            sold = std::cmp::min(contract.count - understock, affordable);
+            shortfall = understock;
+            if contract.count - understock > sold {
+                mut_vendors[seller_pos].add_item(Item::new(contract.item.clone(), contract.price, contract.count - understock - sold, 0), false);
+            }
+
+            payment = sold as f64 * contract.price;
+            mut_vendors[seller_pos].bits += payment;
+            mut_vendors[buyer_pos].bits -= payment;
+            mut_vendors[buyer_pos].add_item(Item::new(contract.item.clone(), contract.price, sold, 0), false);
+
+            penalty = (shortfall as f64 * contract.penalty).min(mut_vendors[seller_pos].bits.max(0.0));
+            mut_vendors[seller_pos].bits -= penalty;
+            mut_vendors[buyer_pos].bits += penalty;
+        }
+        {
+            let mut reputation = self.reputation.write().unwrap();
+            if shortfall > 0 { reputation[seller_pos].defaulted += 1; }
+            reputation[seller_pos].volume += payment;
+            reputation[buyer_pos].volume += payment;
+        }
+
+        self.record(contract.seller.clone(), contract.item.clone(), -(sold as i32), payment);
+        self.record(contract.buyer.clone(), contract.item.clone(), sold as i32, -payment);
+        if shortfall > 0 {
+            self.record(contract.seller.clone(), "contract penalty".to_string(), -(shortfall as i32), -penalty);
+            self.record(contract.buyer.clone(), "contract penalty".to_string(), 0, penalty);
+        }
+
+        let mut contracts = self.contracts.write().unwrap();
+        let c = &mut contracts[contract_id];
+        c.delivered += sold;
+        c.defaulted += shortfall;
+        c.remaining -= 1;
+        c.due += c.every;
+        if c.remaining == 0 { c.state = ContractState::Completed; }
+    }
+
+    /// Moves escrowed units into the buyer's inventory and pays the seller
+    fn deliver_escrow(&mut self, escrow_id: usize) -> Escrow {
+        let escrow = self.escrow.read().unwrap()[escrow_id].clone();
+        let payment = escrow.count as f64 * escrow.price;
+        {
+            let mut mut_vendors = self.vendors.write().unwrap();
+            if let Some(seller) = mut_vendors.iter_mut().find(|v| v.name == escrow.seller) {
+                seller.bits += payment;
+            }
+            if let Some(buyer) = mut_vendors.iter_mut().find(|v| v.name == escrow.buyer) {
+                buyer.add_item(Item::new(escrow.item.clone(), escrow.price, escrow.count, 0), false);
+            }
+        }
+        self.record(escrow.seller.clone(), escrow.item.clone(), 0, payment);
+        self.record(escrow.buyer.clone(), escrow.item.clone(), escrow.count as i32, 0.0);
+        self.escrow.write().unwrap()[escrow_id].state = EscrowState::Delivered;
+        escrow
+    }
+
+    /// Applies the effects of a shock to the ledger and records it as an event
+    /// active for `duration` versions
+    fn fire_event(&mut self, shock: Shock, duration: u32) {
+        match &shock {
+            Shock::SupplyGlut { item, count } => {
+                let mut changes = vec![];
+                for v in self.vendors.write().unwrap().iter_mut().filter(|v| v.get_item(item).is_some()) {
+                    v.add_item(Item::new(item.clone(), 0.0, 0, *count), true);
+                    changes.push(v.name.clone());
+                }
+                for name in changes {
+                    self.record(name, item.clone(), *count as i32, 0.0);
+                }
+            },
+            Shock::DemandSpike { item, count, premium } => {
+                let mut changes = vec![];
+                for v in self.vendors.write().unwrap().iter_mut() {
+                    if let Some((stocked, price)) = v.get_item(item).map(|i| (i.get_count(), i.price)) {
+                        let sold = std::cmp::min(stocked, *count);
+                        if sold == 0 { continue; }
+                        let _ = v.purchase_item(item, sold);
+                        let revenue = sold as f64 * price * (1.0 + premium);
+                        v.bits += revenue;
+                        changes.push((v.name.clone(), sold, revenue));
+                    }
+                }
+                for (name, sold, revenue) in changes {
+                    self.record(name, item.clone(), -(sold as i32), revenue);
+                }
+            },
+            Shock::FeeHoliday | Shock::PriceFreeze { .. } => {}
+        }
+
+        {
+            let mut events = self.events.write().unwrap();
+            let id = events.len();
+            events.push(MarketEvent { id, description: shock.describe(), shock, fired: self.version, expires: self.version + duration });
+        }
+        self.update_avg_price(util::convert_minimal_to_full(self.calculate_avg_prices()));
+    }
+
+    /// Closes a loan on the borrower's behalf. Units the borrower holds are
+    /// returned first, the rest are bought from the cheapest stocked sellers
+    /// and anything still missing is settled in bits at the reference price,
+    /// all paid out of the collateral
+    fn force_buy_in(&mut self, loan_id: usize) {
+        let loan = self.loans.read().unwrap()[loan_id].clone();
+        let reference = self.get_reference_price(&loan.item);
+        let borrower_pos = match self.find_vendor(&loan.borrower) { Some(p) => p, None => return };
+        let mut collateral = loan.collateral;
+        let mut purchases = vec![];
+
+        let held = self.vendors.write().unwrap()[borrower_pos].remove_item(&loan.item, loan.count);
+        let mut remaining = loan.count - held;
+
+        let mut sellers: Vec<(usize, f64, u32)> = self.get_vendors().iter().enumerate()
+            .filter(|(i, v)| *i != borrower_pos && Some(&v.name) != loan.lender.as_ref())
+            .filter_map(|(i, v)| v.get_item(&loan.item).filter(|it| it.get_count() > 0).map(|it| (i, it.price, it.get_count())))
+            .collect();
+        sellers.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+        for (pos, price, stocked) in sellers {
+            if remaining == 0 { break; }
+            let bought = std::cmp::min(stocked, remaining);
+            let mut mut_vendors = self.vendors.write().unwrap();
+            let cost = mut_vendors[pos].get_item(&loan.item).map_or(bought as f64 * price, |it| it.cost(bought));
+            let _ = mut_vendors[pos].purchase_item(&loan.item, bought);
+            mut_vendors[pos].bits += cost;
+            collateral -= cost;
+            remaining -= bought;
+            purchases.push((mut_vendors[pos].name.clone(), bought, cost));
+        }
+
+        let covered = loan.count - remaining;
+        let settlement = remaining as f64 * reference;
+        collateral -= settlement;
+        {
+            let mut mut_vendors = self.vendors.write().unwrap();
+            mut_vendors[borrower_pos].bits += collateral;
+            if let Some(name) = &loan.lender {
+                if let Some(lender) = mut_vendors.iter_mut().find(|v| &v.name == name) {
+                    lender.add_item(Item::new(loan.item.clone(), 0.0, 0, covered), true);
+                    lender.bits += settlement;
+                }
+            }
+        }
+
+        for (seller, bought, cost) in purchases {
+            self.record(seller, loan.item.clone(), -(bought as i32), cost);
+        }
+        self.record(loan.borrower.clone(), loan.item.clone(), -(held as i32), collateral);
+        if let Some(name) = &loan.lender {
+            self.record(name.clone(), loan.item.clone(), covered as i32, settlement);
+        }
+        self.loans.write().unwrap()[loan_id].open = false;
+    }
+
     fn get_vendor_items(&self, index: usize) -> Vec<Item> {
         let mut retval = vec![];
         for i in self.vendors.read().unwrap()[index].get_items().iter() {
@@ -329,16 +2107,183 @@ impl Ledger {
         retval
     }
 
+    fn move_shares(&mut self, issuer: &str, from: &str, to: &str, count: u32) {
+        let mut holdings = self.holdings.write().unwrap();
+        if let Some(h) = holdings.iter_mut().find(|h| h.issuer == issuer && h.holder == from) {
+            h.count -= count;
+        }
+        match holdings.iter_mut().find(|h| h.issuer == issuer && h.holder == to) {
+            Some(h) => h.count += count,
+            None => holdings.push(Holding { issuer: issuer.to_string(), holder: to.to_string(), count })
+        }
+    }
+
+    /// Moves bits from the teammates of a vendor until it holds the given
+    /// amount, as far as the team can cover it, recording each transfer
+    fn draw_team_bits(&mut self, vendor_id: usize, amount: f64) {
+        let mut transfers = vec![];
+        {
+            let mut vendors = self.vendors.write().unwrap();
+            let team = match &vendors[vendor_id].team { Some(t) if vendors[vendor_id].bits < amount => t.clone(), _ => return };
+            for j in 0..vendors.len() {
+                let need = amount - vendors[vendor_id].bits;
+                if need <= 0.0 { break; }
+                if j == vendor_id || vendors[j].team.as_ref() != Some(&team) || vendors[j].bits <= 0.0 { continue; }
+                let moved = vendors[j].bits.min(need);
+                vendors[j].bits -= moved;
+                vendors[vendor_id].bits += moved;
+                transfers.push((vendors[j].name.clone(), vendors[vendor_id].name.clone(), moved));
+            }
+        }
+        for (from, to, moved) in transfers {
+            self.record(from, "team treasury".to_string(), 0, -moved);
+            self.record(to, "team treasury".to_string(), 0, moved);
+        }
+    }
+
+    fn pool_team_bits(&mut self) {
+        let count = self.vendors.read().unwrap().len();
+        for i in 0..count { self.draw_team_bits(i, 0.0); }
+    }
+
+    fn rank_vendors(&self) -> Vec<Standing> {
+        let mut points: HashMap<String, f64> = HashMap::new();
+        for p in self.track_objectives().into_iter().filter(|p| p.complete) {
+            *points.entry(p.vendor).or_insert(0.0) += p.points;
+        }
+        let mut standings: Vec<Standing> = self.get_vendors().iter().enumerate()
+            .map(|(i, v)| Standing {
+                rank: 0,
+                vendor: v.name.clone(),
+                team: v.team.clone(),
+                bits: v.bits,
+                net_worth: self.net_worth(i),
+                points: points.get(&v.name).cloned().unwrap_or(0.0)
+            })
+            .collect();
+        let mut combined: HashMap<String, (f64, f64)> = HashMap::new();
+        for s in standings.iter() {
+            let side = combined.entry(s.team.clone().unwrap_or_else(|| s.vendor.clone())).or_insert((0.0, 0.0));
+            side.0 += s.points;
+            side.1 += s.net_worth;
+        }
+        let side = |s: &Standing| s.team.clone().unwrap_or_else(|| s.vendor.clone());
+        standings.sort_by(|a, b| {
+            combined[&side(b)].partial_cmp(&combined[&side(a)]).unwrap_or(std::cmp::Ordering::Equal)
+                .then_with(|| side(a).cmp(&side(b)))
+                .then_with(|| b.net_worth.partial_cmp(&a.net_worth).unwrap_or(std::cmp::Ordering::Equal))
+        });
+        let mut rank = 0;
+        for i in 0..standings.len() {
+            if i == 0 || side(&standings[i]) != side(&standings[i - 1]) { rank += 1; }
+            standings[i].rank = rank;
+        }
+        standings
+    }
+
+    fn record(&mut self, vendor: String, attribute: String, change: i32, price: f64) {
+        self.version += 1;
+        self.entries.write().unwrap().push(Entry::new(self.version, vendor, attribute, change, price));
+    }
+
+    fn record_ranks(&mut self) {
+        let ranking: Vec<String> = self.rank_vendors().into_iter().map(|s| s.vendor).collect();
+        let mut history = self.rank_history.write().unwrap();
+        if history.last().map_or(true, |r| r.ranking != ranking) {
+            history.push(RankChange { version: self.version, ranking });
+        }
+    }
+
+    fn serialize_items(&self, vendor_id: usize, only: Option<&String>) -> VendorState {
+        let mut state: VendorState = (vec![], vec![], vec![]);
+        for item in self.get_vendor(vendor_id).get_items().iter().filter(|i| only.map_or(true, |name| &i.name == name)) {
+            state.0.push(item.name.clone());
+            state.1.push(item.price);
+            state.2.push(item.get_count());
+        }
+        state
+    }
+
+    fn settle_loans(&mut self) {
+        let maintenance = self.config::<f64>("short_maintenance").unwrap_or(1.1);
+        let undercollateralized: Vec<usize> = self.get_open_loans().iter()
+            .filter(|l| l.collateral < l.count as f64 * self.get_reference_price(&l.item) * maintenance)
+            .map(|l| l.id)
+            .collect();
+        for id in undercollateralized {
+            self.force_buy_in(id);
+        }
+    }
+
+    /// Returns how many other vendors are in a team, or an error when the name
+    /// is empty or the team already holds `team_size` members
+    fn team_room(&self, vendor_id: usize, name: &str) -> Result<usize, LedgerError> {
+        let size = self.config::<usize>("team_size").unwrap_or(0);
+        let members = self.get_vendors().iter().enumerate().filter(|(i, v)| *i != vendor_id && v.team.as_deref() == Some(name)).count();
+        if name.is_empty() || (size > 0 && members >= size) { return Err(LedgerError::InvalidTeam); }
+        Ok(members)
+    }
+
+    fn track_objectives(&self) -> Vec<Progress> {
+        let vendors = self.get_vendors();
+        let reputation = self.reputation.read().unwrap();
+        let held = |v: &Vendor, item: &String| v.get_item(item).map_or(0, |i| i.total()) as f64;
+        let mut retval = vec![];
+        for objective in self.objectives.iter() {
+            for (i, v) in vendors.iter().enumerate() {
+                if objective.vendor.as_ref().map_or(false, |name| name != &v.name) { continue; }
+                let progress = match &objective.goal {
+                    Goal::Hold { item, .. } => held(v, item),
+                    Goal::Corner { item, .. } => {
+                        let all: f64 = vendors.iter().map(|o| held(o, item)).sum();
+                        if all > 0.0 { held(v, item) / all } else { 0.0 }
+                    },
+                    Goal::Trades { .. } => reputation[i].trades as f64
+                };
+                let target = objective.goal.target();
+                retval.push(Progress {
+                    vendor: v.name.clone(),
+                    objective: objective.goal.describe(),
+                    progress,
+                    target,
+                    complete: progress >= target,
+                    points: objective.points.unwrap_or(1.0)
+                });
+            }
+        }
+        retval
+    }
+
+    fn turn_seconds(&self) -> u64 { self.config::<u64>("turn_seconds").unwrap_or(0) }
+
     fn update_avg_price(&mut self, new_vals: Vec<f64>) {
         for (i, &v) in new_vals.iter().enumerate() {
             self.price_history.write().unwrap()[i].push(v);
         }
+        self.price_versions.write().unwrap().push(self.version);
+        self.trip_circuit_breakers();
+    }
+
+    fn trip_circuit_breakers(&mut self) {
+        let threshold = match self.config::<f64>("circuit_breaker") { Some(t) if t > 0.0 => t, _ => return };
+        let cooldown = self.config::<u32>("circuit_breaker_versions").unwrap_or(20);
+        let types = util::get_rust_types();
+        let history = self.price_history.read().unwrap();
+        let mut halts = self.halts.write().unwrap();
+        for (i, prices) in history.iter().enumerate() {
+            if prices.len() < 2 || halts.iter().any(|h| h.item == types[i] && h.until > self.version) { continue; }
+            let (before, after) = (prices[prices.len() - 2], prices[prices.len() - 1]);
+            if before > 0.0 && after.is_finite() && ((after - before) / before).abs() > threshold {
+                halts.push(Halt { item: types[i].to_string(), before, after, from: self.version, until: self.version + cooldown });
+            }
+        }
     }
 }
 
 pub struct MutLedger { pub session_ledger: Arc<RwLock<Ledger>> }
 
-/// Endpoint to get ledger data via http request
+/// Endpoint to get ledger data via http request. Spectator tokens also get
+/// the store and bits of every vendor
 /// 
 /// # Arguments
 /// 
@@ -346,19 +2291,33 @@ pub struct MutLedger { pub session_ledger: Arc<RwLock<Ledger>> }
 ///               ledger state, this is to confirm legitimacy with the server
 #[allow(unused_assignments, unused_variables)]
 #[post("/ledger_state", data="<uuid>")]
-pub fn request_ledger_state(uuid: Result<Form<UUID>, FormError<'_>>, ledger: State<MutLedger>) -> content::Json<String> {
+pub fn request_ledger_state(uuid: Result<Form<UUID>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
     let arc_ledger = ledger.inner().session_ledger.clone();
     let internal_id: usize;
     let serialized_vendor;
     let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
     let mut ledger_state = match uuid {
         Ok(u) => {
+            let uuid = u.into_inner().uuid;
+            if let Err(limited) = super::limits::admit(ledger.inner(), &uuid, super::limits::Call::Read) {
+                return util::construct_json(&limited);
+            }
             let ledger = &*arc_ledger.read().unwrap();
-            match ledger.verify_uuid(u.into_inner().uuid) {
+            match ledger.verify_uuid(uuid.clone()) {
                 Ok(id) => {
                     internal_id = id;
                     serialized_vendor = ledger.serialize_vendor(id);
-                    ledger.serialize_state()
+                    ledger.serialize_state_for(id)
+                },
+                Err(_) if ledger.is_spectator(&uuid) => {
+                    let vendors = ledger.get_vendors();
+                    let stored: HashMap<_, _> = vendors.iter().enumerate()
+                        .map(|(id, v)| (v.name.clone(), ledger.serialize_vendor(id))).collect();
+                    let bits: HashMap<String, f64> = vendors.iter().map(|v| (v.name.clone(), v.bits)).collect();
+                    let mut state = serde_json::to_value(ledger.serialize_state()).unwrap();
+                    state["stored"] = serde_json::to_value(stored).unwrap();
+                    state["bits"] = serde_json::to_value(bits).unwrap();
+                    return content::Json(to_string(&state).unwrap());
                 },
                 Err(_) => {
                     output_vars.insert("UUID".to_string(), Box::new("not found".to_string()));
@@ -382,9 +2341,41 @@ pub fn request_ledger_state(uuid: Result<Form<UUID>, FormError<'_>>, ledger: Sta
     return content::Json(to_string(&ledger_state).unwrap());
 }
 
+/// Endpoint to get ledger entries via http request, a vendor sees its own
+/// entries and a spectator sees every entry
+/// 
+/// # Arguments
+/// 
+/// * `uuid`    - The unique user ID of the vendor or a spectator token
+/// * `ledger`  - The current ledger state
+#[post("/entries", data="<uuid>")]
+pub fn request_entries(uuid: Result<Form<UUID>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let uuid = match uuid {
+        Ok(u) => u.into_inner().uuid,
+        Err(_) => {
+            output_vars.insert("Form".to_string(), Box::new("incorrectly formatted".to_string()));
+            return util::construct_json(&output_vars);
+        }
+    };
+    if let Err(limited) = super::limits::admit(ledger.inner(), &uuid, super::limits::Call::Read) {
+        return util::construct_json(&limited);
+    }
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    match ledger.verify_uuid(uuid.clone()) {
+        Ok(id) => content::Json(ledger.serialize_entries(Some(id))),
+        Err(_) if ledger.is_spectator(&uuid) => content::Json(ledger.serialize_entries(None)),
+        Err(_) => {
+            output_vars.insert("UUID".to_string(), Box::new("not found".to_string()));
+            util::construct_json(&output_vars)
+        }
+    }
+}
+
 /// Endpoint to get vendor names via http request
 #[get("/vendor_names")]
-pub fn request_vendor_names(ledger: State<MutLedger>) -> content::Json<String> {
+pub fn request_vendor_names(ledger: MarketLedger) -> content::Json<String> {
     let arc_ledger = ledger.inner().session_ledger.clone();
     let ledger = &*arc_ledger.read().unwrap();
     let vendor_names = ledger.get_vendor_names();
@@ -392,10 +2383,18 @@ pub fn request_vendor_names(ledger: State<MutLedger>) -> content::Json<String> {
 }
 
 /// Endpoint to get vendor urls via http request
-#[get("/vendor_urls")]
-pub fn request_vendor_urls(ledger: State<MutLedger>) -> content::Json<String> {
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/vendor_urls?<token>")]
+pub fn request_vendor_urls(token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
     let arc_ledger = ledger.inner().session_ledger.clone();
     let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) {
+        return util::construct_json(&super::authorization::reject_hidden());
+    }
     let vendor_urls = ledger.get_vendor_urls();
     return content::Json(to_string(&vendor_urls).unwrap());
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+use super::ledger::MutLedger;
+
+//The kind of API call a vendor makes with its uuid. Every kind but reads
+//counts against the action budget, and each has its own cost in `call_costs`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Call {
+    Read,
+    Stock,
+    Purchase,
+    Other
+}
+
+impl Call {
+    /// Returns the name of the call kind, its key in `call_costs`
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current call kind
+    pub fn name(&self) -> &'static str {
+        match self {
+            Call::Read => "read",
+            Call::Stock => "stock",
+            Call::Purchase => "purchase",
+            Call::Other => "other"
+        }
+    }
+}
+
+//Requests a vendor made in the last second and actions it took since its
+//action budget last renewed, counted against `rate_limit` and `action_budget`
+#[derive(Clone, Debug, Default)]
+pub struct Usage {
+    requests: VecDeque<Instant>,
+    tick: u64,
+    actions: u32
+}
+
+//Why a request was refused, with the seconds until it may be retried
+#[derive(Clone, Debug)]
+pub enum Limited {
+    Rate { limit: u32, retry_after: f64 },
+    Budget { budget: u32, retry_after: f64 }
+}
+
+impl Usage {
+    /// Counts a request of a vendor, refusing it without counting it when the
+    /// vendor is over its rate limit or, for actions, its budget for the tick.
+    /// A limit of zero is no limit
+    ///
+    /// # Arguments
+    ///
+    /// * `self`        - The usage of the vendor
+    /// * `rate_limit`  - The most requests allowed per second
+    /// * `budget`      - The most actions allowed per budget tick
+    /// * `action`      - True if the request changes the market
+    /// * `tick`        - The number of the current tick and the seconds it has left
+    pub fn count(&mut self, rate_limit: u32, budget: u32, action: bool, tick: (u64, f64)) -> Result<(), Limited> {
+        let now = Instant::now();
+        let window = Duration::from_secs(1);
+        while self.requests.front().map_or(false, |t| now.duration_since(*t) >= window) {
+            self.requests.pop_front();
+        }
+        if self.tick != tick.0 {
+            self.tick = tick.0;
+            self.actions = 0;
+        }
+        if action && budget > 0 && self.actions >= budget {
+            return Err(Limited::Budget { budget, retry_after: tick.1 });
+        }
+        if rate_limit > 0 && self.requests.len() >= rate_limit as usize {
+            let waited = now.duration_since(self.requests[0]);
+            return Err(Limited::Rate { limit: rate_limit, retry_after: (window - waited).as_secs_f64() });
+        }
+        self.requests.push_back(now);
+        if action { self.actions += 1; }
+        Ok(())
+    }
+}
+
+/// Admits a call from the vendor behind a uuid, checking that it can afford
+/// the cost of the call, then its rate limit and action budget, and then
+/// charging the cost. A call refused for its cost is not counted against the
+/// limits. Returns the error to answer with when the call is refused. Unknown
+/// uuids pass, to be rejected by the endpoint itself
+///
+/// # Arguments
+///
+/// * `ledger`  - The current ledger state
+/// * `uuid`    - The unique user ID sent with the request
+/// * `call`    - The kind of call being made
+pub fn admit(ledger: &MutLedger, uuid: &str, call: Call) -> Result<(), BTreeMap<String, Box<dyn Display>>> {
+    let unaffordable = |cost: f64| {
+        let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+        output_vars.insert("success".to_string(), Box::new(false));
+        output_vars.insert("bits".to_string(), Box::new(format!("cannot afford the {} call cost of {}", call.name(), cost)));
+        output_vars
+    };
+    let (throttled, cost) = {
+        let ledger = ledger.session_ledger.read().unwrap();
+        let cost = ledger.call_cost(call);
+        if ledger.verify_uuid(uuid.to_string()).map_or(false, |id| ledger.get_vendor(id).bits < cost) {
+            return Err(unaffordable(cost));
+        }
+        (ledger.throttle(uuid, call != Call::Read), cost)
+    };
+    let limited = match throttled {
+        Ok(_) if cost > 0.0 => {
+            if ledger.session_ledger.write().unwrap().charge_call(uuid, call).is_ok() { return Ok(()); }
+            return Err(unaffordable(cost));
+        },
+        Ok(_) => return Ok(()),
+        Err(l) => l
+    };
+    let (message, retry_after) = match limited {
+        Limited::Rate { limit, retry_after } => (format!("over {} requests per second", limit), retry_after),
+        Limited::Budget { budget, retry_after } => (format!("used all {} actions until the budget renews", budget), retry_after)
+    };
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    output_vars.insert("success".to_string(), Box::new(false));
+    output_vars.insert("limit".to_string(), Box::new(message));
+    output_vars.insert("retry_after".to_string(), Box::new((retry_after * 1000.0).ceil() / 1000.0));
+    Err(output_vars)
+}
@@ -17,13 +17,33 @@ use rocket_contrib::serve::StaticFiles;
 use rocket_contrib::templates::Template;
 use serde::de;
 
+mod admin;
 mod authorization;
 mod base;
+mod breakers;
+mod contracts;
+mod equity;
+mod escrow;
+mod events;
+mod indices;
+mod intel;
+mod leaderboard;
 mod ledger;
+mod limits;
+mod markets;
+mod objectives;
+mod replay;
+mod reputation;
+mod scenario;
+mod session;
+mod teams;
 mod tera_functions;
+mod tournament;
+mod turns;
 
 pub mod purchase;
 pub mod shop;
+pub mod short;
 pub mod util;
 
 lazy_static! {
@@ -52,48 +72,98 @@ pub fn get_config<T: de::DeserializeOwned>(key: &str) -> Option<T> {
 /// # Config options
 /// 
 /// * `generated_vendors`   - Number of AI vendors to generate
-#[allow(unused_variables, unused_assignments)]
+/// * `scenario_file`       - Scenario to set up the main market from instead
 fn main() {
-    let mut session_ledger = ledger::Ledger::new();
-    let mut ids = vec![];
-    match get_config::<usize>("generated_vendors") {
-        Some(gv) => {
-            for mut i in 0..gv {
-                match session_ledger.register_vendor(util::name_generator(), None) {
-                    Ok(id) => { ids.push(id); }, Err(_) => { i -= 1; }
-                }
-            }
+    let (session_ledger, ids) = match get_config::<String>("scenario_file") {
+        Some(path) => {
+            let scenario = scenario::read_file(&path).expect("scenario file could not be read");
+            scenario::build(scenario).unwrap_or_else(|e| panic!("scenario file is invalid: {}", e))
         },
         None => {
-            ids.push(session_ledger.register_vendor(util::name_generator(), None).unwrap_or("".to_string()));
-            ids.push(session_ledger.register_vendor(util::name_generator(), Some("oldies".to_string())).unwrap_or("".to_string()));
-            ids.push(session_ledger.register_vendor(util::name_generator(), None).unwrap_or("".to_string()));
-            ids.push(session_ledger.register_vendor(util::name_generator(), Some("icees".to_string())).unwrap_or("".to_string()));
+            let mut session_ledger = ledger::Ledger::new();
+            let ids = session_ledger.generate_vendors();
+            (session_ledger, ids)
         }
-    }
+    };
 
     println!("{:#?}", ids);
     session_ledger.show_avg_prices();
     
+    let main_ledger = Arc::new(RwLock::new(session_ledger));
     rocket::ignite()
-           .manage( ledger::MutLedger{session_ledger: Arc::new(RwLock::new(session_ledger))} )
+           .manage( ledger::MutLedger{session_ledger: main_ledger.clone()} )
+           .manage( markets::Markets::new(main_ledger) )
+           .manage( tournament::Tournaments::from_config() )
+           .manage( admin::AdminSecret::from_config() )
            .mount("/", StaticFiles::from("templates"))
            .mount("/", routes![
+               admin::http_adjust,
+               admin::http_freeze,
+               admin::http_inject,
+               admin::http_kick,
+               admin::http_pause,
+               admin::http_reset,
+               admin::http_resume,
                authorization::register, 
+               authorization::spectate,
                base::index,
+               leaderboard::leaderboard_page,
+               markets::http_create,
+               markets::http_delete,
+               markets::request_markets,
                purchase::form_purchase, 
                purchase::purchase_page,
+               replay::replay_page,
+               scenario::http_load,
                shop::form_stock,
-               shop::stock_page])
+               shop::stock_page,
+               tournament::http_create,
+               tournament::tournament_page])
            .mount("/api", routes![
+               breakers::request_halts,
+               contracts::http_accept,
+               contracts::http_cancel,
+               contracts::http_propose,
+               contracts::request_contracts,
+               equity::http_buy,
+               equity::http_dividend,
+               equity::http_issue,
+               equity::http_offer,
+               equity::request_net_worth,
+               equity::request_shares,
+               escrow::http_cancel,
+               escrow::http_ship,
+               escrow::request_escrow,
+               events::request_events,
+               indices::request_categories,
+               indices::request_indices,
+               intel::http_buy,
+               leaderboard::request_leaderboard,
+               ledger::request_entries,
                ledger::request_ledger_state,
                ledger::request_vendor_names,
                ledger::request_vendor_urls,
+               objectives::request_objectives,
+               replay::request_replay,
+               reputation::request_reputation,
+               session::request_session,
+               session::request_standings,
                purchase::http_purchase,
-               shop::http_stock])
+               purchase::request_quote,
+               shop::http_stock,
+               short::http_borrow,
+               short::http_lend,
+               short::http_return,
+               short::request_loans,
+               teams::http_join,
+               teams::http_transfer,
+               teams::request_teams,
+               tournament::request_tournament,
+               turns::request_turn])
            .mount("/vendors", routes![
                shop::market_home, 
                shop::vendor])
+           .attach(markets::MarketRouter)
            .attach(Template::custom(|engines| {
                let var = BTreeMap::new();
                engines.tera.register_function("catchphrase_generator", tera_functions::make_catchphrase_generator());
@@ -103,6 +173,7 @@ fn main() {
            .register(catchers![
                base::bad_request,
                base::internal_error,
-               base::not_found])
+               base::not_found,
+               base::unauthorized])
            .launch();
 }
\ No newline at end of file
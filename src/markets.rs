@@ -0,0 +1,259 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::{Arc, RwLock};
+
+use config::{Config, File, FileFormat};
+use rocket::{Data, Request, State};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Status;
+use rocket::http::uri::Origin;
+use rocket::request::{self, Form, FormError, FromRequest};
+use rocket::response::content;
+use rocket::Outcome;
+use serde_json::{json, to_string};
+use super::admin::Admin;
+use super::ledger::{Ledger, MutLedger};
+use super::{scenario, session, turns};
+use super::tournament::Tournaments;
+use super::util;
+
+//Id of the market a request was addressed to, None for the main market
+struct MarketId(Option<String>);
+
+//Every market running on the server by id, the main market included
+pub struct Markets {
+    pub ledgers: RwLock<BTreeMap<String, Arc<RwLock<Ledger>>>>
+}
+
+impl Markets {
+    /// Creates the collection of markets, holding only the main market
+    ///
+    /// # Arguments
+    ///
+    /// * `main`    - The ledger of the main market, served at `/api`
+    pub fn new(main: Arc<RwLock<Ledger>>) -> Markets {
+        let mut ledgers = BTreeMap::new();
+        ledgers.insert(MAIN_MARKET.to_string(), main);
+        Markets { ledgers: RwLock::new(ledgers) }
+    }
+
+    /// Returns the ledger of a market if it exists
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current markets object
+    /// * `id`      - The id of the market
+    pub fn get(&self, id: &str) -> Option<Arc<RwLock<Ledger>>> { self.ledgers.read().unwrap().get(id).cloned() }
+
+    /// Adds a new market, returning false if the id is already in use
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current markets object
+    /// * `id`      - The id of the new market
+    /// * `ledger`  - The ledger of the new market
+    pub fn insert(&self, id: String, ledger: Ledger) -> bool {
+        let mut ledgers = self.ledgers.write().unwrap();
+        if ledgers.contains_key(&id) { return false; }
+        ledgers.insert(id, Arc::new(RwLock::new(ledger)));
+        true
+    }
+
+    /// Removes a market, returning false if it does not exist. The main
+    /// market is never removed
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current markets object
+    /// * `id`      - The id of the market
+    pub fn remove(&self, id: &str) -> bool {
+        id != MAIN_MARKET && self.ledgers.write().unwrap().remove(id).is_some()
+    }
+}
+
+/// Parses the TOML config of a new market, which overrides `Config.toml`
+///
+/// # Arguments
+///
+/// * `toml`    - The optional TOML settings, empty for none
+pub fn parse_config(toml: &Option<String>) -> Option<Config> {
+    let mut config = Config::default();
+    match toml.as_deref() {
+        None | Some("") => Some(config),
+        Some(t) => match config.merge(File::from_str(t, FileFormat::Toml)) {
+            Ok(_) => Some(config),
+            Err(_) => None
+        }
+    }
+}
+
+/// Returns true if a market id is made of letters, digits, dashes and
+/// underscores only
+///
+/// # Arguments
+///
+/// * `id`  - The id of the market
+pub fn valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub const MAIN_MARKET: &str = "main";
+
+//Request guard for the ledger of the market a request was addressed to.
+//Requests outside of `/markets/<id>/` use the main market
+pub struct MarketLedger(MutLedger);
+
+impl MarketLedger {
+    /// Returns the ledger of the addressed market
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current market ledger
+    pub fn inner(&self) -> &MutLedger { &self.0 }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for MarketLedger {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let ledger = match &request.local_cache(|| MarketId(None)).0 {
+            Some(id) => {
+                let markets = request.guard::<State<Markets>>()?;
+                match markets.get(id) {
+                    Some(ledger) => MutLedger { session_ledger: ledger },
+                    None => return Outcome::Failure((Status::NotFound, ()))
+                }
+            },
+            None => {
+                let ledger = request.guard::<State<MutLedger>>()?;
+                MutLedger { session_ledger: ledger.session_ledger.clone() }
+            }
+        };
+        turns::resolve_due(&ledger);
+        session::advance(&ledger);
+        Outcome::Success(MarketLedger(ledger))
+    }
+}
+
+//Fairing that routes `/markets/<id>/...` to the regular routes, remembering
+//the market id for the `MarketLedger` guard. Every request also brings the
+//current tournament up to date with its match markets
+pub struct MarketRouter;
+
+impl Fairing for MarketRouter {
+    fn info(&self) -> Info {
+        Info { name: "Market router", kind: Kind::Request }
+    }
+
+    fn on_request(&self, request: &mut Request, _: &Data) {
+        if let (Outcome::Success(tournaments), Outcome::Success(markets)) = (request.guard::<State<Tournaments>>(), request.guard::<State<Markets>>()) {
+            tournaments.tick(&markets);
+        }
+        let path = request.uri().path().to_string();
+        let rest = match path.strip_prefix("/markets/") {
+            Some(rest) => rest,
+            None => return
+        };
+        let (id, route) = match rest.find('/') {
+            Some(pos) => (&rest[..pos], &rest[pos..]),
+            None => return
+        };
+        let uri = match request.uri().query() {
+            Some(query) => format!("{}?{}", route, query),
+            None => route.to_string()
+        };
+        if let Ok(origin) = Origin::parse_owned(uri) {
+            request.local_cache(|| MarketId(Some(id.to_string())));
+            request.set_uri(origin);
+        }
+    }
+}
+
+#[derive(Debug, FromForm)]
+pub struct NewMarket {
+    pub id: String,
+    pub config: Option<String>
+}
+
+/// Endpoint to list every market with its number of vendors, ledger version
+/// and session phase via http request
+#[get("/markets")]
+pub fn request_markets(markets: State<Markets>) -> content::Json<String> {
+    let listing: BTreeMap<String, serde_json::Value> = markets.ledgers.read().unwrap().iter().map(|(id, ledger)| {
+        let ledger = ledger.read().unwrap();
+        (id.clone(), serde_json::json!({
+            "vendors": ledger.get_vendor_names().len(),
+            "version": ledger.get_version(),
+            "phase": ledger.get_session().phase
+        }))
+    }).collect();
+    content::Json(to_string(&listing).unwrap())
+}
+
+/// Endpoint to create a new market via http request. The optional config is
+/// TOML that overrides `Config.toml` for the new market, and the market gets
+/// its own `generated_vendors`, whose UUIDs are answered by vendor name.
+/// Requires the admin secret
+///
+/// # Arguments
+///
+/// * `new_market`  - The DTO with the id and config of the market
+/// * `markets`     - Every market on the server
+#[post("/markets", data="<new_market>")]
+pub fn http_create(_admin: Admin, new_market: Result<Form<NewMarket>, FormError<'_>>, markets: State<Markets>) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let new_market = match new_market {
+        Ok(m) => m.into_inner(),
+        Err(_) => {
+            output_vars.insert("Format".to_string(), Box::new("incorrect"));
+            return util::construct_json(&output_vars);
+        }
+    };
+    if !valid_id(&new_market.id) {
+        output_vars.insert("success".to_string(), Box::new(false));
+        output_vars.insert("id".to_string(), Box::new("must be letters, digits, dashes or underscores".to_string()));
+        return util::construct_json(&output_vars);
+    }
+    let config = match parse_config(&new_market.config) {
+        Some(c) => c,
+        None => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("config".to_string(), Box::new("is not valid TOML".to_string()));
+            return util::construct_json(&output_vars);
+        }
+    };
+    if !util::keeps_catalog(&config) {
+        output_vars.insert("success".to_string(), Box::new(false));
+        output_vars.insert("config".to_string(), Box::new("catalog differs from the running catalog, it can only be changed at startup".to_string()));
+        return util::construct_json(&output_vars);
+    }
+
+    let mut ledger = Ledger::with_config(config);
+    let ids = ledger.generate_vendors();
+    let vendors = scenario::vendor_uuids(&ledger, ids);
+    if !markets.insert(new_market.id.clone(), ledger) {
+        output_vars.insert("success".to_string(), Box::new(false));
+        output_vars.insert("id".to_string(), Box::new("is in use".to_string()));
+        return util::construct_json(&output_vars);
+    }
+    let output = json!({ "success": "true", "market": format!("/markets/{}/", new_market.id), "vendors": vendors });
+    content::Json(to_string(&output).unwrap())
+}
+
+/// Endpoint to delete a market via http request, the main market cannot be
+/// deleted. Requires the admin secret
+///
+/// # Arguments
+///
+/// * `id`      - The id of the market
+/// * `markets` - Every market on the server
+#[delete("/markets/<id>")]
+pub fn http_delete(_admin: Admin, id: String, markets: State<Markets>) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let removed = markets.remove(&id);
+    output_vars.insert("success".to_string(), Box::new(removed));
+    if !removed {
+        output_vars.insert("id".to_string(), Box::new("not found or cannot be deleted".to_string()));
+    }
+    util::construct_json(&output_vars)
+}
@@ -0,0 +1,69 @@
+use rocket::response::content;
+use super::markets::MarketLedger;
+use serde_json::to_string;
+
+//What a vendor has to achieve by the end of the session
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Goal {
+    Hold { item: String, count: u32 },
+    Corner { item: String, share: Option<f64> },
+    Trades { count: u32 }
+}
+
+impl Goal {
+    /// Describes the goal for the objectives listing
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current goal
+    pub fn describe(&self) -> String {
+        match self {
+            Goal::Hold { item, count } => format!("Hold {} {} at the end", count, item),
+            Goal::Corner { item, .. } => format!("Corner the {} market, holding {:.0}% of all units", item, self.target() * 100.0),
+            Goal::Trades { count } => format!("Complete {} trades", count)
+        }
+    }
+
+    /// Returns the amount of progress needed to complete the goal, units for
+    /// holding, the share of all units for cornering and trades for trading
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current goal
+    pub fn target(&self) -> f64 {
+        match self {
+            Goal::Hold { count, .. } | Goal::Trades { count } => *count as f64,
+            Goal::Corner { share, .. } => share.unwrap_or(0.5)
+        }
+    }
+}
+
+//An objective from the `[[objectives]]` tables of the config. Objectives without
+//a vendor apply to every vendor, and completing one is worth `points`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Objective {
+    pub vendor: Option<String>,
+    pub points: Option<f64>,
+    pub goal: Goal
+}
+
+//How far a vendor is with one of its objectives
+#[derive(Clone, Debug, Serialize)]
+pub struct Progress {
+    pub vendor: String,
+    pub objective: String,
+    pub progress: f64,
+    pub target: f64,
+    pub complete: bool,
+    pub points: f64
+}
+
+/// Endpoint to get the progress of every vendor on its objectives via http
+/// request, final once the session has ended
+#[get("/objectives")]
+pub fn request_objectives(ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    content::Json(to_string(&ledger.get_objectives()).unwrap())
+}
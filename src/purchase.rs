@@ -3,17 +3,43 @@ use std::fmt::Display;
 
 use rocket::response::content;
 use rocket::request::{Form, FormError};
-use rocket::State;
+use super::markets::MarketLedger;
 use rocket_contrib::templates::Template;
 use serde_json::to_value;
+use super::escrow::Delivery;
+use super::ledger::MutLedger;
+use super::turns;
 
-//Holds purchase order data, merchandise goes FROM the SELLER, TO the BUYER
-#[derive(Debug, FromForm)]
+//Holds purchase order data, merchandise goes FROM the SELLER, TO the BUYER.
+//Delivery is instant unless `escrow` or `shipment` is requested
+#[derive(Clone, Debug, FromForm)]
 pub struct Order {
     pub item: String,
     pub count: u32,
     pub from: String,
-    pub to: String
+    pub to: String,
+    pub delivery: Option<String>
+}
+
+/// Function for placing a purchase order, which fills it right away or queues
+/// it for the current turn when the market is turn-based
+/// 
+/// # Arguments
+/// 
+/// * `order`   - The purchase order being made
+/// * `ledger`  - The current ledger state
+fn purchase(order: Order, ledger: MarketLedger) -> BTreeMap<String, Box<dyn Display>> {
+    if ledger.inner().session_ledger.read().unwrap().is_spectator(&order.to) {
+        return super::authorization::reject_spectator();
+    }
+    if let Err(limited) = super::limits::admit(ledger.inner(), &order.to, super::limits::Call::Purchase) {
+        return limited;
+    }
+    if ledger.inner().session_ledger.read().unwrap().turn_based() {
+        let uuid = order.to.clone();
+        return turns::submit(turns::Action::Purchase(order), &uuid, ledger.inner());
+    }
+    fill_order(order, ledger.inner())
 }
 
 /// Function for performing a purchase, including confirming resources and
@@ -21,17 +47,17 @@ pub struct Order {
 /// 
 /// # Arguments
 /// 
-/// * `order`   - The purchase order being made
+/// * `order`   - The purchase order being filled
 /// * `ledger`  - The current ledger state
 #[allow(unused_assignments)]
-fn purchase(order: Order, ledger: State<super::ledger::MutLedger>) -> BTreeMap<String, Box<dyn Display>> {
-    let arc_ledger = ledger.inner().session_ledger.clone();
+pub fn fill_order(order: Order, ledger: &MutLedger) -> BTreeMap<String, Box<dyn Display>> {
+    let arc_ledger = ledger.session_ledger.clone();
     let buyer_name: String;
     let seller_name: String;
     let buyer_bits: f64;
     let item_found: bool;
     
-    let mut item_price: f64 = 0.0;
+    let mut total: f64 = 0.0;
     let mut item_count: u32 = 0;
     let buyer_pos: usize;
     let mut seller_pos: usize;
@@ -56,11 +82,20 @@ fn purchase(order: Order, ledger: State<super::ledger::MutLedger>) -> BTreeMap<S
             return output_vars
         }
         
+        if let Err(closed) = super::authorization::check_trading(ledger, buyer_pos) {
+            return closed;
+        }
+
+        if ledger.is_halted(&order.item) {
+            output_vars.insert("item".to_string(), Box::new("trading halted by circuit breaker".to_string()));
+            return output_vars
+        }
+
         let from = ledger.get_vendor(seller_pos);
         seller_name = from.name.clone();
         item_found = match from.get_item(&order.item) {
             Some(i) => {
-                item_price = i.price;
+                total = i.cost(order.count);
                 item_count = match i.get_count() {
                     0 => {
                         output_vars.insert("item".to_string(), Box::new("out of stock".to_string()));
@@ -77,26 +112,38 @@ fn purchase(order: Order, ledger: State<super::ledger::MutLedger>) -> BTreeMap<S
         };
 
         let to = ledger.get_vendor(buyer_pos);
-        buyer_bits = to.bits;
+        buyer_bits = ledger.team_bits(buyer_pos) + ledger.credit_limit(buyer_pos);
         buyer_name = to.name.clone();
     }
 
-    let total = item_price * (order.count as f64);
     if total > buyer_bits {
         output_vars.insert("buyer".to_string(), Box::new("cannot afford the purchase".to_string()));
         return output_vars
     }
 
+    let order_count = order.count;
+    if Delivery::parse(&order.delivery).is_none() {
+        output_vars.insert("delivery".to_string(), Box::new("must be instant, escrow or shipment".to_string()));
+        return output_vars
+    }
+
     let mut understock = 0;
+    let mut escrow = None;
     let mut success = false;
 
     if item_found && (buyer_bits >= total) && item_count > 0 {
         success = true;
-        understock = (*arc_ledger).write().unwrap().purchase(order, seller_pos, buyer_pos, item_price);
+        let receipt = (*arc_ledger).write().unwrap().purchase(order, seller_pos, buyer_pos);
+        understock = receipt.0;
+        escrow = receipt.1;
     }
 
+    if let Some(id) = escrow {
+        output_vars.insert("escrow".to_string(), Box::new(id));
+    }
     output_vars.insert("success".to_string(), Box::new(success));
     output_vars.insert("total".to_string(), Box::new(total));
+    output_vars.insert("unit_price".to_string(), Box::new(if order_count > 0 { total / order_count as f64 } else { 0.0 }));
     output_vars.insert("understock".to_string(), Box::new(understock));
     output_vars.insert("seller".to_string(), Box::new(seller_name));
     output_vars.insert("buyer".to_string(), Box::new(buyer_name));
@@ -111,7 +158,7 @@ fn purchase(order: Order, ledger: State<super::ledger::MutLedger>) -> BTreeMap<S
 /// * `order_data`  - The DTO for the purchase order being completed
 /// * `ledger`      - The current ledger state
 #[post("/purchase", data="<order>")]
-pub fn http_purchase(order: Result<Form<Order>, FormError<'_>>, ledger: State<super::ledger::MutLedger>) -> content::Json<String> {
+pub fn http_purchase(order: Result<Form<Order>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
     match order {
         Ok(o) => super::util::construct_json(&purchase(o.into_inner(), ledger)),
         Err(_) => {
@@ -129,7 +176,7 @@ pub fn http_purchase(order: Result<Form<Order>, FormError<'_>>, ledger: State<su
 /// * `order`   - The purchase order information from the form
 /// * `ledger`  - The current ledger state
 #[post("/purchase", data="<order>")]
-pub fn form_purchase(order: Result<Form<Order>, FormError<'_>>, ledger: State<super::ledger::MutLedger>) -> Template {
+pub fn form_purchase(order: Result<Form<Order>, FormError<'_>>, ledger: MarketLedger) -> Template {
     let mut map = super::HashMap::new();
     let mut response = match order {
         Ok(o) => purchase(o.into_inner(), ledger),
@@ -144,12 +191,49 @@ pub fn form_purchase(order: Result<Form<Order>, FormError<'_>>, ledger: State<su
     Template::render("purchase_response", &map)
 }
 
+/// Endpoint to get a price quote for buying a number of units from a vendor
+/// via http request, including the effective unit price across bulk tiers
+/// 
+/// # Arguments
+/// 
+/// * `item`    - The name of the item
+/// * `count`   - The number of units to quote
+/// * `from`    - The name of the seller
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/quote?<item>&<count>&<from>&<token>")]
+pub fn request_quote(item: String, count: u32, from: String, token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) {
+        return super::util::construct_json(&super::authorization::reject_hidden());
+    }
+    let quoted = ledger.find_vendor(&from).and_then(|pos| ledger.get_vendor(pos).get_item(&item).cloned());
+    match quoted {
+        Some(i) => {
+            let total = i.cost(count);
+            output_vars.insert("total".to_string(), Box::new(total));
+            output_vars.insert("unit_price".to_string(), Box::new(if count > 0 { total / count as f64 } else { i.price }));
+            output_vars.insert("available".to_string(), Box::new(i.get_count()));
+        },
+        None => { output_vars.insert("item".to_string(), Box::new("not found at seller".to_string())); }
+    }
+    super::util::construct_json(&output_vars)
+}
+
 /// Purchasing page GET endpoint
-#[get("/purchase")]
-pub fn purchase_page(ledger: State<super::ledger::MutLedger>) -> Template {
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/purchase?<token>")]
+pub fn purchase_page(token: Option<String>, ledger: MarketLedger) -> Template {
     let mut map = super::HashMap::new();
     let arc_ledger = ledger.inner().session_ledger.clone();
     let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) { return super::authorization::hidden_page("/purchase"); }
     map.insert("names", to_value(ledger.get_vendor_names()).unwrap());
     map.insert("urls", to_value(ledger.get_vendor_urls()).unwrap());
     map.insert("ledger_state", to_value(ledger.serialize_state()).unwrap());
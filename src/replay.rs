@@ -0,0 +1,61 @@
+use std::collections::BTreeMap;
+
+use rocket::response::content;
+use super::markets::MarketLedger;
+use rocket_contrib::templates::Template;
+use serde_json::{to_string, to_value};
+use super::util;
+
+//A vendor as it stood at an earlier ledger version, with the units it owned
+//of each item, stocked and stored together
+#[derive(Clone, Debug, Serialize)]
+pub struct ReplayVendor {
+    pub name: String,
+    pub bits: f64,
+    pub items: BTreeMap<String, i64>
+}
+
+/// Endpoint to get the vendors and price history at a ledger version via http
+/// request, defaulting to the latest version
+///
+/// # Arguments
+///
+/// * `version` - The ledger version to replay
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/replay?<version>&<token>")]
+pub fn request_replay(version: Option<u32>, token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) {
+        return util::construct_json(&super::authorization::reject_hidden());
+    }
+    let latest = ledger.get_version();
+    let version = version.unwrap_or(latest).min(latest);
+    let output = serde_json::json!({
+        "version": version,
+        "latest": latest,
+        "vendors": ledger.replay(version),
+        "types": util::get_rust_types(),
+        "history": ledger.get_price_history_at(version)
+    });
+    content::Json(to_string(&output).unwrap())
+}
+
+/// Replay viewer page GET endpoint, steps through every ledger version of the
+/// session
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/replay?<token>")]
+pub fn replay_page(token: Option<String>, ledger: MarketLedger) -> Template {
+    let mut map = super::HashMap::new();
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) { return super::authorization::hidden_page("/replay"); }
+    map.insert("latest", to_value(ledger.get_version()).unwrap());
+    map.insert("types", to_value(util::get_rust_types()).unwrap());
+    Template::render("replay", &map)
+}
@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+use rocket::response::content;
+use super::markets::MarketLedger;
+use serde_json::to_string;
+
+//Track record of a vendor, built up from every order, contract delivery and
+//price change the vendor is part of
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Reputation {
+    pub fulfilled: u32,
+    pub understocked: u32,
+    pub defaulted: u32,
+    pub repricings: u32,
+    pub volatility: f64,
+    pub volume: f64,
+    pub trades: u32
+}
+
+impl Reputation {
+    /// Records an order sold by the vendor, understocked if any units were
+    /// missing
+    ///
+    /// # Arguments
+    ///
+    /// * `self`        - The current reputation object
+    /// * `understock`  - The number of units the vendor could not sell
+    pub fn order(&mut self, understock: u32) {
+        match understock {
+            0 => self.fulfilled += 1,
+            _ => self.understocked += 1
+        }
+    }
+
+    /// Records a price change, keeping the mean relative size of every price
+    /// change as the vendor's volatility
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current reputation object
+    /// * `before`  - The price before the change
+    /// * `after`   - The price after the change
+    pub fn reprice(&mut self, before: f64, after: f64) {
+        if before <= 0.0 || before == after || !after.is_finite() { return; }
+        let change = ((after - before) / before).abs();
+        self.volatility = (self.volatility * self.repricings as f64 + change) / (self.repricings + 1) as f64;
+        self.repricings += 1;
+    }
+
+    /// Scores the vendor between 0 and 100. Reliability, the share of orders
+    /// and contract deliveries filled in full, counts for 60 points, price
+    /// stability for 25 and trade volume, relative to the initial bits of a
+    /// vendor, for 15. New vendors start at 85
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current reputation object
+    pub fn score(&self) -> f64 {
+        let initial_bits = super::get_config::<f64>("initial_bits").unwrap_or(1000.0);
+        let orders = (self.fulfilled + self.understocked + self.defaulted) as f64;
+        let reliability = (self.fulfilled as f64 + 1.0) / (orders + 1.0);
+        let stability = 1.0 / (1.0 + self.volatility);
+        let activity = self.volume / (self.volume + initial_bits);
+        100.0 * (0.6 * reliability + 0.25 * stability + 0.15 * activity)
+    }
+}
+
+/// Endpoint to get the reputation of every vendor via http request, with the
+/// score of each vendor
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/reputation?<token>")]
+pub fn request_reputation(token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) {
+        return super::util::construct_json(&super::authorization::reject_hidden());
+    }
+    let reputation: BTreeMap<String, serde_json::Value> = ledger.get_vendor_names().into_iter()
+        .zip(ledger.get_reputation())
+        .map(|(name, r)| {
+            let mut value = serde_json::to_value(&r).unwrap();
+            value["score"] = serde_json::Value::from(r.score());
+            (name, value)
+        })
+        .collect();
+    content::Json(to_string(&reputation).unwrap())
+}
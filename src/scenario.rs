@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use config::{Config, ConfigError, File, FileFormat};
+use rocket::State;
+use rocket::request::{Form, FormError};
+use rocket::response::content;
+use serde_json::{json, to_string};
+use super::admin::Admin;
+use super::ledger::{Ledger, LedgerError};
+use super::markets::{self, Markets, MAIN_MARKET};
+use super::shop::Item;
+use super::util;
+
+//A vendor declared by a scenario, it starts with exactly the listed items
+//instead of random ones from the catalog
+#[derive(Clone, Debug, Deserialize)]
+pub struct ScenarioVendor {
+    pub name: String,
+    pub url: Option<String>,
+    pub bits: Option<f64>,
+    pub team: Option<String>,
+    #[serde(default)]
+    pub items: Vec<Item>
+}
+
+#[derive(Debug, FromForm)]
+pub struct NewScenario {
+    pub market: Option<String>,
+    pub scenario: String
+}
+
+/// Reads a scenario file, which holds the same rules as `Config.toml` along
+/// with `[[vendors]]`, `[[catalog]]` and `[[events]]` tables
+///
+/// # Arguments
+///
+/// * `path`    - The path of the scenario file
+pub fn read_file(path: &str) -> Option<Config> {
+    let mut config = Config::default();
+    match config.merge(File::with_name(path)) {
+        Ok(_) => Some(config),
+        Err(_) => None
+    }
+}
+
+/// Builds the ledger of a market from a scenario. The rules override
+/// `Config.toml`, and the declared vendors replace the generated ones when
+/// there are any. Returns the ledger with the IDs of its vendors
+///
+/// # Arguments
+///
+/// * `scenario`    - The parsed scenario
+pub fn build(scenario: Config) -> Result<(Ledger, Vec<String>), String> {
+    if !util::keeps_catalog(&scenario) {
+        return Err("catalog differs from the running catalog, it can only be changed at startup".to_string());
+    }
+    let vendors = match scenario.get::<Vec<ScenarioVendor>>("vendors") {
+        Ok(v) => v,
+        Err(ConfigError::NotFound(_)) => vec![],
+        Err(_) => return Err("vendors are not valid".to_string())
+    };
+    let initial_bits = scenario.get::<f64>("initial_bits").ok().or_else(|| super::get_config::<f64>("initial_bits")).unwrap_or(1000.0);
+
+    let mut ledger = Ledger::with_config(scenario);
+    if vendors.is_empty() {
+        let ids = ledger.generate_vendors();
+        return Ok((ledger, ids));
+    }
+    let mut ids = vec![];
+    for v in vendors {
+        let bits = v.bits.unwrap_or(initial_bits);
+        let id = match ledger.register_vendor_with(v.name.clone(), v.url, bits, v.items) {
+            Ok(id) => id,
+            Err(e) => return Err(format!("vendor {} {}", v.name, vendor_error(e)))
+        };
+        if v.team.is_some() {
+            let pos = ledger.verify_uuid(id.clone()).unwrap();
+            if ledger.set_team(pos, v.team).is_err() {
+                return Err(format!("vendor {} cannot join its team", v.name));
+            }
+        }
+        ids.push(id);
+    }
+    Ok((ledger, ids))
+}
+
+/// Maps the name of each vendor built from a scenario to its UUID, for the
+/// operator to hand out
+///
+/// # Arguments
+///
+/// * `ledger`  - The ledger built from the scenario
+/// * `ids`     - The UUIDs of its vendors
+pub fn vendor_uuids(ledger: &Ledger, ids: Vec<String>) -> BTreeMap<String, String> {
+    ids.into_iter()
+       .filter_map(|id| ledger.verify_uuid(id.clone()).ok().map(|pos| (ledger.get_vendor(pos).name, id)))
+       .collect()
+}
+
+/// Converts a ledger error from registering a scenario vendor into a readable
+/// message
+///
+/// # Arguments
+///
+/// * `err` - The error returned by the ledger
+fn vendor_error(err: LedgerError) -> String {
+    match err {
+        LedgerError::ExistingVendor => "is declared twice".to_string(),
+        LedgerError::ExistingUrl => "has a url that is in use".to_string(),
+        LedgerError::InvalidAmount => "has invalid bits or items".to_string(),
+        _ => "could not be registered".to_string()
+    }
+}
+
+/// Endpoint to load a scenario into a market via http request. The scenario is
+/// TOML in the scenario file format, and replaces the whole state of the
+/// market, creating the market if it does not exist. Answers with the UUID of
+/// every vendor by name. Requires the admin secret
+///
+/// # Arguments
+///
+/// * `new_scenario`    - The DTO with the market id and the scenario
+/// * `markets`         - Every market on the server
+#[post("/scenario", data="<new_scenario>")]
+pub fn http_load(_admin: Admin, new_scenario: Result<Form<NewScenario>, FormError<'_>>, markets: State<Markets>) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let new_scenario = match new_scenario {
+        Ok(s) => s.into_inner(),
+        Err(_) => {
+            output_vars.insert("Format".to_string(), Box::new("incorrect"));
+            return util::construct_json(&output_vars);
+        }
+    };
+    let id = match new_scenario.market.as_deref() {
+        None | Some("") => MAIN_MARKET.to_string(),
+        Some(id) => id.to_string()
+    };
+    if !markets::valid_id(&id) {
+        output_vars.insert("success".to_string(), Box::new(false));
+        output_vars.insert("market".to_string(), Box::new("must be letters, digits, dashes or underscores".to_string()));
+        return util::construct_json(&output_vars);
+    }
+
+    let mut scenario = Config::default();
+    if scenario.merge(File::from_str(&new_scenario.scenario, FileFormat::Toml)).is_err() {
+        output_vars.insert("success".to_string(), Box::new(false));
+        output_vars.insert("scenario".to_string(), Box::new("is not valid TOML".to_string()));
+        return util::construct_json(&output_vars);
+    }
+    let (ledger, ids) = match build(scenario) {
+        Ok(built) => built,
+        Err(e) => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("scenario".to_string(), Box::new(e));
+            return util::construct_json(&output_vars);
+        }
+    };
+
+    let vendors = vendor_uuids(&ledger, ids);
+    match markets.get(&id) {
+        Some(existing) => { *existing.write().unwrap() = ledger; },
+        None => { markets.insert(id.clone(), ledger); }
+    }
+    content::Json(to_string(&json!({ "success": "true", "vendors": vendors })).unwrap())
+}
@@ -0,0 +1,127 @@
+use rocket::response::content;
+use super::ledger::MutLedger;
+use super::markets::MarketLedger;
+use serde_json::to_string;
+use super::objectives::Progress;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Lobby,
+    Running,
+    Ended
+}
+
+//Place of a vendor in the session, ranked by the points of completed
+//objectives and then net worth. Teammates share the rank of their team's
+//combined points and net worth
+#[derive(Clone, Debug, Serialize)]
+pub struct Standing {
+    pub rank: usize,
+    pub vendor: String,
+    pub team: Option<String>,
+    pub bits: f64,
+    pub net_worth: f64,
+    pub points: f64
+}
+
+//Lifecycle of the game. The session waits in the lobby until `min_vendors`
+//have registered, runs until `seconds` have passed or `versions` ledger
+//versions were added since it started, then freezes trading for good
+#[derive(Clone, Debug, Serialize)]
+pub struct Session {
+    pub phase: Phase,
+    pub min_vendors: usize,
+    pub seconds: Option<u64>,
+    pub versions: Option<u32>,
+    pub started_version: Option<u32>,
+    pub started_seconds: Option<u64>,
+    pub ended_version: Option<u32>,
+    pub standings: Vec<Standing>,
+    pub objectives: Vec<Progress>
+}
+
+impl Session {
+    /// Creates a session in the lobby with the given limits, where zero means
+    /// no limit
+    ///
+    /// # Arguments
+    ///
+    /// * `min_vendors` - The number of vendors needed to start trading
+    /// * `seconds`     - The number of seconds the session runs for
+    /// * `versions`    - The number of ledger versions the session runs for
+    pub fn new(min_vendors: usize, seconds: u64, versions: u32) -> Session {
+        Session {
+            phase: Phase::Lobby,
+            min_vendors,
+            seconds: if seconds > 0 { Some(seconds) } else { None },
+            versions: if versions > 0 { Some(versions) } else { None },
+            started_version: None,
+            started_seconds: None,
+            ended_version: None,
+            standings: vec![],
+            objectives: vec![]
+        }
+    }
+
+    /// Creates a session from the `session_vendors`, `session_seconds` and
+    /// `session_versions` limits in `Config.toml`
+    pub fn from_config() -> Session {
+        Session::new(
+            super::get_config::<usize>("session_vendors").unwrap_or(0),
+            super::get_config::<u64>("session_seconds").unwrap_or(0),
+            super::get_config::<u32>("session_versions").unwrap_or(0)
+        )
+    }
+
+    /// Returns true if a running session has reached its time or version limit
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current session
+    /// * `version` - The current ledger version
+    /// * `elapsed` - Seconds since the ledger was created
+    pub fn is_over(&self, version: u32, elapsed: u64) -> bool {
+        self.phase == Phase::Running && (
+            self.versions.map_or(false, |v| version >= self.started_version.unwrap_or(0) + v) ||
+            self.seconds.map_or(false, |s| elapsed >= self.started_seconds.unwrap_or(0) + s)
+        )
+    }
+}
+
+/// Starts or ends the session of a market once it is due, taking the write
+/// lock only then. Runs before every request to the market
+///
+/// # Arguments
+///
+/// * `ledger`  - The current ledger state
+pub fn advance(ledger: &MutLedger) {
+    if ledger.session_ledger.read().unwrap().session_due() {
+        ledger.session_ledger.write().unwrap().update_session();
+    }
+}
+
+/// Endpoint to get the phase and limits of the session via http request
+#[get("/session")]
+pub fn request_session(ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    content::Json(to_string(&ledger.get_session()).unwrap())
+}
+
+/// Endpoint to get the standings of the vendors via http request, final once
+/// the session has ended
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/standings?<token>")]
+pub fn request_standings(token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) {
+        return super::util::construct_json(&super::authorization::reject_hidden());
+    }
+    content::Json(to_string(&ledger.get_standings()).unwrap())
+}
@@ -1,8 +1,9 @@
 use rocket::response::content;
 use rocket::request::{Form, FormError};
-use rocket::State;
+use super::markets::MarketLedger;
 use rocket_contrib::templates::Template;
-use super::ledger::MutLedger;
+use super::ledger::{LedgerError, MutLedger, VendorState};
+use super::turns;
 use serde_json::to_value;
 use std::collections::BTreeMap;
 use std::fmt;
@@ -18,22 +19,91 @@ pub struct Item {
     pub name: String,
     pub price: f64,
     stocked: u32,
-    stored: u32
+    stored: u32,
+    #[serde(default)]
+    tiers: Vec<Tier>
 }
 
-#[derive(Debug, FromForm)]
+//Bulk price breakpoint, every unit from the `min`th unit of an order onward
+//costs `price` until the next breakpoint
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Tier {
+    pub min: u32,
+    pub price: f64
+}
+
+//Change request for an item. Tiers are written as `min:price` pairs separated
+//by commas, an empty string clears them and leaving them out keeps them
+#[derive(Clone, Debug, FromForm)]
 pub struct AuthItem {
     pub name: String,
     pub price: f64,
     pub stock: i32,
-    pub uuid: String
+    pub uuid: String,
+    pub tiers: Option<String>
+}
+
+/// Parses a price schedule of `min:price` pairs, returning None if any pair
+/// is malformed or the schedule is not valid
+/// 
+/// # Arguments
+/// 
+/// * `schedule`    - The comma separated price schedule
+pub fn parse_tiers(schedule: &str) -> Option<Vec<Tier>> {
+    let mut tiers: Vec<Tier> = vec![];
+    for pair in schedule.split(',').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        let mut parts = pair.split(':');
+        let min = parts.next()?.trim().parse::<u32>().ok()?;
+        let price = parts.next()?.trim().parse::<f64>().ok()?;
+        if parts.next().is_some() { return None; }
+        tiers.push(Tier { min, price });
+    }
+    if valid_tiers(&tiers) { Some(tiers) } else { None }
+}
+
+/// Checks a price schedule, every breakpoint has to be 2 or more and above
+/// the one before it, with a finite price that is not negative
+/// 
+/// # Arguments
+/// 
+/// * `tiers`   - The price schedule
+pub fn valid_tiers(tiers: &[Tier]) -> bool {
+    tiers.iter().all(|t| t.min >= 2 && t.price.is_finite() && t.price >= 0.0) &&
+    tiers.windows(2).all(|w| w[0].min < w[1].min)
 }
 
 impl Item {
     pub fn new(name: String, price: f64, stocked: u32, stored: u32) -> Item {
-        Item { name, price, stocked, stored }
+        Item { name, price, stocked, stored, tiers: vec![] }
     }
 
+    /// Gets the total price of buying a number of units in one order, with
+    /// each unit priced by the tier it falls in
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current item object
+    /// * `count`   - The number of units in the order
+    pub fn cost(&self, count: u32) -> f64 {
+        let mut total = 0.0;
+        let mut price = self.price;
+        let mut from = 1;
+        for tier in self.tiers.iter().filter(|t| t.min <= count) {
+            total += (tier.min - from) as f64 * price;
+            price = tier.price;
+            from = tier.min;
+        }
+        if count >= from { total += (count - from + 1) as f64 * price; }
+        total
+    }
+
+    /// Gets the bulk price breakpoints of this item
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current item object
+    pub fn get_tiers(&self) -> Vec<Tier> { self.tiers.clone() }
+
     /// Gets the vendor's stock for this item
     /// 
     /// # Arguments
@@ -60,20 +130,29 @@ impl Item {
     }
 
     fn stock_item(&mut self, count: u32) {
-        if count <= self.stored {
-            self.stocked += self.stored - count;
-            self.stored -= count;
-        } else {
-            self.stocked += self.stored;
-            self.stored = 0;
-        }
+        let diff = std::cmp::min(self.stored, count);
+        self.stored -= diff;
+        self.stocked += diff;
     }
 
     fn store_item(&mut self, count: u32) {
         self.stored += count;
     }
 
-    fn total(&self) -> u32 { self.stocked + self.stored }
+    fn take_item(&mut self, count: u32) -> u32 {
+        let from_store = std::cmp::min(self.stored, count);
+        self.stored -= from_store;
+        let from_stock = std::cmp::min(self.stocked, count - from_store);
+        self.stocked -= from_stock;
+        from_store + from_stock
+    }
+
+    /// Gets the vendor's total holdings of this item, stocked and stored
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current item object
+    pub fn total(&self) -> u32 { self.stocked + self.stored }
 
     fn update(&mut self, price: f64, count: i32) {
         self.price = price;
@@ -94,22 +173,25 @@ impl PartialEq for Item {
         self.name == other.name &&
         self.price == other.price &&
         self.stocked == other.stocked &&
-        self.stored == other.stored
+        self.stored == other.stored &&
+        self.tiers == other.tiers
     }
 }
 
-//A single vendor in the market
+//A single vendor in the market, teammates share their bits as one treasury
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Vendor {
     pub name: String,
     pub url: String,
     pub bits: f64,
-    pub items: Vec<Item>
+    pub items: Vec<Item>,
+    #[serde(default)]
+    pub team: Option<String>
 }
 
 impl Vendor {
     pub fn new(name: String, url: String, bits: f64) -> Vendor{
-        Vendor{ name, url, bits, items: vec![] }
+        Vendor{ name, url, bits, items: vec![], team: None }
     }
 
     /// Adds an item to the vendor
@@ -171,6 +253,21 @@ impl Vendor {
         }
     }
 
+    /// Removes units of an item from the vendor, taking from the store before
+    /// the stock, and returns the number of units actually removed
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `item`    - The name of the item to remove
+    /// * `count`   - The number of units to remove
+    pub fn remove_item(&mut self, item: &String, count: u32) -> u32 {
+        match self.grab_item(item) {
+            Some(i) => i.take_item(count),
+            None => 0
+        }
+    }
+
     /// Updates the price and the counts of the item
     /// 
     /// # Arguments
@@ -183,6 +280,17 @@ impl Vendor {
         if let Some(i) = self.grab_item(&item) { i.update(price, count); }
     }
 
+    /// Replaces the bulk price breakpoints of the item
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current ledger object
+    /// * `item`    - The name of the item to update
+    /// * `tiers`   - The new price schedule, empty for a single price
+    pub fn update_tiers(&mut self, item: String, tiers: Vec<Tier>) {
+        if let Some(i) = self.grab_item(&item) { i.tiers = tiers; }
+    }
+
     fn grab_item(&mut self, name: &String) -> Option<&mut Item> {
         self.items.iter_mut().find(|i| &i.name == name)
     }
@@ -193,6 +301,7 @@ impl PartialEq for Vendor {
         self.name == other.name &&
         self.url == other.url &&
         self.bits == other.bits &&
+        self.team == other.team &&
         self.items.len() == other.items.len() &&
         self.items.iter()
                   .zip(&other.items)
@@ -206,12 +315,34 @@ impl fmt::Debug for Vendor {
          .field("Name", &self.name)
          .field("Url", &self.url)
          .field("Bits", &self.bits)
+         .field("Team", &self.team)
          .field("Item Count", &self.items.len())
          .field("Items", &self.items)
          .finish()
     }
 }
 
+/// A function for submitting an item change request, which applies it right
+/// away or queues it for the current turn when the market is turn-based
+/// 
+/// # Arguments
+/// 
+/// `auth_item` - The auth item change request
+/// `ledger`    - The current ledger state
+fn stock(auth_item: AuthItem, ledger: MarketLedger) -> BTreeMap<String, Box<dyn Display>> {
+    if ledger.inner().session_ledger.read().unwrap().is_spectator(&auth_item.uuid) {
+        return super::authorization::reject_spectator();
+    }
+    if let Err(limited) = super::limits::admit(ledger.inner(), &auth_item.uuid, super::limits::Call::Stock) {
+        return limited;
+    }
+    if ledger.inner().session_ledger.read().unwrap().turn_based() {
+        let uuid = auth_item.uuid.clone();
+        return turns::submit(turns::Action::Stock(auth_item), &uuid, ledger.inner());
+    }
+    apply_stock(auth_item, ledger.inner())
+}
+
 /// A function for updating the state of an object through changing the price
 /// and moving units from the stock to the store or vice versa
 /// 
@@ -219,12 +350,22 @@ impl fmt::Debug for Vendor {
 /// 
 /// `auth_item` - The auth item change request
 /// `ledger`    - The current ledger state
-fn stock(auth_item: AuthItem, ledger: State<super::ledger::MutLedger>) -> BTreeMap<String, Box<dyn Display>> {
-    let arc_ledger = ledger.inner().session_ledger.clone();
+pub fn apply_stock(auth_item: AuthItem, ledger: &MutLedger) -> BTreeMap<String, Box<dyn Display>> {
+    let arc_ledger = ledger.session_ledger.clone();
     let vendor_id: usize;
 
     let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
 
+    let tiers = match auth_item.tiers.as_ref().map(|t| parse_tiers(t)) {
+        Some(None) => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("tiers".to_string(), Box::new("must be increasing min:price pairs with min above 1".to_string()));
+            return output_vars;
+        },
+        Some(t) => t,
+        None => None
+    };
+
     {
         let ledger = &*arc_ledger.read().unwrap();
         vendor_id = match ledger.verify_uuid(auth_item.uuid) {
@@ -235,10 +376,42 @@ fn stock(auth_item: AuthItem, ledger: State<super::ledger::MutLedger>) -> BTreeM
                 return output_vars;
             }
         };
+        if let Err(closed) = super::authorization::check_trading(ledger, vendor_id) {
+            return closed;
+        }
+
+        let current = ledger.get_vendor(vendor_id).get_item(&auth_item.name).map(|i| (i.price, i.get_tiers()));
+        let repriced = match &current {
+            Some((price, current_tiers)) => *price != auth_item.price || tiers.as_ref().map_or(false, |t| t != current_tiers),
+            None => true
+        };
+        if ledger.is_price_frozen(&auth_item.name) && repriced {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("price".to_string(), Box::new("is frozen by a market event".to_string()));
+            return output_vars;
+        }
+
+        let mut prices = vec![auth_item.price];
+        prices.extend(tiers.iter().flatten().map(|t| t.price));
+        if repriced {
+            for price in prices {
+                let message = match ledger.check_price(&auth_item.name, price) {
+                    Ok(_) => continue,
+                    Err(LedgerError::TradingHalted) => "cannot change while trading is halted",
+                    Err(_) => "is outside the price band"
+                };
+                output_vars.insert("success".to_string(), Box::new(false));
+                output_vars.insert("price".to_string(), Box::new(message.to_string()));
+                return output_vars;
+            }
+        }
     }
 
     {
         let mut ledger = (&*arc_ledger).write().unwrap();
+        if let Some(t) = tiers {
+            ledger.update_tiers(vendor_id, auth_item.name.clone(), t);
+        }
         ledger.update_item(vendor_id, auth_item.name, auth_item.price, auth_item.stock);
     }
 
@@ -247,19 +420,35 @@ fn stock(auth_item: AuthItem, ledger: State<super::ledger::MutLedger>) -> BTreeM
 }
 
 /// An endpoint that displays all of the vendors currently in the market with
-/// the prices of their goods
+/// the prices of their goods, which with limited visibility are hidden from
+/// all but spectators until the session ends
 /// 
 /// # Arguments
 /// 
+/// * `token`     - A spectator token, needed with limited visibility
 /// * `ledger`    - The current ledger state
-#[get("/")]
-pub fn market_home(ledger: State<MutLedger>) -> Template {
+#[get("/?<token>")]
+pub fn market_home(token: Option<String>, ledger: MarketLedger) -> Template {
     let mut map = super::HashMap::new();
     let arc_ledger = ledger.inner().session_ledger.clone();
     let ledger = &*arc_ledger.read().unwrap();
-    map.insert("vendor_names", to_value(ledger.get_vendor_names()).unwrap());
-    map.insert("vendor_urls", to_value(ledger.get_vendor_urls()).unwrap());
-    map.insert("ledger_state", to_value(ledger.serialize_state()).unwrap());
+    let names = ledger.get_vendor_names();
+    let (state, urls, reputation) = match ledger.reveals_all(token.as_deref()) {
+        true => (
+            ledger.serialize_state(),
+            ledger.get_vendor_urls(),
+            ledger.get_reputation().iter().map(|r| r.score().round().to_string()).collect()
+        ),
+        false => (
+            names.iter().map(|name| (name.clone(), VendorState::default())).collect(),
+            vec![String::new(); names.len()],
+            vec!["hidden".to_string(); names.len()]
+        )
+    };
+    map.insert("vendor_names", to_value(names).unwrap());
+    map.insert("vendor_urls", to_value(urls).unwrap());
+    map.insert("ledger_state", to_value(state).unwrap());
+    map.insert("reputation", to_value(reputation).unwrap());
     map.insert("ticker_items", to_value(vec!["All purchases are final!",
                                              "Stocked items are available for synchronous sale!",
                                              "Please keep your hands and feet inside tht market at all times",
@@ -272,11 +461,13 @@ pub fn market_home(ledger: State<MutLedger>) -> Template {
 /// # Arguments
 /// 
 /// * `url`       - The url of the vendor
+/// * `token`     - A spectator token, needed with limited visibility
 /// * `ledger`    - The current ledger state
-#[get("/<url>")]
-pub fn vendor(url: String, ledger: State<MutLedger>) -> Template {
+#[get("/<url>?<token>")]
+pub fn vendor(url: String, token: Option<String>, ledger: MarketLedger) -> Template {
     let arc_ledger = ledger.inner().session_ledger.clone();
     let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) { return super::authorization::hidden_page(&format!("/vendors/{}", url)); }
     let vendors = ledger.get_vendors();
     let vend = vendors.iter().find(|x| x.url == url);
     match vend {
@@ -300,7 +491,7 @@ pub fn vendor(url: String, ledger: State<MutLedger>) -> Template {
 /// * `auth_item`   - The DTO for the stock order being completed
 /// * `ledger`      - The current ledger state
 #[post("/stock", data="<auth_item>")]
-pub fn http_stock(auth_item: Result<Form<AuthItem>, FormError<'_>>, ledger: State<super::ledger::MutLedger>) -> content::Json<String> {
+pub fn http_stock(auth_item: Result<Form<AuthItem>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
     match auth_item {
         Ok(ai) => super::util::construct_json(&stock(ai.into_inner(), ledger)),
         Err(_) => {
@@ -318,7 +509,7 @@ pub fn http_stock(auth_item: Result<Form<AuthItem>, FormError<'_>>, ledger: Stat
 /// * `auth_item`   - The DTO for the stock order being completed
 /// * `ledger`      - The current ledger state
 #[post("/stock", data="<auth_item>")]
-pub fn form_stock(auth_item: Result<Form<AuthItem>, FormError<'_>>, ledger: State<super::ledger::MutLedger>) -> Template {
+pub fn form_stock(auth_item: Result<Form<AuthItem>, FormError<'_>>, ledger: MarketLedger) -> Template {
     let mut map = super::HashMap::new();
     let mut response = match auth_item {
         Ok(ai) => stock(ai.into_inner(), ledger),
@@ -334,11 +525,17 @@ pub fn form_stock(auth_item: Result<Form<AuthItem>, FormError<'_>>, ledger: Stat
 }
 
 /// Stocking page GET endpoint
-#[get("/stock")]
-pub fn stock_page(ledger: State<super::ledger::MutLedger>) -> Template {
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/stock?<token>")]
+pub fn stock_page(token: Option<String>, ledger: MarketLedger) -> Template {
     let mut map = super::HashMap::new();
     let arc_ledger = ledger.inner().session_ledger.clone();
     let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) { return super::authorization::hidden_page("/stock"); }
     map.insert("names", to_value(ledger.get_vendor_names()).unwrap());
     map.insert("urls", to_value(ledger.get_vendor_urls()).unwrap());
     map.insert("ledger_state", to_value(ledger.serialize_state()).unwrap());
@@ -0,0 +1,194 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use rocket::response::content;
+use rocket::request::{Form, FormError};
+use super::markets::MarketLedger;
+use serde_json::to_string;
+use super::ledger::{Ledger, LedgerError};
+use super::util::{self, HasUuid};
+
+//Units of an item lent to a short seller, backed by bits held as collateral.
+//Loans from the market's lending pool have no lender
+#[derive(Clone, Debug, Serialize)]
+pub struct Loan {
+    pub id: usize,
+    pub lender: Option<String>,
+    pub borrower: String,
+    pub item: String,
+    pub count: u32,
+    pub collateral: f64,
+    pub fee: f64,
+    pub open: bool
+}
+
+//Standing offer from a vendor to lend stored units of an item for a fee rate
+#[derive(Clone, Debug, Serialize)]
+pub struct LendOffer {
+    pub lender: String,
+    pub item: String,
+    pub count: u32,
+    pub rate: f64
+}
+
+//Request to borrow units, an empty `from` borrows from the lending pool
+#[derive(Debug, FromForm)]
+pub struct BorrowOrder {
+    pub item: String,
+    pub count: u32,
+    pub from: String,
+    pub uuid: String
+}
+
+#[derive(Debug, FromForm)]
+pub struct AuthLendOffer {
+    pub item: String,
+    pub count: u32,
+    pub rate: f64,
+    pub uuid: String
+}
+
+#[derive(Debug, FromForm)]
+pub struct AuthLoan {
+    pub loan: usize,
+    pub uuid: String
+}
+
+/// Converts a ledger error from a loan operation into a readable message
+///
+/// # Arguments
+///
+/// * `err` - The error returned by the ledger
+fn loan_error(err: LedgerError) -> String {
+    match err {
+        LedgerError::InsufficientFunds => "cannot afford the collateral and fee".to_string(),
+        LedgerError::InsufficientStock => "not enough units available".to_string(),
+        LedgerError::InvalidLoan => "not found or already closed".to_string(),
+        LedgerError::NoReferencePrice => "has no reference price yet".to_string(),
+        _ => "could not be completed".to_string()
+    }
+}
+
+/// Borrows units of an item from a willing vendor or the lending pool. The
+/// borrower pays the fee to the lender and posts collateral up front, and the
+/// borrowed units are placed in the borrower's store
+///
+/// # Arguments
+///
+/// * `ledger`          - The current ledger state
+/// * `borrower_pos`    - The ID of the borrowing vendor
+/// * `order`           - The borrow order being made
+fn borrow(ledger: &mut Ledger, borrower_pos: usize, order: BorrowOrder) -> BTreeMap<String, Box<dyn Display>> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let lender_pos = match order.from.as_str() {
+        "" => None,
+        name => match ledger.find_vendor(name) {
+            Some(pos) if pos != borrower_pos => Some(pos),
+            _ => {
+                output_vars.insert("success".to_string(), Box::new(false));
+                output_vars.insert("lender".to_string(), Box::new("not found".to_string()));
+                return output_vars;
+            }
+        }
+    };
+    if !util::get_rust_types().contains(&order.item.as_str()) || order.count == 0 {
+        output_vars.insert("success".to_string(), Box::new(false));
+        output_vars.insert("item".to_string(), Box::new("invalid".to_string()));
+        return output_vars;
+    }
+
+    match ledger.borrow(borrower_pos, lender_pos, order.item, order.count) {
+        Ok(loan) => {
+            output_vars.insert("success".to_string(), Box::new(true));
+            output_vars.insert("loan".to_string(), Box::new(loan.id));
+            output_vars.insert("collateral".to_string(), Box::new(loan.collateral));
+            output_vars.insert("fee".to_string(), Box::new(loan.fee));
+        },
+        Err(e) => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("loan".to_string(), Box::new(loan_error(e)));
+        }
+    }
+    output_vars
+}
+
+impl HasUuid for BorrowOrder { fn uuid(&self) -> &str { &self.uuid } }
+impl HasUuid for AuthLendOffer { fn uuid(&self) -> &str { &self.uuid } }
+impl HasUuid for AuthLoan { fn uuid(&self) -> &str { &self.uuid } }
+
+/// Endpoint for borrowing items via HTTP request
+///
+/// # Arguments
+///
+/// * `order`   - The DTO for the borrow order being made
+/// * `ledger`  - The current ledger state
+#[post("/borrow", data="<order>")]
+pub fn http_borrow(order: Result<Form<BorrowOrder>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(order, ledger, borrow)
+}
+
+/// Endpoint for offering stored units up for lending via HTTP request. A count
+/// of zero withdraws the offer
+///
+/// # Arguments
+///
+/// * `offer`   - The DTO for the lending offer
+/// * `ledger`  - The current ledger state
+#[post("/lend", data="<offer>")]
+pub fn http_lend(offer: Result<Form<AuthLendOffer>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(offer, ledger, |ledger, vendor_id, offer| {
+        let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+        if offer.rate.is_finite() && offer.rate >= 0.0 {
+            ledger.set_lend_offer(vendor_id, offer.item, offer.count, offer.rate);
+            output_vars.insert("success".to_string(), Box::new(true));
+        } else {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("rate".to_string(), Box::new("must be a non-negative number".to_string()));
+        }
+        output_vars
+    })
+}
+
+/// Endpoint for returning borrowed units and reclaiming collateral via HTTP
+/// request. The borrower must hold the full number of borrowed units
+///
+/// # Arguments
+///
+/// * `auth_loan`   - The DTO naming the loan to close
+/// * `ledger`      - The current ledger state
+#[post("/return", data="<auth_loan>")]
+pub fn http_return(auth_loan: Result<Form<AuthLoan>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(auth_loan, ledger, |ledger, borrower_pos, auth_loan| {
+        let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+        match ledger.return_loan(borrower_pos, auth_loan.loan) {
+            Ok(loan) => {
+                output_vars.insert("success".to_string(), Box::new(true));
+                output_vars.insert("collateral".to_string(), Box::new(loan.collateral));
+            },
+            Err(e) => {
+                output_vars.insert("success".to_string(), Box::new(false));
+                output_vars.insert("loan".to_string(), Box::new(loan_error(e)));
+            }
+        }
+        output_vars
+    })
+}
+
+/// Endpoint to get all open loans and lending offers via http request
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/loans?<token>")]
+pub fn request_loans(token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) {
+        return util::construct_json(&super::authorization::reject_hidden());
+    }
+    let mut loans = BTreeMap::new();
+    loans.insert("loans", serde_json::to_value(ledger.get_open_loans()).unwrap());
+    loans.insert("offers", serde_json::to_value(ledger.get_lend_offers()).unwrap());
+    content::Json(to_string(&loans).unwrap())
+}
@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use rocket::response::content;
+use rocket::request::{Form, FormError};
+use super::markets::MarketLedger;
+use serde_json::to_string;
+use super::ledger::LedgerError;
+use super::limits::Call;
+use super::util::{self, HasUuid};
+
+//Vendors playing together. The treasury is the combined bits of the members,
+//any of whom may spend it
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Team {
+    pub members: Vec<String>,
+    pub bits: f64,
+    pub net_worth: f64
+}
+
+//Request to join a team, an empty team leaves the current one. Joining an
+//existing team takes its join token
+#[derive(Debug, FromForm)]
+pub struct AuthTeam {
+    pub team: String,
+    pub token: Option<String>,
+    pub uuid: String
+}
+
+#[derive(Debug, FromForm)]
+pub struct Transfer {
+    pub item: String,
+    pub count: u32,
+    pub to: String,
+    pub uuid: String
+}
+
+/// Converts a ledger error from a team operation into a readable message
+///
+/// # Arguments
+///
+/// * `err` - The error returned by the ledger
+pub fn team_error(err: LedgerError) -> String {
+    match err {
+        LedgerError::InvalidTeam => "is full or not shared".to_string(),
+        LedgerError::InvalidToken => "join token is missing or wrong".to_string(),
+        LedgerError::SessionClosed => "can no longer be changed, the session has ended".to_string(),
+        LedgerError::InsufficientStock => "not enough units available".to_string(),
+        _ => "could not be completed".to_string()
+    }
+}
+
+impl HasUuid for AuthTeam { fn uuid(&self) -> &str { &self.uuid } }
+impl HasUuid for Transfer { fn uuid(&self) -> &str { &self.uuid } }
+
+/// Endpoint for joining or leaving a team via http request
+///
+/// # Arguments
+///
+/// * `auth_team`   - The DTO naming the team to join
+/// * `ledger`      - The current ledger state
+#[post("/team/join", data="<auth_team>")]
+pub fn http_join(auth_team: Result<Form<AuthTeam>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_call(auth_team, ledger, Call::Other, |_, _| Ok(()), |ledger, vendor_id, auth_team| {
+        let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+        let team = if auth_team.team.is_empty() { None } else { Some(auth_team.team) };
+        match ledger.join_team(vendor_id, team, auth_team.token) {
+            Ok(token) => {
+                output_vars.insert("success".to_string(), Box::new(true));
+                if let Some(token) = token { output_vars.insert("token".to_string(), Box::new(token)); }
+            },
+            Err(e) => {
+                output_vars.insert("success".to_string(), Box::new(false));
+                output_vars.insert("team".to_string(), Box::new(team_error(e)));
+            }
+        }
+        util::construct_json(&output_vars)
+    })
+}
+
+/// Endpoint for giving units of an item to a teammate via http request
+///
+/// # Arguments
+///
+/// * `transfer`    - The DTO for the transfer
+/// * `ledger`      - The current ledger state
+#[post("/team/transfer", data="<transfer>")]
+pub fn http_transfer(transfer: Result<Form<Transfer>, FormError<'_>>, ledger: MarketLedger) -> content::Json<String> {
+    util::vendor_action(transfer, ledger, |ledger, vendor_id, transfer| {
+        let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+        let to = match ledger.find_vendor(&transfer.to) {
+            Some(pos) => pos,
+            None => {
+                output_vars.insert("success".to_string(), Box::new(false));
+                output_vars.insert("to".to_string(), Box::new("not found".to_string()));
+                return output_vars;
+            }
+        };
+        match ledger.transfer_item(vendor_id, to, transfer.item, transfer.count) {
+            Ok(count) => {
+                output_vars.insert("success".to_string(), Box::new(true));
+                output_vars.insert("count".to_string(), Box::new(count));
+            },
+            Err(e) => {
+                output_vars.insert("success".to_string(), Box::new(false));
+                output_vars.insert("transfer".to_string(), Box::new(team_error(e)));
+            }
+        }
+        output_vars
+    })
+}
+
+/// Endpoint to get every team with its members, treasury and combined net
+/// worth via http request
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/teams?<token>")]
+pub fn request_teams(token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    if !ledger.reveals_all(token.as_deref()) {
+        return util::construct_json(&super::authorization::reject_hidden());
+    }
+    content::Json(to_string(&ledger.get_teams()).unwrap())
+}
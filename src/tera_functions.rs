@@ -9,7 +9,7 @@ pub fn make_catchphrase_generator() -> GlobalFn {
     })
 }
 
-/// Tera function to find the index of a type in the static constant list
+/// Tera function to find the index of an item in the item catalog
 /// 
 /// # Arguments
 /// 
@@ -1,15 +1,19 @@
-use rocket::http::{ContentType, Status};
+use rocket::http::{ContentType, Header, Status};
 use rocket::local::Client;
 use std::collections::HashSet;
 use std::iter::FromIterator;
 use super::*;
 use super::shop::{Item, Vendor};
 
+fn admin_header() -> Header<'static> {
+    Header::new("X-Admin-Secret", "test-secret")
+}
+
 fn create_test_ledger(generate: usize) -> (ledger::MutLedger, Vec<String>) {
     let mut session_ledger = ledger::Ledger::new();
     let mut ids = vec![];
     for _ in 0..generate {
-        ids.push(session_ledger.register_vendor(util::name_generator(), None).unwrap());
+        ids.push(session_ledger.register_vendor(session_ledger.random_name(), None).unwrap());
     }
     (ledger::MutLedger{session_ledger: Arc::new(RwLock::new(session_ledger))}, ids)
 }
@@ -33,6 +37,844 @@ fn test_index_endpoint() {
     assert!(response.body_string().unwrap().contains("The Code Market"));
 }
 
+#[test]
+fn test_circuit_breaker() {
+    assert!(breakers::within_band(5.0, 1.0, 10.0));
+    assert!(!breakers::within_band(11.0, 1.0, 10.0));
+    assert!(!breakers::within_band(0.05, 1.0, 10.0));
+    assert!(breakers::within_band(1e9, 0.0, 10.0));
+
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("seller".to_string(), None);
+    let _ = ledger.register_vendor("buyer".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 1.0, 10);
+    let order = || purchase::Order{ item: item.clone(), count: 1, from: "seller".to_string(), to: "".to_string(), delivery: None };
+    ledger.purchase(order(), 0, 1);
+    assert!(!ledger.is_halted(&item));
+    assert!(ledger.check_price(&item, 5.0).is_ok());
+    assert!(matches!(ledger.check_price(&item, 500.0), Err(ledger::LedgerError::OutsidePriceBand)));
+
+    ledger.update_item(0, item.clone(), 5.0, 0);
+    ledger.purchase(order(), 0, 1);
+    assert!(ledger.is_halted(&item));
+    assert!(matches!(ledger.check_price(&item, 5.0), Err(ledger::LedgerError::TradingHalted)));
+    assert_eq!(ledger.get_active_halts()[0].until, ledger.get_version() + 20);
+}
+
+#[test]
+fn test_equity() {
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("issuer".to_string(), None);
+    let _ = ledger.register_vendor("investor".to_string(), None);
+    let _ = ledger.register_vendor("trader".to_string(), None);
+    assert!(ledger.issue_shares(0, 100, 2.0).is_ok());
+    assert_eq!(ledger.get_share_offers()[0].count, 100);
+
+    assert_eq!(ledger.buy_shares(1, "issuer".to_string(), "issuer".to_string(), 40).unwrap(), 80.0);
+    assert!(ledger.buy_shares(1, "issuer".to_string(), "issuer".to_string(), 61).is_err());
+    assert_eq!(ledger.get_vendor(0).bits, 1080.0);
+    let worth = ledger.net_worth(1);
+
+    assert!(ledger.offer_shares(1, "issuer".to_string(), 50, 3.0).is_err());
+    assert!(ledger.offer_shares(1, "issuer".to_string(), 10, 3.0).is_ok());
+    assert!(ledger.buy_shares(2, "issuer".to_string(), "investor".to_string(), 10).is_ok());
+    assert_eq!(ledger.get_share_prices()["issuer"], 3.0);
+    assert_eq!(ledger.net_worth(1), worth + 30.0 + 30.0 * 3.0 - 40.0 * 2.0);
+
+    assert_eq!(ledger.pay_dividend(0, 0.5).unwrap(), 20.0);
+    assert_eq!(ledger.get_vendor(0).bits, 1060.0);
+    assert_eq!(ledger.get_vendor(1).bits, 1000.0 - 80.0 + 30.0 + 15.0);
+    assert_eq!(ledger.get_vendor(2).bits, 1000.0 - 30.0 + 5.0);
+}
+
+#[test]
+fn test_escrow_delivery() {
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("seller".to_string(), None);
+    let _ = ledger.register_vendor("buyer".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 2.0, 10);
+    let order = purchase::Order{ item: item.clone(), count: 3, from: "seller".to_string(), to: "".to_string(), delivery: Some("escrow".to_string()) };
+    let (_, escrow) = ledger.purchase(order, 0, 1);
+    let escrow = escrow.expect("purchase escrowed");
+    let held = ledger.get_vendor_escrow(1)[0].clone();
+    assert_eq!(held.count, 3);
+    assert_eq!(ledger.get_vendor(1).get_item(&item).map_or(0, |i| i.get_count()), 0);
+    assert_eq!(ledger.get_vendor(0).bits, 1000.0);
+    assert!(ledger.cancel_escrow(1, escrow).is_err());
+
+    while ledger.get_version() < held.release.unwrap() {
+        let order = purchase::Order{ item: item.clone(), count: 1, from: "seller".to_string(), to: "".to_string(), delivery: None };
+        ledger.purchase(order, 0, 1);
+    }
+    let bought = (ledger.get_vendor(1).get_item(&item).unwrap().get_count() - 3) as f64;
+    assert_eq!(ledger.get_vendor(0).bits, 1006.0 + 2.0 * bought);
+    assert_eq!(ledger.get_vendor(1).bits, 994.0 - 2.0 * bought);
+}
+
+#[test]
+fn test_escrow_shipment() {
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("seller".to_string(), None);
+    let _ = ledger.register_vendor("buyer".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 2.0, 10);
+    let shipment = || purchase::Order{ item: item.clone(), count: 2, from: "seller".to_string(), to: "".to_string(), delivery: Some("shipment".to_string()) };
+    let first = ledger.purchase(shipment(), 0, 1).1.unwrap();
+    let second = ledger.purchase(shipment(), 0, 1).1.unwrap();
+
+    assert!(ledger.ship_escrow(1, first).is_err());
+    assert!(ledger.ship_escrow(0, first).is_ok());
+    assert!(ledger.cancel_escrow(1, first).is_err());
+    assert!(ledger.cancel_escrow(1, second).is_ok());
+    assert_eq!(ledger.get_vendor(0).get_item(&item).unwrap().get_count(), 8);
+    assert_eq!(ledger.get_vendor(1).get_item(&item).unwrap().get_count(), 2);
+    assert_eq!(ledger.get_vendor(1).bits, 996.0);
+}
+
+#[test]
+fn test_supply_contract() {
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("seller".to_string(), None);
+    let _ = ledger.register_vendor("buyer".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 2.0, 10);
+    let held = ledger.get_vendor(1).get_item(&item).map_or(0, |i| i.get_count());
+    let proposal = |from: &str| contracts::ContractProposal{ item: item.clone(), count: 4, price: 3.0, every: 1, interval: "versions".to_string(), deliveries: 3, penalty: 5.0, from: from.to_string(), uuid: "".to_string() };
+
+    assert!(ledger.propose_contract(1, proposal("buyer")).is_err());
+    let withdrawn = ledger.propose_contract(1, proposal("seller")).unwrap();
+    assert!(ledger.cancel_contract(0, withdrawn).is_ok());
+    assert!(ledger.accept_contract(0, withdrawn).is_err());
+    let id = ledger.propose_contract(1, proposal("seller")).unwrap();
+    assert!(ledger.accept_contract(1, id).is_err());
+    assert!(ledger.accept_contract(0, id).is_ok());
+
+    while ledger.get_vendor_contracts(1)[id].state == contracts::ContractState::Active {
+        let order = purchase::Order{ item: item.clone(), count: 0, from: "seller".to_string(), to: "".to_string(), delivery: None };
+        ledger.purchase(order, 0, 1);
+    }
+    let contract = ledger.get_vendor_contracts(0)[id].clone();
+    assert_eq!(contract.delivered, 10);
+    assert_eq!(contract.defaulted, 2);
+    assert_eq!(ledger.get_vendor(0).get_item(&item).unwrap().get_count(), 0);
+    assert_eq!(ledger.get_vendor(1).get_item(&item).unwrap().get_count(), held + 10);
+    assert_eq!(ledger.get_vendor(0).bits, 1020.0);
+    assert_eq!(ledger.get_vendor(1).bits, 980.0);
+}
+
+#[test]
+fn test_reputation() {
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("seller".to_string(), None);
+    let _ = ledger.register_vendor("buyer".to_string(), None);
+    assert_eq!(ledger.get_reputation()[0].score(), 85.0);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 2.0, 10);
+    ledger.update_item(0, item.clone(), 3.0, 0);
+    let order = |count| purchase::Order{ item: item.clone(), count, from: "seller".to_string(), to: "".to_string(), delivery: None };
+    ledger.purchase(order(3), 0, 1);
+    ledger.purchase(order(20), 0, 1);
+
+    let reputation = ledger.get_reputation();
+    assert_eq!(reputation[0].fulfilled, 1);
+    assert_eq!(reputation[0].understocked, 1);
+    assert!(reputation[0].repricings >= 1);
+    assert_eq!(reputation[0].volume, 30.0);
+    assert_eq!(reputation[1].volume, 30.0);
+    assert!(reputation[0].score() < reputation[1].score());
+    assert_eq!(ledger.credit_limit(1), 0.0);
+}
+
+#[test]
+fn test_catalog_from_config() {
+    let mut options = Config::default();
+    options.merge(File::from_str("[[catalog]]\nname = \"Vec\"\ncategory = \"collection\"\nmin_price = 2.0\nmax_price = 4.0\ncount = 10\n\n[[catalog]]\nname = \"Box\"", FileFormat::Toml)).unwrap();
+    let catalog = options.get::<Vec<util::CatalogItem>>("catalog").expect("catalog parsed");
+    assert_eq!(catalog.len(), 2);
+    assert_eq!(catalog[0].initial_count(50), 10);
+    assert_eq!(catalog[1].initial_count(50), 50);
+    let mut rng = util::seeded_rng(None);
+    let price = catalog[0].initial_price(&mut rng);
+    assert!((2.0..4.0).contains(&price));
+    assert_eq!(catalog[1].category, "");
+    assert_eq!(catalog[1].initial_price(&mut rng), 0.0);
+
+    let types = util::get_rust_types();
+    assert_eq!(util::get_catalog(0, &mut rng).len(), types.len());
+    assert_eq!(util::get_rust_type_index(types[3].to_string()), 3);
+    assert_eq!(util::convert_minimal_to_full(HashMap::new()), vec![0.0; types.len()]);
+    assert!(!util::keeps_catalog(&options));
+    assert!(util::keeps_catalog(&Config::default()));
+}
+
+#[test]
+fn test_price_index() {
+    let history = vec![vec![0.0, 2.0, 4.0, f64::NAN], vec![0.0, 0.0, 5.0, 10.0]];
+    assert_eq!(indices::price_index(&history, &[0]), vec![100.0, 100.0, 200.0, 200.0]);
+    assert_eq!(indices::price_index(&history, &[0, 1]), vec![100.0, 100.0, 150.0, 200.0]);
+
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("test".to_string(), None);
+    let price_indices = ledger.get_price_indices();
+    assert!(price_indices.contains_key("market"));
+    for category in util::get_categories().keys() {
+        assert_eq!(price_indices[category].len(), price_indices["market"].len());
+    }
+}
+
+#[test]
+fn test_session_lifecycle() {
+    let mut limits = session::Session::from_config();
+    limits.phase = session::Phase::Running;
+    limits.versions = Some(10);
+    limits.started_version = Some(5);
+    assert!(!limits.is_over(14, 0));
+    assert!(limits.is_over(15, 0));
+
+    let mut ledger = ledger::Ledger::new();
+    let id1 = ledger.register_vendor("test".to_string(), None).unwrap();
+    let v1_items = ledger.get_ledger_items();
+    let _ = ledger.register_vendor("test2".to_string(), None);
+    assert!(ledger.trading_open());
+    ledger.end_session();
+    assert!(!ledger.trading_open());
+    assert!(ledger.register_vendor("late".to_string(), None).is_err());
+
+    let standings = ledger.get_standings();
+    assert_eq!(standings.len(), 2);
+    assert_eq!(standings[0].rank, 1);
+    assert!(standings[0].net_worth >= standings[1].net_worth);
+
+    let rocket = rocket::ignite()
+                        .manage(ledger::MutLedger{ session_ledger: Arc::new(RwLock::new(ledger)) })
+                        .mount("/", routes![shop::http_stock, session::request_standings]);
+    let client = Client::new(rocket).expect("valid rocket instance");
+    let mut stock_response = client.post("/stock")
+                                   .body(format!("name={}&price=1&stock=5&uuid={}", v1_items[0], id1))
+                                   .header(ContentType::Form)
+                                   .dispatch();
+    assert!(stock_response.body_string().unwrap().contains("\"session\": \"trading is closed\""));
+    let mut standings_response = client.get("/standings").dispatch();
+    assert!(standings_response.body_string().unwrap().contains("\"rank\":1"));
+
+    let mut options = Config::default();
+    let _ = options.set("session_vendors", 1);
+    let _ = options.set("session_versions", 1);
+    let mut ledger = ledger::Ledger::with_config(options);
+    let _ = ledger.register_vendor("test".to_string(), None);
+    let _ = ledger.adjust_bits(0, 1.0);
+    assert!(ledger.session_due());
+    let rocket = rocket::ignite()
+                        .manage(ledger::MutLedger{ session_ledger: Arc::new(RwLock::new(ledger)) })
+                        .mount("/", routes![session::request_session]);
+    let client = Client::new(rocket).expect("valid rocket instance");
+    let mut session_response = client.get("/session").dispatch();
+    assert!(session_response.body_string().unwrap().contains("\"phase\":\"ended\""));
+}
+
+#[test]
+fn test_leaderboard() {
+    assert_eq!(leaderboard::ranks(&[1.0, 3.0, 2.0]), vec![3, 1, 2]);
+
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("seller".to_string(), None);
+    let _ = ledger.register_vendor("buyer".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 2.0, 10);
+    let order = purchase::Order{ item: item.clone(), count: 5, from: "seller".to_string(), to: "".to_string(), delivery: None };
+    ledger.purchase(order, 0, 1);
+
+    let rows = ledger.get_leaderboard();
+    assert_eq!(rows[0].bits_rank, 1);
+    assert_eq!(rows[1].bits_rank, 2);
+    assert_eq!(rows[0].volume, rows[1].volume);
+    let history = ledger.get_rank_history();
+    assert!(!history.is_empty());
+    assert_eq!(history.last().unwrap().ranking.len(), 2);
+}
+
+#[test]
+fn test_markets() {
+    let (main, _) = create_test_ledger(1);
+    let markets = markets::Markets::new(main.session_ledger.clone());
+    let rocket = rocket::ignite()
+                        .mount("/", routes![markets::http_create, markets::http_delete, markets::request_markets])
+                        .mount("/api", routes![ledger::request_vendor_names])
+                        .manage(main)
+                        .manage(admin::AdminSecret("test-secret".to_string()))
+                                            .manage(markets)
+                        .attach(markets::MarketRouter);
+    let client = Client::new(rocket).expect("valid rocket instance");
+    let response = client.post("/markets")
+                        .header(ContentType::Form)
+                        .body("id=third")
+                        .dispatch();
+    assert_eq!(response.status(), Status::Unauthorized);
+    let response = client.post("/markets")
+                        .header(ContentType::Form)
+                        .header(admin_header())
+                        .body("id=second&config=generated_vendors%20%3D%202")
+                        .dispatch();
+    assert_eq!(response.status(), Status::Ok);
+
+    let mut response = client.get("/markets/second/api/vendor_names").dispatch();
+    let names: Vec<String> = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(names.len(), 2);
+    let mut response = client.get("/api/vendor_names").dispatch();
+    let names: Vec<String> = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(names.len(), 1);
+    assert_eq!(client.get("/markets/missing/api/vendor_names").dispatch().status(), Status::NotFound);
+
+    let mut response = client.get("/markets").dispatch();
+    assert!(response.body_string().unwrap().contains("\"second\""));
+    client.delete("/markets/second").header(admin_header()).dispatch();
+    client.delete("/markets/main").header(admin_header()).dispatch();
+    let mut response = client.get("/markets").dispatch();
+    let listing = response.body_string().unwrap();
+    assert!(!listing.contains("\"second\""));
+    assert!(listing.contains("\"main\""));
+}
+
+#[test]
+fn test_scenario() {
+    let (ledger, ids) = scenario::build(scenario::read_file("scenarios/oldies.toml").unwrap()).unwrap();
+    assert_eq!(ids.len(), 4);
+    assert_eq!(ledger.get_vendor_urls()[..2], ["oldies".to_string(), "icees".to_string()]);
+    let oldies = ledger.get_vendor(0);
+    assert_eq!(oldies.bits, 1200.0);
+    let u8_item = oldies.get_item(&"u8".to_string()).unwrap();
+    assert_eq!((u8_item.price, u8_item.get_count(), u8_item.get_stored()), (1.5, 20, 30));
+    assert_eq!(ledger.get_vendor(2).bits, 1000.0);
+    assert_eq!(ledger.get_vendor(3).url, "bool_barn");
+    let mut tiered = Config::default();
+    tiered.merge(File::from_str("[[vendors]]\nname = \"Bulk\"\nitems = [{ name = \"u8\", price = 2.0, stocked = 1, stored = 2, tiers = [{ min = 10, price = 1.0 }, { min = 5, price = 0.5 }] }]", FileFormat::Toml)).unwrap();
+    assert!(scenario::build(tiered).is_err());
+
+    let (main, _) = create_test_ledger(1);
+    let markets = markets::Markets::new(main.session_ledger.clone());
+    let rocket = rocket::ignite()
+                        .mount("/", routes![scenario::http_load])
+                        .mount("/api", routes![ledger::request_vendor_names])
+                        .manage(main)
+                        .manage(admin::AdminSecret("test-secret".to_string()))
+                        .manage(markets)
+                        .attach(markets::MarketRouter);
+    let client = Client::new(rocket).expect("valid rocket instance");
+    let mut response = client.post("/scenario")
+                        .header(ContentType::Form)
+                        .header(admin_header())
+                        .body("scenario=%5B%5Bvendors%5D%5D%0Aname%20%3D%20%22Solo%22%0Aitems%20%3D%20%5B%7B%20name%20%3D%20%22u8%22%2C%20price%20%3D%202.0%2C%20stocked%20%3D%201%2C%20stored%20%3D%202%20%7D%5D")
+                        .dispatch();
+    let loaded: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(loaded["success"], "true");
+    let uuid = loaded["vendors"]["Solo"].as_str().unwrap().to_string();
+    assert!(client.rocket().state::<ledger::MutLedger>().unwrap().session_ledger.read().unwrap().verify_uuid(uuid).is_ok());
+    let mut response = client.get("/api/vendor_names").dispatch();
+    assert_eq!(response.body_string().unwrap(), "[\"Solo\"]");
+
+    let mut response = client.post("/scenario")
+                        .header(ContentType::Form)
+                        .header(admin_header())
+                        .body("market=other&scenario=%5B%5Bvendors%5D%5D%0Aname%20%3D%20%22Bad%22%0Aitems%20%3D%20%5B%7B%20name%20%3D%20%22Vec%22%2C%20price%20%3D%202.0%2C%20stocked%20%3D%201%2C%20stored%20%3D%202%20%7D%5D")
+                        .dispatch();
+    assert!(response.body_string().unwrap().contains("\"success\": \"false\""));
+    assert_eq!(client.get("/markets/other/api/vendor_names").dispatch().status(), Status::NotFound);
+}
+
+#[test]
+fn test_objectives() {
+    let mut options = Config::default();
+    options.merge(File::from_str("[[objectives]]\npoints = 5\ngoal = { kind = \"hold\", item = \"u8\", count = 8 }\n\n[[objectives]]\nvendor = \"b\"\ngoal = { kind = \"corner\", item = \"u16\" }\n\n[[objectives]]\ngoal = { kind = \"trades\", count = 1 }", FileFormat::Toml)).unwrap();
+    let mut ledger = ledger::Ledger::with_config(options);
+    ledger.register_vendor_with("a".to_string(), None, 100.0, vec![Item::new("u8".to_string(), 1.0, 0, 10)]).unwrap();
+    ledger.register_vendor_with("b".to_string(), None, 100.0, vec![Item::new("u16".to_string(), 1.0, 10, 0)]).unwrap();
+    assert_eq!(ledger.get_objectives().len(), 5);
+    let points: Vec<f64> = ledger.get_standings().iter().map(|s| s.points).collect();
+    assert_eq!(points, vec![5.0, 1.0]);
+
+    let order = purchase::Order{ item: "u16".to_string(), count: 4, from: "b".to_string(), to: "".to_string(), delivery: None };
+    ledger.purchase(order, 1, 0);
+    ledger.end_session();
+    let ledger_objectives = ledger.get_objectives();
+    let complete: Vec<(&str, &str)> = ledger_objectives.iter().filter(|p| p.complete).map(|p| (p.vendor.as_str(), p.objective.as_str())).collect();
+    assert_eq!(complete, vec![("a", "Hold 8 u8 at the end"), ("b", "Corner the u16 market, holding 50% of all units"), ("a", "Complete 1 trades"), ("b", "Complete 1 trades")]);
+    let standings = ledger.get_standings();
+    assert_eq!((standings[0].vendor.as_str(), standings[0].points), ("a", 6.0));
+    assert_eq!(standings[1].points, 2.0);
+}
+
+#[test]
+fn test_turn_resolution() {
+    let mut options = Config::default();
+    options.merge(File::from_str("turn_seconds = 1", FileFormat::Toml)).unwrap();
+    let mut ledger = ledger::Ledger::with_config(options);
+    let seller = ledger.register_vendor("seller".to_string(), None).unwrap();
+    let buyer = ledger.register_vendor("buyer".to_string(), None).unwrap();
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    let stocked = ledger.get_vendor(0).get_item(&item).unwrap().get_count();
+    assert!(ledger.turn_based());
+    let ledger = ledger::MutLedger{ session_ledger: Arc::new(RwLock::new(ledger)) };
+
+    let order = purchase::Order{ item: item.clone(), count: 2, from: "seller".to_string(), to: buyer.clone(), delivery: None };
+    let auth_item = shop::AuthItem{ name: item.clone(), price: 2.0, stock: 10, uuid: seller.clone(), tiers: None };
+    turns::submit(turns::Action::Purchase(order), &buyer, &ledger);
+    turns::submit(turns::Action::Stock(auth_item), &seller, &ledger);
+    turns::resolve_due(&ledger);
+    {
+        let reader = ledger.session_ledger.read().unwrap();
+        assert_eq!(reader.get_turn().queue.len(), 2);
+        assert_eq!(reader.get_vendor(0).get_item(&item).unwrap().get_count(), stocked);
+    }
+
+    std::thread::sleep(std::time::Duration::from_millis(1100));
+    turns::resolve_due(&ledger);
+    let reader = ledger.session_ledger.read().unwrap();
+    let turn = reader.get_turn();
+    assert_eq!(turn.number, 2);
+    assert_eq!(turn.resolved, Some(1));
+    assert_eq!(turn.results[0].action, "stock");
+    assert_eq!(turn.results[1].result["success"], "true");
+    assert_eq!(reader.get_vendor(0).get_item(&item).unwrap().get_count(), stocked + 8);
+    assert_eq!(reader.get_vendor(0).get_item(&item).unwrap().price, 2.0);
+}
+
+#[test]
+fn test_seeded_market() {
+    let seeded = || {
+        let mut options = Config::default();
+        options.merge(File::from_str("seed = 7\ngenerated_vendors = 3", FileFormat::Toml)).unwrap();
+        let mut ledger = ledger::Ledger::with_config(options);
+        ledger.generate_vendors();
+        ledger
+    };
+    let (first, second) = (seeded(), seeded());
+    assert_eq!(first.get_vendor_names(), second.get_vendor_names());
+    assert_eq!(first.serialize_state(), second.serialize_state());
+    let items = first.get_ledger_items();
+    assert_eq!(format!("{:?}", events::Shock::random(&items, &mut util::seeded_rng(Some(3)))),
+               format!("{:?}", events::Shock::random(&items, &mut util::seeded_rng(Some(3)))));
+}
+
+#[test]
+fn test_tournament_pairings() {
+    let entrants: Vec<String> = vec!["a", "b", "c"].into_iter().map(String::from).collect();
+    let rounds = tournament::round_robin(&entrants);
+    assert_eq!(rounds.len(), 3);
+    let mut games: Vec<(String, String)> = rounds.iter().flatten().filter_map(|(a, b)| b.clone().map(|b| (a.clone(), b))).collect();
+    assert_eq!(games.len(), 3);
+    games.iter_mut().for_each(|g| if g.0 > g.1 { *g = (g.1.clone(), g.0.clone()) });
+    games.sort();
+    games.dedup();
+    assert_eq!(games.len(), 3);
+
+    let points: BTreeMap<String, f64> = vec![("a".to_string(), 1.0), ("c".to_string(), 1.0)].into_iter().collect();
+    let played = vec![("a".to_string(), "c".to_string())].into_iter().collect();
+    let pairs = tournament::swiss(&entrants, &points, &BTreeMap::new(), &played);
+    assert_eq!(pairs[0], ("a".to_string(), Some("b".to_string())));
+    assert_eq!(pairs[1], ("c".to_string(), None));
+
+    let (a, b) = tournament::elo(1500.0, 1500.0, 1.0, 32.0);
+    assert_eq!((a, b), (1516.0, 1484.0));
+}
+
+#[test]
+fn test_tournament() {
+    let (main, _) = create_test_ledger(0);
+    let markets = markets::Markets::new(main.session_ledger.clone());
+    let mut ratings = tournament::Ratings::default();
+    let entrants = vec!["alpha".to_string(), "beta".to_string()];
+    let mut cup = tournament::Tournament::new("cup".to_string(), entrants, tournament::Format::RoundRobin, None, None, &markets, &ratings);
+    assert_eq!(cup.rounds, 1);
+    assert_eq!(cup.matches[0].market, "cup-r1-m1");
+
+    let arc_ledger = markets.get("cup-r1-m1").expect("match market created");
+    {
+        let mut ledger = arc_ledger.write().unwrap();
+        let token = cup.get_tokens()["alpha"].clone();
+        assert!(ledger.check_entrant("alpha", Some(&token)).is_ok());
+        assert!(matches!(ledger.check_entrant("alpha", None), Err(ledger::LedgerError::InvalidToken)));
+        assert!(matches!(ledger.check_entrant("gamma", Some(&token)), Err(ledger::LedgerError::InvalidVendor)));
+        assert!(markets.get(markets::MAIN_MARKET).unwrap().read().unwrap().check_entrant("gamma", None).is_ok());
+        let _ = ledger.register_vendor("alpha".to_string(), None);
+        let _ = ledger.register_vendor("beta".to_string(), None);
+        let item = ledger.get_vendor(0).get_items()[0].name.clone();
+        ledger.update_item(0, item.clone(), 5.0, 10);
+        let order = purchase::Order{ item, count: 5, from: "alpha".to_string(), to: "".to_string(), delivery: None };
+        ledger.purchase(order, 0, 1);
+    }
+    assert!(!cup.update(&markets, &mut ratings));
+    arc_ledger.write().unwrap().end_session();
+    assert!(cup.update(&markets, &mut ratings));
+    assert!(cup.finished);
+    assert_eq!(cup.points["alpha"] + cup.points["beta"], 1.0);
+    assert_eq!(ratings.get("alpha") + ratings.get("beta"), 3000.0);
+
+    let entrants = vec!["alpha".to_string(), "beta".to_string()];
+    let config = Some("tournament_lobby_seconds = 0".to_string());
+    let mut lobby = tournament::Tournament::new("lobby".to_string(), entrants, tournament::Format::RoundRobin, None, config, &markets, &ratings);
+    let _ = markets.get("lobby-r1-m1").unwrap().write().unwrap().register_vendor("alpha".to_string(), None);
+    assert!(lobby.update(&markets, &mut ratings));
+    assert!(lobby.finished);
+    assert_eq!(lobby.matches[0].forfeited, vec!["beta".to_string()]);
+    assert_eq!(lobby.points["alpha"], 1.0);
+    assert_eq!(ratings.get("alpha") + ratings.get("beta"), 3000.0);
+
+    let tournaments = tournament::Tournaments::from_config();
+    tournaments.replace(cup, &markets);
+    tournaments.replace(lobby, &markets);
+    assert!(markets.get("cup-r1-m1").is_none());
+    assert!(markets.get("lobby-r1-m1").is_some());
+}
+
+#[test]
+fn test_replay() {
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("seller".to_string(), None);
+    let _ = ledger.register_vendor("buyer".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 2.0, 10);
+    let before = ledger.get_version();
+    let units = ledger.get_vendor(0).get_item(&item).unwrap().total() as i64;
+    let bits = ledger.get_vendor(0).bits;
+    let order = purchase::Order{ item: item.clone(), count: 5, from: "seller".to_string(), to: "".to_string(), delivery: None };
+    ledger.purchase(order, 0, 1);
+
+    let past = ledger.replay(before);
+    assert_eq!(past.len(), 2);
+    assert_eq!(past[0].bits, bits);
+    assert_eq!(ledger.get_vendor(0).bits, bits + 10.0);
+    assert_eq!(past[0].items[&item], units);
+    let held = past[1].items.get(&item).cloned().unwrap_or(0);
+    let now = ledger.replay(ledger.get_version());
+    assert_eq!(now[0].items[&item], units - 5);
+    assert_eq!(now[1].items[&item], held + 5);
+    assert!(ledger.replay(0).is_empty());
+    assert!(ledger.get_price_history_at(before)[0].len() < ledger.get_price_history()[0].len());
+
+    let _ = ledger.register_vendor_with("empty".to_string(), None, 5.0, vec![]);
+    let now = ledger.replay(ledger.get_version());
+    assert_eq!(now.len(), 3);
+    assert!(now[2].items.is_empty());
+    assert_eq!(ledger.replay(before).len(), 2);
+
+    let mut options = Config::default();
+    options.merge(File::from_str("call_costs = { purchase = 1.0 }", FileFormat::Toml)).unwrap();
+    let mut ledger = ledger::Ledger::with_config(options);
+    let seller = ledger.register_vendor("seller".to_string(), None).unwrap();
+    let _ = ledger.register_vendor("buyer".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 2.0, 10);
+    let bits = ledger.get_vendor(0).bits;
+    let mut versions = vec![ledger.get_version()];
+    for _ in 0..2 {
+        let _ = ledger.charge_call(&seller, limits::Call::Purchase);
+        let order = purchase::Order{ item: item.clone(), count: 2, from: "seller".to_string(), to: "".to_string(), delivery: None };
+        ledger.purchase(order, 0, 1);
+        versions.push(ledger.get_version());
+    }
+    let _ = ledger.charge_call(&seller, limits::Call::Purchase);
+    assert_eq!(ledger.get_vendor(0).bits, bits + 5.0);
+    assert_eq!(ledger.replay(versions[0])[0].bits, bits);
+    assert_eq!(ledger.replay(versions[1])[0].bits, bits + 3.0);
+    assert_eq!(ledger.replay(versions[2])[0].bits, bits + 6.0);
+}
+
+#[test]
+fn test_teams() {
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("seller".to_string(), None);
+    let _ = ledger.register_vendor("buyer".to_string(), None);
+    let _ = ledger.register_vendor("partner".to_string(), None);
+    assert!(ledger.trading_open());
+    let token = ledger.join_team(1, Some("red".to_string()), None).expect("team joined");
+    assert!(token.is_some());
+    assert!(matches!(ledger.check_team(3, Some(&"red".to_string()), None), Err(ledger::LedgerError::InvalidToken)));
+    assert!(ledger.join_team(2, Some("red".to_string()), None).is_err());
+    assert!(ledger.join_team(2, Some("red".to_string()), Some("wrong".to_string())).is_err());
+    assert_eq!(ledger.join_team(2, Some("red".to_string()), token.clone()).ok(), Some(token));
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    let bits = ledger.get_vendor(1).bits;
+    assert_eq!(ledger.team_bits(1), bits * 2.0);
+
+    ledger.update_item(0, item.clone(), bits / 10.0, 15);
+    let order = purchase::Order{ item: item.clone(), count: 15, from: "seller".to_string(), to: "".to_string(), delivery: None };
+    ledger.purchase(order, 0, 1);
+    assert_eq!(ledger.get_vendor(1).bits, 0.0);
+    assert_eq!(ledger.get_vendor(2).bits, bits / 2.0);
+    assert_eq!(ledger.get_teams()["red"].bits, bits / 2.0);
+
+    let stored = ledger.get_vendor(2).get_item(&item).map_or(0, |i| i.get_stored());
+    assert_eq!(ledger.transfer_item(1, 2, item.clone(), 5).ok(), Some(5));
+    assert_eq!(ledger.get_vendor(2).get_item(&item).unwrap().get_stored(), stored + 5);
+    assert!(ledger.transfer_item(1, 0, item.clone(), 5).is_err());
+
+    let standings = ledger.get_standings();
+    let red: Vec<usize> = standings.iter().filter(|s| s.team.is_some()).map(|s| s.rank).collect();
+    assert_eq!(red.len(), 2);
+    assert_eq!(red[0], red[1]);
+
+    ledger.end_session();
+    assert!(matches!(ledger.join_team(0, Some("blue".to_string()), None), Err(ledger::LedgerError::SessionClosed)));
+}
+
+#[test]
+fn test_admin() {
+    let (ledger, ids) = create_test_ledger(2);
+    let (name, item) = {
+        let reader = ledger.session_ledger.read().unwrap();
+        (reader.get_vendor(0).name, reader.get_vendor(0).get_items()[0].name.clone())
+    };
+    let held = ledger.session_ledger.read().unwrap().get_vendor(0).get_item(&item).unwrap().total();
+    let rocket = rocket::ignite()
+                        .mount("/", routes![admin::http_adjust, admin::http_freeze, admin::http_inject, admin::http_kick, admin::http_pause, admin::http_reset, admin::http_resume])
+                        .mount("/api", routes![ledger::request_entries, shop::http_stock])
+                        .manage(ledger)
+                        .manage(admin::AdminSecret("test-secret".to_string()));
+    let client = Client::new(rocket).expect("valid rocket instance");
+    assert_eq!(client.post("/admin/pause").dispatch().status(), Status::Unauthorized);
+    assert_eq!(client.post("/admin/pause").header(Header::new("X-Admin-Secret", "wrong")).dispatch().status(), Status::Unauthorized);
+    assert_eq!(client.post("/admin/pause").header(Header::new("X-Admin-Secret", "test-secreT")).dispatch().status(), Status::Unauthorized);
+
+    let stock = |client: &Client| {
+        let mut response = client.post("/api/stock").header(ContentType::Form).body(format!("name={}&price=1.0&stock=0&uuid={}", item, ids[0])).dispatch();
+        response.body_string().unwrap()
+    };
+    client.post("/admin/pause").header(admin_header()).dispatch();
+    assert!(stock(&client).contains("trading is closed"));
+    client.post("/admin/resume").header(admin_header()).dispatch();
+    assert!(stock(&client).contains("\"success\": \"true\""));
+    client.post("/admin/freeze").header(ContentType::Form).header(admin_header()).body(format!("vendor={}", name)).dispatch();
+    assert!(stock(&client).contains("trading is closed"));
+    client.post("/admin/freeze").header(ContentType::Form).header(admin_header()).body(format!("vendor={}&frozen=false", name)).dispatch();
+    assert!(stock(&client).contains("\"success\": \"true\""));
+
+    let mut response = client.post("/admin/adjust").header(ContentType::Form).header(admin_header())
+                        .body(format!("vendor={}&bits=-250.5&item={}&count=-5", name, item)).dispatch();
+    let body = response.body_string().unwrap();
+    assert!(body.contains("\"bits\": \"749.5\""));
+    assert!(body.contains(&format!("\"units\": \"{}\"", held - 5)));
+    let mut response = client.post("/admin/inject").header(ContentType::Form).header(admin_header())
+                        .body(format!("vendor={}&item={}&count=5&price=3.0", name, item)).dispatch();
+    assert!(response.body_string().unwrap().contains(&format!("\"units\": \"{}\"", held)));
+    let mut response = client.post("/admin/adjust").header(ContentType::Form).header(admin_header())
+                        .body(format!("vendor={}&bits=100&item={}&count=-100000", name, item)).dispatch();
+    assert!(response.body_string().unwrap().contains("not enough units"));
+    assert_eq!(client.rocket().state::<ledger::MutLedger>().unwrap().session_ledger.read().unwrap().get_vendor(0).bits, 749.5);
+    for body in [format!("vendor={}&bits=100&item={}", name, item), format!("vendor={}&bits=100&count=5", name)].iter() {
+        let mut response = client.post("/admin/adjust").header(ContentType::Form).header(admin_header()).body(body.as_str()).dispatch();
+        assert!(response.body_string().unwrap().contains("\"Format\": \"incorrect\""));
+    }
+    assert_eq!(client.rocket().state::<ledger::MutLedger>().unwrap().session_ledger.read().unwrap().get_vendor(0).bits, 749.5);
+    let mut response = client.post("/admin/inject").header(ContentType::Form).header(admin_header())
+                        .body(format!("vendor={}&item={}&count=4294967295&price=3.0", name, item)).dispatch();
+    assert!(response.body_string().unwrap().contains("\"success\": \"false\""));
+    let mut response = client.post("/api/entries").header(ContentType::Form).body(format!("uuid={}", ids[0])).dispatch();
+    let entries: Vec<serde_json::Value> = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert!(entries.iter().any(|e| e["attribute"] == "admin bits" && e["price"] == -250.5));
+    assert!(entries.iter().any(|e| e["attribute"] == item.as_str() && e["change"] == -5));
+
+    client.post("/admin/kick").header(ContentType::Form).header(admin_header()).body(format!("vendor={}", name)).dispatch();
+    assert!(stock(&client).contains("not recognized"));
+    client.post("/admin/reset").header(admin_header()).dispatch();
+    let mut response = client.post("/api/entries").header(ContentType::Form).body(format!("uuid={}", ids[1])).dispatch();
+    assert!(response.body_string().unwrap().contains("not found"));
+}
+
+#[test]
+fn test_spectators() {
+    let (ledger, ids) = create_test_ledger(2);
+    let (seller, item) = {
+        let reader = ledger.session_ledger.read().unwrap();
+        (reader.get_vendor(0).name, reader.get_vendor(0).get_items()[0].name.clone())
+    };
+    let rocket = rocket::ignite()
+                        .mount("/", routes![authorization::spectate])
+                        .mount("/api", routes![ledger::request_entries, ledger::request_ledger_state, purchase::http_purchase, shop::http_stock])
+                        .manage(ledger)
+                        .manage(admin::AdminSecret("test-secret".to_string()));
+    let client = Client::new(rocket).expect("valid rocket instance");
+    assert_eq!(client.post("/spectate").dispatch().status(), Status::Unauthorized);
+    let mut response = client.post("/spectate").header(admin_header()).dispatch();
+    let token = serde_json::from_str::<serde_json::Value>(&response.body_string().unwrap()).unwrap()["token"].as_str().unwrap().to_string();
+
+    let mut response = client.post("/api/ledger_state").header(ContentType::Form).body(format!("uuid={}", token)).dispatch();
+    let state: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert_eq!(state["stored"].as_object().unwrap().len(), 2);
+    assert_eq!(state["bits"][&seller], 1000.0);
+
+    let mut response = client.post("/api/entries").header(ContentType::Form).body(format!("uuid={}", token)).dispatch();
+    let all: Vec<serde_json::Value> = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    let mut response = client.post("/api/entries").header(ContentType::Form).body(format!("uuid={}", ids[0])).dispatch();
+    let own: Vec<serde_json::Value> = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert!(own.len() < all.len());
+    assert!(own.iter().all(|e| e["vendor"] == seller.as_str()));
+
+    let mut response = client.post("/api/stock").header(ContentType::Form).body(format!("name={}&price=1.0&stock=1&uuid={}", item, token)).dispatch();
+    assert!(response.body_string().unwrap().contains("read-only"));
+    let mut response = client.post("/api/purchase").header(ContentType::Form).body(format!("item={}&count=1&from={}&to={}", item, seller, token)).dispatch();
+    assert!(response.body_string().unwrap().contains("read-only"));
+}
+
+#[test]
+fn test_intel() {
+    let mut options = Config::default();
+    options.merge(File::from_str("visibility = \"limited\"\nintel_cost = 5.0", FileFormat::Toml)).unwrap();
+    let mut ledger = ledger::Ledger::with_config(options);
+    let _ = ledger.register_vendor_with("a".to_string(), None, 100.0, vec![Item::new("u8".to_string(), 2.0, 5, 0), Item::new("u16".to_string(), 1.0, 0, 5)]);
+    let _ = ledger.register_vendor_with("b".to_string(), None, 100.0, vec![Item::new("u8".to_string(), 1.0, 3, 0), Item::new("u16".to_string(), 3.0, 2, 0)]);
+    let _ = ledger.register_vendor_with("c".to_string(), None, 4.0, vec![Item::new("u8".to_string(), 3.0, 1, 0)]);
+    assert_eq!(ledger.serialize_state().len(), 3);
+
+    let state = ledger.serialize_state_for(0);
+    assert_eq!(state.len(), 2);
+    assert_eq!(state["a"].0.len(), 2);
+    assert_eq!(state["b"].0.len(), 2);
+    assert!(!state.contains_key("c"));
+
+    let report = ledger.buy_intel(0, intel::Intel::Vendor(2)).unwrap();
+    assert_eq!(report["c"].1, vec![3.0]);
+    assert_eq!(ledger.get_vendor(0).bits, 95.0);
+    assert!(ledger.serialize_entries(Some(0)).contains("intel on c"));
+    let report = ledger.buy_intel(0, intel::Intel::Item("u8".to_string())).unwrap();
+    assert_eq!(report.len(), 3);
+    assert!(report.values().all(|v| v.0 == vec!["u8".to_string()]));
+    assert!(matches!(ledger.buy_intel(2, intel::Intel::Vendor(0)), Err(ledger::LedgerError::InsufficientFunds)));
+
+    let token = ledger.add_spectator();
+    let session_ledger = Arc::new(RwLock::new(ledger));
+    let rocket = rocket::ignite()
+                        .mount("/api", routes![replay::request_replay, session::request_standings, teams::request_teams])
+                        .manage(ledger::MutLedger{ session_ledger: session_ledger.clone() });
+    let client = Client::new(rocket).expect("valid rocket instance");
+    for path in ["/api/replay", "/api/standings", "/api/teams"].iter() {
+        let mut response = client.get(*path).dispatch();
+        assert!(response.body_string().unwrap().contains("\"visibility\": \"is limited"));
+    }
+    let mut response = client.get(format!("/api/replay?token={}", token)).dispatch();
+    assert!(response.body_string().unwrap().contains("\"vendors\""));
+    session_ledger.write().unwrap().end_session();
+    let mut response = client.get("/api/replay").dispatch();
+    assert!(response.body_string().unwrap().contains("\"vendors\""));
+}
+
+#[test]
+fn test_limited_visibility() {
+    let mut options = Config::default();
+    options.merge(File::from_str("visibility = \"limited\"", FileFormat::Toml)).unwrap();
+    let mut ledger = ledger::Ledger::with_config(options);
+    let _ = ledger.register_vendor_with("a".to_string(), Some("alpha".to_string()), 100.0, vec![Item::new("u8".to_string(), 2.0, 5, 0)]);
+    let _ = ledger.register_vendor_with("b".to_string(), Some("bravo".to_string()), 100.0, vec![Item::new("u16".to_string(), 7.25, 3, 0)]);
+    let token = ledger.add_spectator();
+    let rocket = rocket::ignite()
+                        .mount("/", routes![purchase::purchase_page, shop::stock_page])
+                        .mount("/api", routes![equity::request_shares, ledger::request_vendor_urls, purchase::request_quote,
+                                               reputation::request_reputation, short::request_loans, turns::request_turn])
+                        .mount("/vendors", routes![shop::market_home])
+                        .manage(ledger::MutLedger{ session_ledger: Arc::new(RwLock::new(ledger)) })
+                        .attach(Template::fairing());
+    let client = Client::new(rocket).expect("valid rocket instance");
+    for path in ["/api/quote?item=u16&count=1&from=b", "/api/shares", "/api/loans", "/api/reputation", "/api/vendor_urls"].iter() {
+        let mut response = client.get(*path).dispatch();
+        assert!(response.body_string().unwrap().contains("\"visibility\": \"is limited"));
+    }
+    let mut response = client.get(format!("/api/quote?item=u16&count=1&from=b&token={}", token)).dispatch();
+    assert!(response.body_string().unwrap().contains("7.25"));
+    let mut response = client.get("/api/turn").dispatch();
+    assert!(response.body_string().unwrap().contains("\"results\":[]"));
+    for path in ["/purchase", "/stock", "/vendors/"].iter() {
+        let body = client.get(*path).dispatch().body_string().unwrap();
+        assert!(!body.contains("bravo") && !body.contains("7.25"));
+    }
+    let body = client.get(format!("/vendors/?token={}", token)).dispatch().body_string().unwrap();
+    assert!(body.contains("bravo") && body.contains("7.25"));
+}
+
+#[test]
+fn test_rate_limits() {
+    let mut options = Config::default();
+    options.merge(File::from_str("rate_limit = 3\naction_budget = 1\nbudget_seconds = 60.0", FileFormat::Toml)).unwrap();
+    let mut session_ledger = ledger::Ledger::with_config(options);
+    let id = session_ledger.register_vendor("test".to_string(), None).unwrap();
+    assert!(session_ledger.throttle(&id, true).is_ok());
+    match session_ledger.throttle(&id, true) {
+        Err(limits::Limited::Budget { budget, retry_after }) => assert!(budget == 1 && retry_after > 0.0 && retry_after <= 60.0),
+        _ => panic!("second action should be over budget")
+    }
+    assert!(session_ledger.throttle(&id, false).is_ok());
+    assert!(session_ledger.throttle(&id, false).is_ok());
+    assert!(matches!(session_ledger.throttle(&id, false), Err(limits::Limited::Rate { limit: 3, .. })));
+    assert!(session_ledger.throttle("unknown", true).is_ok());
+
+    let ledger = ledger::MutLedger{session_ledger: Arc::new(RwLock::new(session_ledger))};
+    let rocket = rocket::ignite()
+                        .mount("/api", routes![ledger::request_ledger_state])
+                        .manage(ledger);
+    let client = Client::new(rocket).expect("valid rocket instance");
+    let mut response = client.post("/api/ledger_state").header(ContentType::Form).body(format!("uuid={}", id)).dispatch();
+    let body = response.body_string().unwrap();
+    assert!(body.contains("\"limit\": \"over 3 requests per second\""));
+    assert!(body.contains("retry_after"));
+}
+
+#[test]
+fn test_call_costs() {
+    let mut options = Config::default();
+    options.merge(File::from_str("rate_limit = 1\ncall_costs = { read = 0.5, purchase = 2.0 }", FileFormat::Toml)).unwrap();
+    let mut session_ledger = ledger::Ledger::with_config(options);
+    let id = session_ledger.register_vendor("test".to_string(), None).unwrap();
+    let poor = session_ledger.register_vendor_with("poor".to_string(), None, 0.25, vec![]).unwrap();
+    let version = session_ledger.get_version();
+    assert_eq!(session_ledger.call_cost(limits::Call::Stock), 0.0);
+    assert_eq!(session_ledger.charge_call(&id, limits::Call::Stock).unwrap(), 0.0);
+    assert_eq!(session_ledger.charge_call(&id, limits::Call::Purchase).unwrap(), 2.0);
+    assert_eq!(session_ledger.get_vendor(0).bits, 998.0);
+    assert!(session_ledger.serialize_entries(Some(0)).contains("purchase call"));
+    assert_eq!(session_ledger.get_version(), version);
+
+    let ledger = ledger::MutLedger{session_ledger: Arc::new(RwLock::new(session_ledger))};
+    let rocket = rocket::ignite()
+                        .mount("/api", routes![ledger::request_ledger_state])
+                        .manage(ledger);
+    let client = Client::new(rocket).expect("valid rocket instance");
+    let mut response = client.post("/api/ledger_state").header(ContentType::Form).body(format!("uuid={}", id)).dispatch();
+    let state: serde_json::Value = serde_json::from_str(&response.body_string().unwrap()).unwrap();
+    assert!(state["stored"].is_array());
+    let ledger = client.rocket().state::<ledger::MutLedger>().unwrap();
+    assert_eq!(ledger.session_ledger.read().unwrap().get_vendor(0).bits, 997.5);
+    let mut response = client.post("/api/ledger_state").header(ContentType::Form).body(format!("uuid={}", poor)).dispatch();
+    assert!(response.body_string().unwrap().contains("cannot afford the read call cost"));
+    let _ = ledger.session_ledger.write().unwrap().adjust_bits(1, 1.0);
+    let mut response = client.post("/api/ledger_state").header(ContentType::Form).body(format!("uuid={}", poor)).dispatch();
+    assert!(!response.body_string().unwrap().contains("\"success\": \"false\""));
+}
+
+#[test]
+fn test_events_from_config() {
+    let mut options = Config::default();
+    options.merge(File::from_str("[[events]]\nversion = 10\nshock = { kind = \"supply_glut\", item = \"u8\", count = 5 }\n\n[[events]]\nseconds = 30\nduration = 5\nshock = { kind = \"fee_holiday\" }", FileFormat::Toml)).unwrap();
+    let events = options.get::<Vec<events::ScheduledEvent>>("events").expect("events parsed");
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].version, Some(10));
+    assert!(matches!(events[1].shock, events::Shock::FeeHoliday));
+}
+
+#[test]
+fn test_events_fire() {
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("test".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    let stored = ledger.get_vendor(0).get_item(&item).unwrap().get_stored();
+    ledger.schedule_event(events::ScheduledEvent{ version: Some(0), seconds: None, duration: Some(0), shock: events::Shock::SupplyGlut{ item: item.clone(), count: 10 } });
+    ledger.schedule_event(events::ScheduledEvent{ version: Some(0), seconds: None, duration: Some(100), shock: events::Shock::PriceFreeze{ item: item.clone() } });
+    ledger.tick();
+    assert_eq!(ledger.get_events().len(), 2);
+    assert_eq!(ledger.get_vendor(0).get_item(&item).unwrap().get_stored(), stored + 10);
+    assert!(ledger.is_price_frozen(&item));
+    assert!(!ledger.fees_waived());
+}
+
 #[test]
 fn test_get_count_get_stored() {
     let item = shop::Item::new("test".to_string(), 2.0, 15, 41);
@@ -44,7 +886,7 @@ fn test_get_count_get_stored() {
 fn test_get_item_history() {
     let mut ledger = ledger::Ledger::new();
     let _ = ledger.register_vendor("test".to_string(), None);
-    for s in util::get_rust_types(0) {
+    for s in util::get_rust_types() {
         if !ledger.get_ledger_items().contains(&s.to_string()) {
             assert_eq!(ledger.get_item_history(s.to_string()), vec![0.0]);
         }
@@ -72,7 +914,7 @@ fn test_get_price_history() {
     let _ = ledger.register_vendor("test".to_string(), None);
     let ledger_items: Vec<String> = ledger.get_ledger_items();
     let price_history = ledger.get_price_history();
-    for rt in util::get_rust_types(0) {
+    for rt in util::get_rust_types() {
         if !ledger_items.contains(&rt.to_string()) {
             assert_eq!(price_history[util::get_rust_type_index(rt.to_string())], vec![0.0]);
         }
@@ -131,6 +973,21 @@ fn test_new_item() {
     assert_eq!(i.get_count(), 32);
 }
 
+#[test]
+fn test_item_tiered_cost() {
+    let mut v = Vendor::new(String::from("Vendor"), String::from("vendor"), 1000.0);
+    v.add_item(Item::new(String::from("u8"), 2.0, 100, 0), false);
+    v.update_tiers(String::from("u8"), shop::parse_tiers("10:1.5, 50:1").unwrap());
+    let item = v.get_item(&String::from("u8")).unwrap();
+    assert_eq!(item.cost(5), 10.0);
+    assert_eq!(item.cost(10), 18.0 + 1.5);
+    assert_eq!(item.cost(60), 18.0 + 40.0 * 1.5 + 11.0);
+    assert!(shop::parse_tiers("").unwrap().is_empty());
+    assert!(shop::parse_tiers("1:1.0").is_none());
+    assert!(shop::parse_tiers("10:1.0,5:0.5").is_none());
+    assert!(shop::parse_tiers("10-1.0").is_none());
+}
+
 #[test]
 fn test_new_vendor() {
     let v = Vendor::new(String::from("Test Vendor"), String::from("test_vendor"), 100.0);
@@ -174,6 +1031,42 @@ fn test_purchase() {
     assert!(purchase_response.body_string().unwrap().contains("\"success\": \"true\""));
 }
 
+#[test]
+fn test_tiered_purchase() {
+    let mut ledger = ledger::Ledger::new();
+    let id1 = ledger.register_vendor("test".to_string(), None).unwrap();
+    let v1_items = ledger.get_ledger_items();
+    let id2 = ledger.register_vendor("test2".to_string(), None).unwrap();
+
+    let rocket = rocket::ignite()
+                        .manage(ledger::MutLedger{ session_ledger: Arc::new(RwLock::new(ledger)) })
+                        .mount("/", routes![purchase::http_purchase, purchase::request_quote, shop::http_stock]);
+    let client = Client::new(rocket).expect("valid rocket instance");
+
+    let mut stock_response = client.post("/stock")
+                                   .body(format!("name={}&price=2&stock=20&uuid={}&tiers=5:1", v1_items[0], id1))
+                                   .header(ContentType::Form)
+                                   .dispatch();
+    assert!(stock_response.body_string().unwrap().contains("\"success\": \"true\""));
+
+    let mut quote_response = client.get(format!("/quote?item={}&count=10&from=test", v1_items[0])).dispatch();
+    let quote = quote_response.body_string().unwrap();
+    assert!(quote.contains("\"total\": \"14\""));
+    assert!(quote.contains("\"unit_price\": \"1.4\""));
+
+    let mut purchase_response = client.post("/purchase")
+                                      .body(format!("item={}&count=10&from=test&to={}", v1_items[0], id2))
+                                      .header(ContentType::Form)
+                                      .dispatch();
+    assert!(purchase_response.body_string().unwrap().contains("\"total\": \"14\""));
+
+    let mut bad_response = client.post("/stock")
+                                 .body(format!("name={}&price=2&stock=0&uuid={}&tiers=5", v1_items[0], id1))
+                                 .header(ContentType::Form)
+                                 .dispatch();
+    assert!(bad_response.body_string().unwrap().contains("\"success\": \"false\""));
+}
+
 #[test]
 fn test_register_endpoint() {
     let rocket = rocket::ignite()
@@ -240,6 +1133,20 @@ fn test_vendor_add_item() {
     assert_eq!(item.get_count(), 100);
 }
 
+#[test]
+fn test_vendor_restock_item() {
+    let v = &mut Vendor::new(String::from("Vendor"), String::from("vendor"), 1000.0);
+    v.add_item(Item::new(String::from("f32"), 32.0, 10, 5), false);
+    v.add_item(Item::new(String::from("f32"), 32.0, 4, 0), false);
+    let item = v.get_item(&String::from("f32")).unwrap();
+    assert_eq!(item.get_count(), 14);
+    assert_eq!(item.get_stored(), 5);
+    v.add_item(Item::new(String::from("f32"), 32.0, 0, 3), true);
+    let item = v.get_item(&String::from("f32")).unwrap();
+    assert_eq!(item.get_count(), 14);
+    assert_eq!(item.get_stored(), 8);
+}
+
 #[test]
 fn test_vendor_purchase_item() {
     let v = &mut Vendor::new(String::from("Vendor"), String::from("vendor"), 1000.0);
@@ -255,6 +1162,65 @@ fn test_vendor_purchase_item() {
     assert_eq!(0, v.get_item(&stir).unwrap().get_count());
 }
 
+#[test]
+fn test_short_borrow_and_return() {
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("lender".to_string(), None);
+    let _ = ledger.register_vendor("borrower".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 2.0, 10);
+    let order = purchase::Order{ item: item.clone(), count: 1, from: "lender".to_string(), to: "".to_string(), delivery: None };
+    ledger.purchase(order, 0, 1);
+    assert!(ledger.get_reference_price(&item) > 0.0);
+    assert!(ledger.borrow(1, None, item.clone(), 5).is_err());
+
+    ledger.set_lend_offer(0, item.clone(), 5, 0.1);
+    let bits = ledger.get_vendor(1).bits;
+    let loan = ledger.borrow(1, Some(0), item.clone(), 5).expect("loan granted");
+    assert_eq!(ledger.get_vendor(1).bits, bits - loan.collateral - loan.fee);
+    assert_eq!(ledger.get_vendor(0).get_item(&item).unwrap().get_stored(), 35);
+    assert!(ledger.get_lend_offers().is_empty());
+
+    let _ = ledger.return_loan(1, loan.id).expect("loan returned");
+    assert_eq!(ledger.get_vendor(1).bits, bits - loan.fee);
+    assert_eq!(ledger.get_vendor(0).get_item(&item).unwrap().get_stored(), 40);
+    assert!(ledger.get_open_loans().is_empty());
+}
+
+#[test]
+fn test_short_forced_buy_in() {
+    let mut ledger = ledger::Ledger::new();
+    let _ = ledger.register_vendor("lender".to_string(), None);
+    let _ = ledger.register_vendor("borrower".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 1.0, 10);
+    let order = purchase::Order{ item: item.clone(), count: 1, from: "lender".to_string(), to: "".to_string(), delivery: None };
+    ledger.purchase(order, 0, 1);
+    ledger.set_lend_offer(0, item.clone(), 5, 0.0);
+    let _ = ledger.borrow(1, Some(0), item.clone(), 5).expect("loan granted");
+
+    ledger.update_item(0, item.clone(), 50.0, 0);
+    let order = purchase::Order{ item: item.clone(), count: 1, from: "lender".to_string(), to: "".to_string(), delivery: None };
+    ledger.purchase(order, 0, 1);
+    assert!(ledger.get_open_loans().is_empty());
+    assert_eq!(ledger.get_vendor(0).get_item(&item).unwrap().get_stored(), 40);
+
+    let mut options = Config::default();
+    options.merge(File::from_str("short_collateral = 1.5\nshort_maintenance = 2.0", FileFormat::Toml)).unwrap();
+    let mut ledger = ledger::Ledger::with_config(options);
+    let _ = ledger.register_vendor("lender".to_string(), None);
+    let _ = ledger.register_vendor("borrower".to_string(), None);
+    let item = ledger.get_vendor(0).get_items()[0].name.clone();
+    ledger.update_item(0, item.clone(), 1.0, 10);
+    let order = purchase::Order{ item: item.clone(), count: 1, from: "lender".to_string(), to: "".to_string(), delivery: None };
+    ledger.purchase(order, 0, 1);
+    ledger.set_lend_offer(0, item.clone(), 5, 0.0);
+    let _ = ledger.borrow(1, Some(0), item.clone(), 5).expect("loan granted");
+    assert_eq!(ledger.get_open_loans().len(), 1);
+    ledger.tick();
+    assert!(ledger.get_open_loans().is_empty());
+}
+
 #[test]
 fn test_verify_uuid() {
     let (ledger, ids) = create_test_ledger(3);
@@ -0,0 +1,480 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Display;
+use std::fs;
+use std::sync::RwLock;
+use std::time::Instant;
+
+use rocket::State;
+use rocket::request::{Form, FormError};
+use rocket::response::content;
+use rocket_contrib::templates::Template;
+use serde_json::{to_string, to_value};
+use super::admin::Admin;
+use super::ledger::Ledger;
+use super::markets::{self, Markets};
+use super::session::{Phase, Standing};
+use super::util;
+
+const INITIAL_RATING: f64 = 1500.0;
+
+//How entrants are paired up for each round. Round-robin plays everyone against
+//everyone once, Swiss pairs entrants with similar scores for a set of rounds
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    RoundRobin,
+    Swiss
+}
+
+impl Format {
+    /// Parses the pairing format of a tournament, defaulting to round-robin
+    ///
+    /// # Arguments
+    ///
+    /// * `format`  - The optional format from the request
+    pub fn parse(format: &Option<String>) -> Option<Format> {
+        match format.as_deref() {
+            None | Some("") | Some("round_robin") => Some(Format::RoundRobin),
+            Some("swiss") => Some(Format::Swiss),
+            Some(_) => None
+        }
+    }
+}
+
+//A session between two entrants, played in its own market. Entrants without
+//an opponent sit the round out, and players that never register forfeit
+#[derive(Clone, Debug, Serialize)]
+pub struct Match {
+    pub round: u32,
+    pub market: String,
+    pub players: Vec<String>,
+    pub standings: Vec<Standing>,
+    pub forfeited: Vec<String>,
+    pub finished: bool,
+    #[serde(skip)]
+    created: Instant
+}
+
+//A series of market sessions between bots, entered under their vendor names
+#[derive(Clone, Debug, Serialize)]
+pub struct Tournament {
+    pub id: String,
+    pub format: Format,
+    pub entrants: Vec<String>,
+    pub rounds: u32,
+    pub round: u32,
+    pub points: BTreeMap<String, f64>,
+    pub byes: Vec<(u32, String)>,
+    pub matches: Vec<Match>,
+    pub finished: bool,
+    #[serde(skip)]
+    config: Option<String>,
+    #[serde(skip)]
+    lobby_seconds: u64,
+    #[serde(skip)]
+    tokens: HashMap<String, String>
+}
+
+impl Tournament {
+    /// Creates a tournament and starts its first round
+    ///
+    /// # Arguments
+    ///
+    /// * `id`          - The id of the tournament, prefixing its market ids
+    /// * `entrants`    - The vendor names of the competing bots
+    /// * `format`      - How entrants are paired up
+    /// * `rounds`      - The number of Swiss rounds, ignored for round-robin
+    /// * `config`      - TOML overriding `Config.toml` in every match market
+    /// * `markets`     - Every market on the server
+    /// * `ratings`     - The ratings of every bot
+    pub fn new(id: String, entrants: Vec<String>, format: Format, rounds: Option<u32>, config: Option<String>, markets: &Markets, ratings: &Ratings) -> Tournament {
+        let rounds = match format {
+            Format::RoundRobin => round_robin(&entrants).len() as u32,
+            Format::Swiss => rounds.unwrap_or_else(|| (entrants.len() as f64).log2().ceil() as u32).max(1)
+        };
+        let points = entrants.iter().map(|e| (e.clone(), 0.0)).collect();
+        let lobby_seconds = markets::parse_config(&config).and_then(|c| c.get::<u64>("tournament_lobby_seconds").ok())
+            .or_else(|| super::get_config::<u64>("tournament_lobby_seconds")).unwrap_or(60);
+        let tokens = entrants.iter().map(|e| (e.clone(), nanoid::simple())).collect();
+        let mut tournament = Tournament { id, format, entrants, rounds, round: 0, points, byes: vec![], matches: vec![], finished: false, config, lobby_seconds, tokens };
+        tournament.start_round(markets, ratings);
+        tournament
+    }
+
+    /// Returns the join token of each entrant, which its bot must send when
+    /// registering in a match market
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current tournament
+    pub fn get_tokens(&self) -> &HashMap<String, String> { &self.tokens }
+
+    /// Records the standings of every match whose session has ended, updating
+    /// points and ratings, and starts the next round once all of its matches
+    /// are finished. A match still waiting for its players after
+    /// `tournament_lobby_seconds` is forfeited by those that did not register,
+    /// without changing ratings. Returns true if any match finished
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current tournament
+    /// * `markets` - Every market on the server
+    /// * `ratings` - The ratings of every bot
+    pub fn update(&mut self, markets: &Markets, ratings: &mut Ratings) -> bool {
+        let mut changed = false;
+        for m in self.matches.iter_mut().filter(|m| !m.finished) {
+            let ledger = match markets.get(&m.market) {
+                Some(l) => l,
+                None => continue
+            };
+            let mut ledger = ledger.write().unwrap();
+            ledger.update_session();
+            let phase = ledger.get_session().phase;
+            let abandoned = phase == Phase::Lobby && m.created.elapsed().as_secs() >= self.lobby_seconds;
+            if phase != Phase::Ended && !abandoned { continue; }
+            m.standings = ledger.get_standings();
+            m.finished = true;
+            changed = true;
+
+            if abandoned {
+                let registered = ledger.get_vendor_names();
+                m.forfeited = m.players.iter().filter(|p| !registered.contains(p)).cloned().collect();
+                if let [forfeited] = m.forfeited.as_slice() {
+                    let winner = m.players.iter().find(|p| *p != forfeited).unwrap();
+                    *self.points.entry(winner.clone()).or_insert(0.0) += 1.0;
+                }
+                continue;
+            }
+
+            let worth = |p: &String| m.standings.iter().find(|s| &s.vendor == p).map_or(0.0, |s| s.net_worth);
+            let (a, b) = (&m.players[0], &m.players[1]);
+            let score = match worth(a).partial_cmp(&worth(b)) {
+                Some(std::cmp::Ordering::Greater) => 1.0,
+                Some(std::cmp::Ordering::Less) => 0.0,
+                _ => 0.5
+            };
+            *self.points.entry(a.clone()).or_insert(0.0) += score;
+            *self.points.entry(b.clone()).or_insert(0.0) += 1.0 - score;
+            ratings.record(a, b, score);
+        }
+
+        if self.matches.iter().all(|m| m.finished) && !self.finished {
+            if self.round < self.rounds {
+                self.start_round(markets, ratings);
+            } else {
+                self.finished = true;
+            }
+        }
+        changed
+    }
+
+    /// Pairs up the entrants for the next round and creates a market for each
+    /// match, in which only its players may register, under their entrant
+    /// names and with their join tokens
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current tournament
+    /// * `markets` - Every market on the server
+    /// * `ratings` - The ratings of every bot
+    fn start_round(&mut self, markets: &Markets, ratings: &Ratings) {
+        self.round += 1;
+        let pairs = match self.format {
+            Format::RoundRobin => round_robin(&self.entrants).remove(self.round as usize - 1),
+            Format::Swiss => {
+                let played: HashSet<(String, String)> = self.matches.iter()
+                    .flat_map(|m| vec![(m.players[0].clone(), m.players[1].clone()), (m.players[1].clone(), m.players[0].clone())])
+                    .collect();
+                swiss(&self.entrants, &self.points, &ratings.ratings, &played)
+            }
+        };
+
+        for (n, (a, b)) in pairs.into_iter().enumerate() {
+            let b = match b {
+                Some(b) => b,
+                None => {
+                    if self.format == Format::Swiss { *self.points.entry(a.clone()).or_insert(0.0) += 1.0; }
+                    self.byes.push((self.round, a));
+                    continue;
+                }
+            };
+            let mut config = markets::parse_config(&self.config).unwrap_or_default();
+            let _ = config.set_default("generated_vendors", 0);
+            let _ = config.set_default("session_vendors", 2);
+            let _ = config.set_default("session_seconds", super::get_config::<i64>("tournament_seconds").unwrap_or(300));
+            let market = format!("{}-r{}-m{}", self.id, self.round, n + 1);
+            let ledger = Ledger::with_config(config);
+            ledger.set_entrants([&a, &b].iter().map(|p| ((*p).clone(), self.tokens[*p].clone())).collect());
+            markets.insert(market.clone(), ledger);
+            self.matches.push(Match { round: self.round, market, players: vec![a, b], standings: vec![], forfeited: vec![], finished: false, created: Instant::now() });
+        }
+    }
+}
+
+//Elo rating of every bot that has played a tournament match, saved to the
+//`ratings_file` so ratings carry over between tournaments
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Ratings {
+    pub ratings: BTreeMap<String, f64>,
+    #[serde(skip)]
+    path: String
+}
+
+impl Ratings {
+    /// Loads the ratings saved at a path, starting empty if there are none
+    ///
+    /// # Arguments
+    ///
+    /// * `path`    - The file the ratings are saved in
+    pub fn load(path: String) -> Ratings {
+        let ratings = fs::read_to_string(&path).ok().and_then(|s| serde_json::from_str(&s).ok()).unwrap_or_default();
+        Ratings { ratings, path }
+    }
+
+    /// Returns the rating of a bot, new bots start at 1500
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current ratings
+    /// * `bot`     - The name of the bot
+    pub fn get(&self, bot: &str) -> f64 { self.ratings.get(bot).cloned().unwrap_or(INITIAL_RATING) }
+
+    /// Updates the ratings of two bots after a match and saves them
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current ratings
+    /// * `a`       - The name of the first bot
+    /// * `b`       - The name of the second bot
+    /// * `score`   - The score of the first bot, 1 for a win and 0.5 for a draw
+    pub fn record(&mut self, a: &str, b: &str, score: f64) {
+        let k = super::get_config::<f64>("elo_k").unwrap_or(32.0);
+        let (ra, rb) = elo(self.get(a), self.get(b), score, k);
+        self.ratings.insert(a.to_string(), ra);
+        self.ratings.insert(b.to_string(), rb);
+        if !self.path.is_empty() {
+            let _ = fs::write(&self.path, to_string(&self.ratings).unwrap());
+        }
+    }
+}
+
+//The current tournament and the ratings of every bot
+pub struct Tournaments {
+    pub current: RwLock<Option<Tournament>>,
+    pub ratings: RwLock<Ratings>,
+    updated: RwLock<Instant>
+}
+
+impl Tournaments {
+    /// Creates the tournament state with the ratings saved in `ratings_file`
+    pub fn from_config() -> Tournaments {
+        let path = super::get_config::<String>("ratings_file").unwrap_or_else(|| "ratings.json".to_string());
+        Tournaments { current: RwLock::new(None), ratings: RwLock::new(Ratings::load(path)), updated: RwLock::new(Instant::now()) }
+    }
+
+    /// Brings the current tournament up to date at most once a second, so it
+    /// advances while bots trade without anyone polling it
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current tournament state
+    /// * `markets` - Every market on the server
+    pub fn tick(&self, markets: &Markets) {
+        {
+            let mut updated = self.updated.write().unwrap();
+            if updated.elapsed().as_secs() < 1 { return; }
+            *updated = Instant::now();
+        }
+        self.update(markets);
+    }
+
+    /// Replaces the current tournament, removing the match markets of the
+    /// previous one
+    ///
+    /// # Arguments
+    ///
+    /// * `self`        - The current tournament state
+    /// * `tournament`  - The new tournament
+    /// * `markets`     - Every market on the server
+    pub fn replace(&self, tournament: Tournament, markets: &Markets) {
+        let previous = self.current.write().unwrap().replace(tournament);
+        for m in previous.iter().flat_map(|t| t.matches.iter()) {
+            markets.remove(&m.market);
+        }
+    }
+
+    /// Brings the current tournament up to date with its match markets
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current tournament state
+    /// * `markets` - Every market on the server
+    pub fn update(&self, markets: &Markets) {
+        if let Some(t) = self.current.write().unwrap().as_mut() {
+            t.update(markets, &mut self.ratings.write().unwrap());
+        }
+    }
+}
+
+/// Returns the new ratings of two players after a game
+///
+/// # Arguments
+///
+/// * `a`       - The rating of the first player
+/// * `b`       - The rating of the second player
+/// * `score`   - The score of the first player, 1 for a win and 0.5 for a draw
+/// * `k`       - The largest possible change in rating
+pub fn elo(a: f64, b: f64, score: f64, k: f64) -> (f64, f64) {
+    let expected = 1.0 / (1.0 + 10f64.powf((b - a) / 400.0));
+    let change = k * (score - expected);
+    (a + change, b - change)
+}
+
+/// Schedules every round of a round-robin with the circle method. With an odd
+/// number of entrants one of them sits out each round
+///
+/// # Arguments
+///
+/// * `entrants`    - The names of the entrants
+pub fn round_robin(entrants: &[String]) -> Vec<Vec<(String, Option<String>)>> {
+    let mut circle: Vec<Option<String>> = entrants.iter().cloned().map(Some).collect();
+    if circle.len() % 2 == 1 { circle.push(None); }
+    let n = circle.len();
+    let mut rounds = vec![];
+    for _ in 1..n {
+        let round = (0..n / 2).filter_map(|i| match (&circle[i], &circle[n - 1 - i]) {
+            (Some(a), b) => Some((a.clone(), b.clone())),
+            (None, Some(b)) => Some((b.clone(), None)),
+            (None, None) => None
+        }).collect();
+        rounds.push(round);
+        let last = circle.pop().unwrap();
+        circle.insert(1, last);
+    }
+    rounds
+}
+
+/// Pairs entrants with similar points for a Swiss round, avoiding rematches
+/// where possible. Ties on points are broken by rating, and with an odd number
+/// of entrants the lowest ranked one sits out
+///
+/// # Arguments
+///
+/// * `entrants`    - The names of the entrants
+/// * `points`      - The tournament points of each entrant
+/// * `ratings`     - The rating of each entrant
+/// * `played`      - Every pair of entrants that has already met
+pub fn swiss(entrants: &[String], points: &BTreeMap<String, f64>, ratings: &BTreeMap<String, f64>, played: &HashSet<(String, String)>) -> Vec<(String, Option<String>)> {
+    let score = |e: &String| (points.get(e).cloned().unwrap_or(0.0), ratings.get(e).cloned().unwrap_or(INITIAL_RATING));
+    let mut ranked = entrants.to_vec();
+    ranked.sort_by(|a, b| score(b).partial_cmp(&score(a)).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut pairs = vec![];
+    while !ranked.is_empty() {
+        let a = ranked.remove(0);
+        if ranked.is_empty() {
+            pairs.push((a, None));
+            break;
+        }
+        let pos = ranked.iter().position(|b| !played.contains(&(a.clone(), b.clone()))).unwrap_or(0);
+        pairs.push((a, Some(ranked.remove(pos))));
+    }
+    pairs
+}
+
+#[derive(Debug, FromForm)]
+pub struct NewTournament {
+    pub id: String,
+    pub entrants: String,
+    pub format: Option<String>,
+    pub rounds: Option<u32>,
+    pub config: Option<String>
+}
+
+/// Endpoint to start a tournament via http request, replacing any previous
+/// one and removing its markets. Entrants are the comma separated vendor names
+/// the bots will register under in each match market, and the join token of
+/// each entrant is returned. Requires the admin secret
+///
+/// # Arguments
+///
+/// * `new_tournament`  - The DTO describing the tournament
+/// * `tournaments`     - The tournament state
+/// * `markets`         - Every market on the server
+#[post("/tournament", data="<new_tournament>")]
+pub fn http_create(_admin: Admin, new_tournament: Result<Form<NewTournament>, FormError<'_>>, tournaments: State<Tournaments>, markets: State<Markets>) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let new_tournament = match new_tournament {
+        Ok(t) => t.into_inner(),
+        Err(_) => {
+            output_vars.insert("Format".to_string(), Box::new("incorrect"));
+            return util::construct_json(&output_vars);
+        }
+    };
+    let mut entrants: Vec<String> = new_tournament.entrants.split(',').map(|e| e.trim().to_string()).filter(|e| !e.is_empty()).collect();
+    let mut seen = HashSet::new();
+    entrants.retain(|e| seen.insert(e.clone()));
+
+    output_vars.insert("success".to_string(), Box::new(false));
+    let format = match Format::parse(&new_tournament.format) {
+        Some(f) => f,
+        None => {
+            output_vars.insert("format".to_string(), Box::new("must be round_robin or swiss".to_string()));
+            return util::construct_json(&output_vars);
+        }
+    };
+    if entrants.len() < 2 {
+        output_vars.insert("entrants".to_string(), Box::new("must name at least two bots".to_string()));
+        return util::construct_json(&output_vars);
+    }
+    let prefix = format!("{}-", new_tournament.id);
+    let id_taken = markets.ledgers.read().unwrap().keys().any(|k| k.starts_with(&prefix));
+    if new_tournament.id.is_empty() || !new_tournament.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') || id_taken {
+        output_vars.insert("id".to_string(), Box::new("must be unused letters, digits or underscores".to_string()));
+        return util::construct_json(&output_vars);
+    }
+    match markets::parse_config(&new_tournament.config) {
+        None => {
+            output_vars.insert("config".to_string(), Box::new("is not valid TOML".to_string()));
+            return util::construct_json(&output_vars);
+        },
+        Some(config) if !util::keeps_catalog(&config) => {
+            output_vars.insert("config".to_string(), Box::new("catalog differs from the running catalog, it can only be changed at startup".to_string()));
+            return util::construct_json(&output_vars);
+        },
+        Some(_) => {}
+    }
+
+    let tournament = Tournament::new(new_tournament.id, entrants, format, new_tournament.rounds, new_tournament.config, &markets, &tournaments.ratings.read().unwrap());
+    let output = serde_json::json!({
+        "success": "true",
+        "rounds": tournament.rounds.to_string(),
+        "tokens": tournament.get_tokens()
+    });
+    tournaments.replace(tournament, &markets);
+    content::Json(to_string(&output).unwrap())
+}
+
+/// Endpoint to get the current tournament with its matches and points, and
+/// the ratings of every bot, via http request
+#[get("/tournament")]
+pub fn request_tournament(tournaments: State<Tournaments>, markets: State<Markets>) -> content::Json<String> {
+    tournaments.update(&markets);
+    let output = serde_json::json!({
+        "tournament": *tournaments.current.read().unwrap(),
+        "ratings": tournaments.ratings.read().unwrap().ratings
+    });
+    content::Json(to_string(&output).unwrap())
+}
+
+/// Tournament results page GET endpoint
+#[get("/tournament")]
+pub fn tournament_page(tournaments: State<Tournaments>, markets: State<Markets>) -> Template {
+    tournaments.update(&markets);
+    let mut map = super::HashMap::new();
+    let mut ratings: Vec<(String, f64)> = tournaments.ratings.read().unwrap().ratings.clone().into_iter().collect();
+    ratings.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+    map.insert("ratings", to_value(ratings).unwrap());
+    map.insert("tournament", to_value(&*tournaments.current.read().unwrap()).unwrap());
+    Template::render("tournament", &map)
+}
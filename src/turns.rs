@@ -0,0 +1,156 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use rocket::response::content;
+use super::markets::MarketLedger;
+use serde_json::to_string;
+use super::ledger::MutLedger;
+use super::purchase::{self, Order};
+use super::shop::{self, AuthItem};
+
+//A stock or purchase action submitted for the current turn
+#[derive(Clone, Debug)]
+pub enum Action {
+    Stock(AuthItem),
+    Purchase(Order)
+}
+
+impl Action {
+    /// Returns the kind of the action and the item it is for
+    ///
+    /// # Arguments
+    ///
+    /// * `self`    - The current action
+    pub fn describe(&self) -> (String, String) {
+        match self {
+            Action::Stock(a) => ("stock".to_string(), a.name.clone()),
+            Action::Purchase(o) => ("purchase".to_string(), o.item.clone())
+        }
+    }
+}
+
+//An action waiting for its turn to close, with the name of the vendor that
+//submitted it
+#[derive(Clone, Debug)]
+pub struct QueuedAction {
+    pub vendor: String,
+    pub action: Action
+}
+
+//The outcome of a queued action, published once its turn is resolved
+#[derive(Clone, Debug, Serialize)]
+pub struct TurnResult {
+    pub vendor: String,
+    pub action: String,
+    pub item: String,
+    pub result: BTreeMap<String, String>
+}
+
+//Turn-based trading state. Actions queue until the turn closes `closes`
+//seconds after the ledger was created, then resolve together in random order
+#[derive(Clone, Debug, Serialize)]
+pub struct Turn {
+    pub number: u32,
+    pub closes: u64,
+    #[serde(skip)]
+    pub queue: Vec<QueuedAction>,
+    pub resolved: Option<u32>,
+    pub resolved_version: u32,
+    pub results: Vec<TurnResult>
+}
+
+impl Turn {
+    /// Creates the first turn, closing after the given number of seconds
+    ///
+    /// # Arguments
+    ///
+    /// * `seconds` - The length of a turn
+    pub fn new(seconds: u64) -> Turn {
+        Turn { number: 1, closes: seconds, queue: vec![], resolved: None, resolved_version: 0, results: vec![] }
+    }
+}
+
+/// Queues a stock or purchase action for the current turn instead of running
+/// it, rejecting vendors that are not recognized or cannot trade
+///
+/// # Arguments
+///
+/// * `action`  - The action being submitted
+/// * `uuid`    - The unique user ID of the submitting vendor
+/// * `ledger`  - The current ledger state
+pub fn submit(action: Action, uuid: &str, ledger: &MutLedger) -> BTreeMap<String, Box<dyn Display>> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let ledger = ledger.session_ledger.read().unwrap();
+    let vendor_id = match ledger.verify_uuid(uuid.to_string()) {
+        Ok(id) => id,
+        Err(_) => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("UUID".to_string(), Box::new("not recognized".to_string()));
+            return output_vars;
+        }
+    };
+    if let Err(closed) = super::authorization::check_trading(&ledger, vendor_id) {
+        return closed;
+    }
+    let turn = ledger.queue_action(vendor_id, action);
+    output_vars.insert("success".to_string(), Box::new(true));
+    output_vars.insert("queued".to_string(), Box::new(true));
+    output_vars.insert("turn".to_string(), Box::new(turn));
+    output_vars.insert("closes_in".to_string(), Box::new(ledger.turn_closes_in()));
+    output_vars
+}
+
+/// Resolves the current turn if it has closed. Stock actions run before
+/// purchases so that new prices apply to the same turn, and each group keeps
+/// the random order of the ledger so no vendor gains from submitting first
+///
+/// # Arguments
+///
+/// * `ledger`  - The current ledger state
+pub fn resolve_due(ledger: &MutLedger) {
+    let due = ledger.session_ledger.read().unwrap().take_due_turn();
+    let (number, mut queue) = match due {
+        Some(d) => d,
+        None => return
+    };
+    queue.sort_by_key(|q| match q.action { Action::Stock(_) => 0, Action::Purchase(_) => 1 });
+
+    let results = queue.into_iter().map(|q| {
+        let (action, item) = q.action.describe();
+        let output = match q.action {
+            Action::Stock(a) => shop::apply_stock(a, ledger),
+            Action::Purchase(o) => purchase::fill_order(o, ledger)
+        };
+        let result = output.iter().map(|(k, v)| (k.clone(), format!("{}", v))).collect();
+        TurnResult { vendor: q.vendor, action, item, result }
+    }).collect();
+    ledger.session_ledger.write().unwrap().finish_turn(number, results);
+}
+
+/// Endpoint to get the current turn and the results of the last resolved turn
+/// via http request. With limited visibility the results, which name every
+/// vendor's orders, are left out for all but spectators until the session ends
+///
+/// # Arguments
+///
+/// * `token`   - A spectator token, needed with limited visibility
+/// * `ledger`  - The current ledger state
+#[get("/turn?<token>")]
+pub fn request_turn(token: Option<String>, ledger: MarketLedger) -> content::Json<String> {
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let ledger = &*arc_ledger.read().unwrap();
+    let mut turn = ledger.get_turn();
+    if !ledger.reveals_all(token.as_deref()) { turn.results.clear(); }
+    let output = serde_json::json!({
+        "turn_based": ledger.turn_based(),
+        "turn": turn.number,
+        "closes_in": ledger.turn_closes_in(),
+        "queued": turn.queue.len(),
+        "last": {
+            "turn": turn.resolved,
+            "version": turn.resolved_version,
+            "results": turn.results
+        }
+    });
+    content::Json(to_string(&output).unwrap())
+}
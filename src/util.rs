@@ -1,16 +1,72 @@
 use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
+use rocket::request::{Form, FormError};
 use rocket::response::content;
+use super::authorization;
+use super::ledger::Ledger;
+use super::limits::{self, Call};
+use super::markets::MarketLedger;
 
 const ADJECTIVES: &'static [&'static str] = &["Dry", "Oafish", "Unusual", "ExtraLarge", "Thirsty", "Alluring", "Bewildered", "Steadfast", "Rotund", "Unsightly", "Swanky", "Majestic", "Torpid", "Capricious", "Vacuous", "Exclusive"];
 const CATCHPHRASE: &'static [&'static str] = &["Best prices this side of the http", "1-800-bits4bytes", "Your favorite fiscal fenagler!", "Friendly neighborhood objectivism-man", "My manager \"produces\" value", "Great sales for the highest bidder!", "Being dead inside makes you more aerodynamic!", "Ask not what your vendor can sell to you, ask what you can buy from your vendor!", "Buy, or buy not, there is no haggle.", "Life is a box of types", "This is exactly how the dark web works", "Imagine how much easier this would be if we had healthcare"];
 const OCCUPATION: &'static [&'static str] = &["Vendor", "Dealer", "Merchant", "Hawker", "Peddler", "Huckster", "Clerk", "Salesperson", "Trader", "Agent", "Chef", "Agriculturalist", "Pusher", "Capitalist", "Suspect", "Seal Clubber"];
 const RUST_TYPES: &'static [&'static str] = &["bool", "u8", "u16", "u32", "u64", "u128", "i8", "i16", "i32", "i64", "i128", "f32", "f64", "str", "char", "never"];
 
-/// Returns a random catchphrase from a static list
+//Action forms are all authenticated by the acting vendor's uuid
+pub trait HasUuid {
+    fn uuid(&self) -> &str;
+}
+
+//Check a vendor has to pass before a call runs, answering with the output
+//variables of the refusal
+pub type VendorCheck = fn(&Ledger, usize) -> Result<(), BTreeMap<String, Box<dyn Display>>>;
+
+lazy_static! {
+    static ref CATALOG: Vec<CatalogItem> = load_catalog();
+}
+
+//An item that can be traded in the market. New vendors receive `count` units,
+//or `item_count` if unset, priced between `min_price` and `max_price`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CatalogItem {
+    pub name: String,
+    #[serde(default)]
+    pub category: String,
+    #[serde(default)]
+    pub min_price: f64,
+    #[serde(default)]
+    pub max_price: f64,
+    pub count: Option<u32>
+}
+
+impl CatalogItem {
+    /// Gets the number of units of this item given to new vendors
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`        - The current catalog item
+    /// * `item_count`  - The number of units given when the item sets none
+    pub fn initial_count(&self, item_count: u32) -> u32 { self.count.unwrap_or(item_count) }
+
+    /// Gets a random starting price for this item within its price range
+    /// 
+    /// # Arguments
+    /// 
+    /// * `self`    - The current catalog item
+    /// * `rng`     - The random number generator of the market
+    pub fn initial_price<R: Rng>(&self, rng: &mut R) -> f64 {
+        if self.max_price <= self.min_price { return self.min_price.max(0.0); }
+        rng.gen_range(self.min_price.max(0.0), self.max_price)
+    }
+}
+
+/// Returns a random catchphrase from a static list. Catchphrases are only
+/// shown on pages, so they come from an unseeded generator and leave the
+/// random draws of every market alone
 pub fn catchphrase_generator() -> String {
     let mut rng = rand::thread_rng();
     format!("{}", CATCHPHRASE[rng.gen_range(0, CATCHPHRASE.len())])
@@ -38,6 +94,38 @@ pub fn construct_json(values: &BTreeMap<String, Box<dyn Display>>) -> content::J
     content::Json(format_string)
 }
 
+/// Builds the default catalog from the primitive Rust types, grouped by kind
+fn default_catalog() -> Vec<CatalogItem> {
+    RUST_TYPES.iter().map(|&t| CatalogItem {
+        name: t.to_string(),
+        category: match t.chars().next() {
+            Some('u') => "unsigned",
+            Some('i') => "signed",
+            Some('f') => "float",
+            _ if t == "str" || t == "char" => "text",
+            _ => "special"
+        }.to_string(),
+        min_price: 0.0,
+        max_price: 0.0,
+        count: None
+    }).collect()
+}
+
+/// Loads the item catalog from the `[[catalog]]` tables of the startup
+/// scenario named by `scenario_file`, the file named by `catalog_file`, or
+/// from `Config.toml` itself, falling back to the default catalog of Rust types
+fn load_catalog() -> Vec<CatalogItem> {
+    let read = |key: &str| super::get_config::<String>(key).and_then(|path| {
+        let mut options = config::Config::default();
+        options.merge(config::File::with_name(&path)).ok()?;
+        options.get::<Vec<CatalogItem>>("catalog").ok()
+    });
+    match read("scenario_file").or_else(|| read("catalog_file")).or_else(|| super::get_config::<Vec<CatalogItem>>("catalog")) {
+        Some(catalog) if !catalog.is_empty() => catalog,
+        _ => default_catalog()
+    }
+}
+
 /// Converts minimal HashMap of prices (only containing items currently tracked
 /// by the ledger) into an ordered vector containing zeros in place of any
 /// untracked items
@@ -46,35 +134,137 @@ pub fn construct_json(values: &BTreeMap<String, Box<dyn Display>>) -> content::J
 /// 
 /// * `avg_prices`  - A mapping from item to average price
 pub fn convert_minimal_to_full(avg_prices: HashMap<String, f64>) -> Vec<f64> {
-    let mut vec_prices = vec![0.0; RUST_TYPES.len()];
-    for (i, t) in RUST_TYPES.iter().enumerate() {
-        if avg_prices.contains_key(&t.to_string()){
-            vec_prices[i] = avg_prices[&t.to_string()];
+    let mut vec_prices = vec![0.0; CATALOG.len()];
+    for (i, t) in CATALOG.iter().enumerate() {
+        if avg_prices.contains_key(&t.name){
+            vec_prices[i] = avg_prices[&t.name];
         }
     }
     vec_prices
 }
 
-/// Gets the index of an item according to the RUST_TYPES static vector
+/// Gets a random set of items from the catalog. If count is zero, returns
+/// copy of the whole catalog
+/// 
+/// # Arguments
+/// 
+/// * `count`   - The number of items to return, use 0 for full catalog
+/// * `rng`     - The random number generator of the market
+pub fn get_catalog<R: Rng>(count: usize, rng: &mut R) -> Vec<CatalogItem> {
+    if count == 0 { return CATALOG.to_vec(); }
+    CATALOG.choose_multiple(rng, count).cloned().collect()
+}
+
+/// Groups the names of the items in the catalog by category, items without a
+/// category are grouped under `other`
+pub fn get_categories() -> BTreeMap<String, Vec<String>> {
+    let mut categories: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for item in CATALOG.iter() {
+        let category = if item.category.is_empty() { "other".to_string() } else { item.category.clone() };
+        categories.entry(category).or_default().push(item.name.clone());
+    }
+    categories
+}
+
+/// Gets the index of an item according to the item catalog
 /// 
 /// # Arguments
 /// 
 /// * `t`   - The item name in question
-pub fn get_rust_type_index(t: String) -> usize { RUST_TYPES.iter().position(|&x| x == &*t).unwrap() }
+pub fn get_rust_type_index(t: String) -> usize { CATALOG.iter().position(|x| x.name == t).unwrap() }
 
-/// Gets a random set of types. If count is zero, returns copy of types list
+/// Gets the names of every item in the catalog. Random items for a market
+/// come from `get_catalog` with the random number generator of the market
+pub fn get_rust_types() -> Vec<&'static str> { CATALOG.iter().map(|x| x.name.as_str()).collect() }
+
+/// Returns true if the config of a market lists the running catalog or none,
+/// since the catalog is shared by every market and only set at startup
 /// 
 /// # Arguments
 /// 
-/// * `count`   - The number of types to return, use 0 for full list
-pub fn get_rust_types(count: usize) -> Vec<&'static str> { 
-    if count == 0 { return RUST_TYPES.to_vec(); }
-    let mut rng = rand::thread_rng();
-    RUST_TYPES.choose_multiple(&mut rng, count).cloned().collect()
+/// * `config`  - The settings of the market
+pub fn keeps_catalog(config: &config::Config) -> bool {
+    match config.get::<Vec<CatalogItem>>("catalog") {
+        Ok(catalog) => catalog.iter().map(|c| c.name.as_str()).eq(CATALOG.iter().map(|c| c.name.as_str())),
+        Err(config::ConfigError::NotFound(_)) => true,
+        Err(_) => false
+    }
 }
 
 /// Generates a random name using the static name lists
-pub fn name_generator() -> String {
-    let mut rng = rand::thread_rng();
+/// 
+/// # Arguments
+/// 
+/// * `rng` - The random number generator of the market
+pub fn name_generator<R: Rng>(rng: &mut R) -> String {
     format!("{} {}", ADJECTIVES[rng.gen_range(0, ADJECTIVES.len())], OCCUPATION[rng.gen_range(0, OCCUPATION.len())])
-}
\ No newline at end of file
+}
+
+/// Creates a random number generator from a seed, so that everything drawn
+/// from it repeats between runs, or from system entropy if there is no seed
+/// 
+/// # Arguments
+/// 
+/// * `seed`    - The optional `seed` from the config
+pub fn seeded_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(s) => StdRng::seed_from_u64(s),
+        None => StdRng::from_entropy()
+    }
+}
+
+/// Verifies the form, the vendor's uuid and that the vendor may trade, then
+/// runs a trading operation with the vendor's location and returns its output
+/// 
+/// # Arguments
+/// 
+/// * `form`        - The submitted form
+/// * `ledger`      - The current ledger state
+/// * `operation`   - The operation, returning the output variables
+pub fn vendor_action<T, F>(form: Result<Form<T>, FormError<'_>>, ledger: MarketLedger, operation: F) -> content::Json<String>
+where T: HasUuid, F: FnOnce(&mut Ledger, usize, T) -> BTreeMap<String, Box<dyn Display>> {
+    vendor_call(form, ledger, Call::Other, authorization::check_trading, |ledger, vendor_id, form| construct_json(&operation(ledger, vendor_id, form)))
+}
+
+/// Verifies the form, admits the call against the limits and costs of the
+/// vendor, verifies the vendor's uuid and runs a check on the vendor, then
+/// runs an operation with the vendor's location and returns its response.
+/// Spectator tokens are refused, they can only read
+/// 
+/// # Arguments
+/// 
+/// * `form`        - The submitted form
+/// * `ledger`      - The current ledger state
+/// * `call`        - The kind of call, deciding its cost
+/// * `check`       - The check the vendor has to pass, such as `check_trading`
+/// * `operation`   - The operation, returning the response
+pub fn vendor_call<T, F>(form: Result<Form<T>, FormError<'_>>, ledger: MarketLedger, call: Call, check: VendorCheck, operation: F) -> content::Json<String>
+where T: HasUuid, F: FnOnce(&mut Ledger, usize, T) -> content::Json<String> {
+    let mut output_vars: BTreeMap<String, Box<dyn Display>> = BTreeMap::new();
+    let form = match form {
+        Ok(f) => f.into_inner(),
+        Err(_) => {
+            output_vars.insert("Format".to_string(), Box::new("incorrect"));
+            return construct_json(&output_vars);
+        }
+    };
+
+    if let Err(limited) = limits::admit(ledger.inner(), form.uuid(), call) {
+        return construct_json(&limited);
+    }
+    let arc_ledger = ledger.inner().session_ledger.clone();
+    let mut ledger = arc_ledger.write().unwrap();
+    let vendor_id = match ledger.verify_uuid(form.uuid().to_string()) {
+        Ok(id) => id,
+        Err(_) if ledger.is_spectator(form.uuid()) => return construct_json(&authorization::reject_spectator()),
+        Err(_) => {
+            output_vars.insert("success".to_string(), Box::new(false));
+            output_vars.insert("UUID".to_string(), Box::new("not recognized".to_string()));
+            return construct_json(&output_vars);
+        }
+    };
+    if let Err(refused) = check(&ledger, vendor_id) {
+        return construct_json(&refused);
+    }
+    operation(&mut ledger, vendor_id, form)
+}
@@ -1,4 +1,4 @@
-function renderData(name, data) {
+function renderData(name, data, measure = 'average price') {
     var datum = [
         {
             line: { color: '#00F000'},
@@ -18,7 +18,7 @@ function renderData(name, data) {
             font: {
                 size: 24
             },
-            text: name.concat(' average price over time')
+            text: name.concat(' ', measure, ' over time')
         },
         xaxis: {
             gridcolor: '#AAA',
@@ -28,8 +28,8 @@ function renderData(name, data) {
         },
         yaxis: { 
             gridcolor: '#AAA',
-            title: 'Item Price',
-            range: [0, 10],
+            title: measure === 'average price' ? 'Item Price' : 'Index',
+            range: measure === 'average price' ? [0, 10] : undefined,
             tickcolor: '#AAA'
         }
     };
@@ -0,0 +1,56 @@
+var replayItem = null;
+var replayTimer = null;
+
+function selectItem(name) {
+    replayItem = name;
+    showVersion(document.getElementById('replay-version').value);
+}
+
+function showVersion(version) {
+    document.getElementById('replay-label').textContent = 'Version '.concat(version);
+    var token = new URLSearchParams(window.location.search).get('token');
+    fetch('api/replay?version='.concat(version, token ? '&token='.concat(encodeURIComponent(token)) : ''))
+        .then(function (response) { return response.json(); })
+        .then(function (replay) {
+            renderData(replayItem, replay.history[replay.types.indexOf(replayItem)]);
+            renderVendors(replay.vendors);
+        });
+}
+
+function renderVendors(vendors) {
+    var container = document.getElementById('replay-vendors');
+    container.innerHTML = '';
+    vendors.forEach(function (vendor) {
+        var card = document.createElement('div');
+        card.className = 'leaderboard';
+        var title = document.createElement('h3');
+        title.textContent = vendor.name.concat(': ', vendor.bits.toFixed(2), ' bits');
+        card.appendChild(title);
+        var list = document.createElement('ul');
+        Object.keys(vendor.items).forEach(function (item) {
+            var entry = document.createElement('li');
+            entry.textContent = item.concat(': ', vendor.items[item], ' units');
+            list.appendChild(entry);
+        });
+        card.appendChild(list);
+        container.appendChild(card);
+    });
+}
+
+function togglePlay() {
+    var slider = document.getElementById('replay-version');
+    var button = document.getElementById('replay-play');
+    if (replayTimer !== null) {
+        clearInterval(replayTimer);
+        replayTimer = null;
+        button.textContent = 'Play';
+        return;
+    }
+    if (Number(slider.value) >= Number(slider.max)) { slider.value = 0; }
+    button.textContent = 'Pause';
+    replayTimer = setInterval(function () {
+        if (Number(slider.value) >= Number(slider.max)) { togglePlay(); return; }
+        slider.value = Number(slider.value) + 1;
+        showVersion(slider.value);
+    }, 250);
+}